@@ -0,0 +1,26 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    #[cfg(feature = "c-header")]
+    generate_c_header();
+}
+
+// Writes the header into OUT_DIR, where the `c-header` test compares it with the committed one.
+#[cfg(feature = "c-header")]
+fn generate_c_header() {
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+    let out_dir = std::env::var("OUT_DIR").unwrap();
+
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+
+    let config = cbindgen::Config::from_file(format!("{crate_dir}/cbindgen.toml")).unwrap();
+
+    // Only the C interface is exported, not the public items of the rest of the crate.
+    cbindgen::Builder::new()
+        .with_config(config)
+        .with_src(format!("{crate_dir}/src/ffi.rs"))
+        .generate()
+        .expect("Unable to generate the C header")
+        .write_to_file(format!("{out_dir}/tiqtaqtoe.h"));
+}
@@ -0,0 +1,44 @@
+// Plays random moves through the C interface until the game is over.
+//
+//     cargo build --release
+//     cc examples/c/random_game.c -Iinclude target/release/libtiqtaqtoe.a -lm -lpthread -ldl \
+//         -o random_game
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "tiqtaqtoe.h"
+
+int main(void) {
+  TqtBoard *board = tqt_board_new(42);
+  TqtMove moves[81];
+
+  srand(42);
+
+  while (!tqt_board_is_game_over(board)) {
+    size_t count = tqt_board_legal_moves(board, moves, 81);
+    TqtMove move = moves[rand() % count];
+
+    TqtStatus status = tqt_board_do_turn(board, move.first, move.second,
+                                         tqt_board_current_token(board));
+    if (status != TQT_STATUS_OK) {
+      fprintf(stderr, "Illegal move: %d\n", status);
+      return 1;
+    }
+
+    tqt_board_collapse_random(board);
+  }
+
+  size_t length = tqt_board_render(board, NULL, 0);
+  char *text = malloc(length + 1);
+  tqt_board_render(board, text, length + 1);
+  printf("%s", text);
+  free(text);
+
+  float score_x, score_o;
+  tqt_board_score(board, &score_x, &score_o);
+  printf("X: %.1f, O: %.1f\n", score_x, score_o);
+
+  tqt_board_free(board);
+  return 0;
+}
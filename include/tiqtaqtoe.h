@@ -0,0 +1,144 @@
+#ifndef TIQTAQTOE_H
+#define TIQTAQTOE_H
+
+/* Generated by cbindgen from src/ffi.rs, see the `c-header` feature in Cargo.toml to update. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef enum TqtToken {
+  TQT_TOKEN_X = 0,
+  TQT_TOKEN_O = 1,
+} TqtToken;
+
+typedef enum TqtStatus {
+  TQT_STATUS_OK = 0,
+  TQT_STATUS_INVALID_POSITION,
+  TQT_STATUS_OCCUPIED,
+  TQT_STATUS_ENTANGLED,
+  TQT_STATUS_WRONG_TURN,
+  TQT_STATUS_COLLAPSE_PENDING,
+  TQT_STATUS_NO_COLLAPSE_PENDING,
+  TQT_STATUS_INVALID_COLLAPSE,
+  TQT_STATUS_GAME_OVER,
+  TQT_STATUS_NULL_POINTER,
+} TqtStatus;
+
+// An opaque handle to a board, with its own RNG for random collapses.
+typedef struct TqtBoard TqtBoard;
+
+typedef struct TqtPosition {
+  uint8_t row;
+  uint8_t column;
+} TqtPosition;
+
+// A move, or a spooky mark, between two positions. Both are the same for a classical move.
+typedef struct TqtMove {
+  struct TqtPosition first;
+  struct TqtPosition second;
+} TqtMove;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Creates an empty board, whose random collapses are driven by the seed.
+// Free it with `tqt_board_free`.
+struct TqtBoard *tqt_board_new(uint64_t seed);
+
+// Copies a board, including the state of its RNG. Returns null if `board` is null.
+//
+// # Safety
+//
+// `board` must be null or a live handle from this library.
+struct TqtBoard *tqt_board_clone(const struct TqtBoard *board);
+
+// # Safety
+//
+// `board` must be null or a handle from this library that isn't used afterwards.
+void tqt_board_free(struct TqtBoard *board);
+
+// The turn number, starting at 1, or 0 if `board` is null.
+//
+// # Safety
+//
+// `board` must be null or a live handle from this library.
+uint8_t tqt_board_turn(const struct TqtBoard *board);
+
+// Whose turn it is. A null `board` gives X as well, so check handles for null beforehand.
+//
+// # Safety
+//
+// `board` must be null or a live handle from this library.
+enum TqtToken tqt_board_current_token(const struct TqtBoard *board);
+
+// Plays a move for `token`, a `TqtToken` passed as an integer, since C can pass any value for
+// an enum. Values other than `TQT_TOKEN_X` and `TQT_TOKEN_O` are refused as the wrong turn.
+//
+// # Safety
+//
+// `board` must be null or a live handle from this library.
+enum TqtStatus tqt_board_do_turn(struct TqtBoard *board,
+                                 struct TqtPosition position_1,
+                                 struct TqtPosition position_2,
+                                 uint8_t token);
+
+// Writes the spooky mark that has to be collapsed into `spooky_mark`, if there is one.
+//
+// # Safety
+//
+// `board` must be null or a live handle from this library, and `spooky_mark` null or writable.
+bool tqt_board_pending_collapse(const struct TqtBoard *board, struct TqtMove *spooky_mark);
+
+// Resolves the pending cycle by putting its spooky mark in the chosen position.
+//
+// # Safety
+//
+// `board` must be null or a live handle from this library.
+enum TqtStatus tqt_board_collapse(struct TqtBoard *board, struct TqtPosition position);
+
+// Resolves the pending cycle by a coin flip from the board's RNG.
+//
+// # Safety
+//
+// `board` must be null or a live handle from this library.
+enum TqtStatus tqt_board_collapse_random(struct TqtBoard *board);
+
+// Writes up to `capacity` legal moves into `moves` and returns how many there are in total.
+//
+// # Safety
+//
+// `board` must be null or a live handle from this library, and `moves` must be null or point
+// to at least `capacity` writable moves.
+size_t tqt_board_legal_moves(const struct TqtBoard *board, struct TqtMove *moves, size_t capacity);
+
+// # Safety
+//
+// `board` must be null or a live handle from this library.
+bool tqt_board_is_game_over(const struct TqtBoard *board);
+
+// Writes the scores of X and O, each 0, 0.5 or 1.
+//
+// # Safety
+//
+// `board` must be null or a live handle from this library, and `score_x` and `score_o` null
+// or writable.
+enum TqtStatus tqt_board_score(const struct TqtBoard *board, float *score_x, float *score_o);
+
+// Renders the board as UTF-8 text into `buffer`, like `snprintf`: at most `capacity` bytes
+// are written, always null terminated, and the length of the whole text is returned.
+//
+// # Safety
+//
+// `board` must be null or a live handle from this library, and `buffer` must be null or point
+// to at least `capacity` writable bytes.
+size_t tqt_board_render(const struct TqtBoard *board, char *buffer, size_t capacity);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TIQTAQTOE_H */
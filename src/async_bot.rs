@@ -0,0 +1,914 @@
+use std::future::{self, Future};
+use std::pin::Pin;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+use crate::board::{Board, Position, SpookyMark, Token};
+use crate::bot::{get_best_collapse, Bot, BotPair, Offer};
+use crate::evaluation::HeuristicEvaluator;
+use crate::game::Rules;
+
+/// The asynchronous counterpart of `Bot`, for players that wait on something else than the
+/// processor to answer, like a remote or human player. `Game::play_whole_game_async` plays many
+/// games with them concurrently on one runtime, where `Bot::get_next_move` would block a
+/// thread for every game.
+///
+/// The methods mean the same as those of `Bot`, and their futures are `Send` so that games can
+/// be spawned on multithreaded runtimes.
+pub trait AsyncBot: Send {
+    fn get_next_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = (Position, Position)> + Send;
+
+    fn get_timed_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+        _budget: Duration,
+    ) -> impl Future<Output = (Position, Position)> + Send {
+        self.get_next_move(board, token)
+    }
+
+    fn get_failure(&mut self, _token: Token) -> Option<String> {
+        None
+    }
+
+    fn choose_collapse(
+        &mut self,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = Position> + Send {
+        future::ready(get_best_collapse(
+            board,
+            token,
+            &HeuristicEvaluator {
+                rules: Rules::PlayerCollapse,
+                ..HeuristicEvaluator::default()
+            },
+        ))
+    }
+
+    fn new_game(&mut self, _rules: Rules, _token: Token) -> impl Future<Output = ()> + Send {
+        future::ready(())
+    }
+
+    fn get_offer(
+        &mut self,
+        _board: &Board,
+        _token: Token,
+    ) -> impl Future<Output = Option<Offer>> + Send {
+        future::ready(None)
+    }
+
+    fn accept_draw(&mut self, _board: &Board, _token: Token) -> impl Future<Output = bool> + Send {
+        future::ready(false)
+    }
+
+    fn notify_move(
+        &mut self,
+        _board: &Board,
+        _token: Token,
+        _positions: (Position, Position),
+    ) -> impl Future<Output = ()> + Send {
+        future::ready(())
+    }
+
+    fn notify_collapse(
+        &mut self,
+        _board: &Board,
+        _token: Token,
+        _spooky_mark: SpookyMark,
+        _position: Position,
+    ) -> impl Future<Output = ()> + Send {
+        future::ready(())
+    }
+
+    fn game_over(
+        &mut self,
+        _board: &Board,
+        _token: Token,
+        _score: (f32, f32),
+    ) -> impl Future<Output = ()> + Send {
+        future::ready(())
+    }
+
+    /// Not a future, unlike the other methods, since it only starts the pondering in the
+    /// background, as in `Bot::start_pondering`.
+    fn start_pondering(&mut self, _board: &Board, _token: Token) {}
+
+    /// Not a future either, since stopping the pondering doesn't wait on anything.
+    fn stop_pondering(&mut self, _token: Token) {}
+}
+
+impl<X, O> AsyncBot for BotPair<X, O>
+where
+    X: AsyncBot,
+    O: AsyncBot,
+{
+    async fn get_next_move(&mut self, board: &Board, token: Token) -> (Position, Position) {
+        match token {
+            Token::X => self.bot_x.get_next_move(board, token).await,
+            Token::O => self.bot_o.get_next_move(board, token).await,
+        }
+    }
+
+    async fn get_timed_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+        budget: Duration,
+    ) -> (Position, Position) {
+        match token {
+            Token::X => self.bot_x.get_timed_move(board, token, budget).await,
+            Token::O => self.bot_o.get_timed_move(board, token, budget).await,
+        }
+    }
+
+    fn get_failure(&mut self, token: Token) -> Option<String> {
+        match token {
+            Token::X => self.bot_x.get_failure(token),
+            Token::O => self.bot_o.get_failure(token),
+        }
+    }
+
+    async fn choose_collapse(&mut self, board: &Board, token: Token) -> Position {
+        match token {
+            Token::X => self.bot_x.choose_collapse(board, token).await,
+            Token::O => self.bot_o.choose_collapse(board, token).await,
+        }
+    }
+
+    async fn new_game(&mut self, rules: Rules, token: Token) {
+        match token {
+            Token::X => self.bot_x.new_game(rules, token).await,
+            Token::O => self.bot_o.new_game(rules, token).await,
+        }
+    }
+
+    async fn get_offer(&mut self, board: &Board, token: Token) -> Option<Offer> {
+        match token {
+            Token::X => self.bot_x.get_offer(board, token).await,
+            Token::O => self.bot_o.get_offer(board, token).await,
+        }
+    }
+
+    async fn accept_draw(&mut self, board: &Board, token: Token) -> bool {
+        match token {
+            Token::X => self.bot_x.accept_draw(board, token).await,
+            Token::O => self.bot_o.accept_draw(board, token).await,
+        }
+    }
+
+    async fn notify_move(&mut self, board: &Board, token: Token, positions: (Position, Position)) {
+        match token {
+            Token::X => self.bot_x.notify_move(board, token, positions).await,
+            Token::O => self.bot_o.notify_move(board, token, positions).await,
+        }
+    }
+
+    async fn notify_collapse(
+        &mut self,
+        board: &Board,
+        token: Token,
+        spooky_mark: SpookyMark,
+        position: Position,
+    ) {
+        match token {
+            Token::X => {
+                self.bot_x
+                    .notify_collapse(board, token, spooky_mark, position)
+                    .await
+            }
+            Token::O => {
+                self.bot_o
+                    .notify_collapse(board, token, spooky_mark, position)
+                    .await
+            }
+        }
+    }
+
+    async fn game_over(&mut self, board: &Board, token: Token, score: (f32, f32)) {
+        match token {
+            Token::X => self.bot_x.game_over(board, token, score).await,
+            Token::O => self.bot_o.game_over(board, token, score).await,
+        }
+    }
+
+    fn start_pondering(&mut self, board: &Board, token: Token) {
+        match token {
+            Token::X => self.bot_x.start_pondering(board, token),
+            Token::O => self.bot_o.start_pondering(board, token),
+        }
+    }
+
+    fn stop_pondering(&mut self, token: Token) {
+        match token {
+            Token::X => self.bot_x.stop_pondering(token),
+            Token::O => self.bot_o.stop_pondering(token),
+        }
+    }
+}
+
+/// Plays a `Bot` as an `AsyncBot`, answering on the thread polling its futures.
+///
+/// Only fit for bots that answer quickly, since the runtime can't run anything else meanwhile.
+/// Slower bots are better played through a `ThreadBot`.
+#[derive(Clone)]
+pub struct SyncBot<B>(pub B)
+where
+    B: Bot + Send;
+
+impl<B> AsyncBot for SyncBot<B>
+where
+    B: Bot + Send,
+{
+    fn get_next_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = (Position, Position)> + Send {
+        future::ready(self.0.get_next_move(board, token))
+    }
+
+    fn get_timed_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+        budget: Duration,
+    ) -> impl Future<Output = (Position, Position)> + Send {
+        future::ready(self.0.get_timed_move(board, token, budget))
+    }
+
+    fn get_failure(&mut self, token: Token) -> Option<String> {
+        self.0.get_failure(token)
+    }
+
+    fn choose_collapse(
+        &mut self,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = Position> + Send {
+        future::ready(self.0.choose_collapse(board, token))
+    }
+
+    fn new_game(&mut self, rules: Rules, token: Token) -> impl Future<Output = ()> + Send {
+        self.0.new_game(rules, token);
+        future::ready(())
+    }
+
+    fn get_offer(
+        &mut self,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = Option<Offer>> + Send {
+        future::ready(self.0.get_offer(board, token))
+    }
+
+    fn accept_draw(&mut self, board: &Board, token: Token) -> impl Future<Output = bool> + Send {
+        future::ready(self.0.accept_draw(board, token))
+    }
+
+    fn notify_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+        positions: (Position, Position),
+    ) -> impl Future<Output = ()> + Send {
+        self.0.notify_move(board, token, positions);
+        future::ready(())
+    }
+
+    fn notify_collapse(
+        &mut self,
+        board: &Board,
+        token: Token,
+        spooky_mark: SpookyMark,
+        position: Position,
+    ) -> impl Future<Output = ()> + Send {
+        self.0.notify_collapse(board, token, spooky_mark, position);
+        future::ready(())
+    }
+
+    fn game_over(
+        &mut self,
+        board: &Board,
+        token: Token,
+        score: (f32, f32),
+    ) -> impl Future<Output = ()> + Send {
+        self.0.game_over(board, token, score);
+        future::ready(())
+    }
+
+    fn start_pondering(&mut self, board: &Board, token: Token) {
+        self.0.start_pondering(board, token)
+    }
+
+    fn stop_pondering(&mut self, token: Token) {
+        self.0.stop_pondering(token)
+    }
+}
+
+// The answer of a `ThreadBot`'s thread, which is dropped without answering if the bot panics.
+enum ReplyState<T> {
+    Waiting(Option<Waker>),
+    Answered(T),
+    Dropped,
+}
+
+// Resolves to the answer, or to `None` if the bot's thread stopped without answering.
+struct Reply<T> {
+    state: Arc<Mutex<ReplyState<T>>>,
+}
+
+struct Answer<T> {
+    state: Arc<Mutex<ReplyState<T>>>,
+}
+
+impl<T> Answer<T> {
+    fn settle(&self, new_state: ReplyState<T>) {
+        let old_state = std::mem::replace(&mut *self.state.lock().unwrap(), new_state);
+
+        if let ReplyState::Waiting(Some(waker)) = old_state {
+            waker.wake();
+        }
+    }
+}
+
+impl<T> Drop for Answer<T> {
+    fn drop(&mut self) {
+        let answered = matches!(*self.state.lock().unwrap(), ReplyState::Answered(_));
+
+        if !answered {
+            self.settle(ReplyState::Dropped);
+        }
+    }
+}
+
+impl<T> Future for Reply<T> {
+    type Output = Option<T>;
+
+    fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Option<T>> {
+        let mut state = self.state.lock().unwrap();
+
+        match std::mem::replace(&mut *state, ReplyState::Dropped) {
+            ReplyState::Answered(answer) => Poll::Ready(Some(answer)),
+            ReplyState::Waiting(_) => {
+                *state = ReplyState::Waiting(Some(context.waker().clone()));
+                Poll::Pending
+            }
+            ReplyState::Dropped => Poll::Ready(None),
+        }
+    }
+}
+
+type Job<B> = Box<dyn FnOnce(&mut B) + Send>;
+
+const THREAD_STOPPED: &str = "the bot's thread stopped without answering";
+
+// What a move or collapse of a bot whose thread stopped is taken to be. `Game` never plays it,
+// since the bot reports a failure.
+const NO_POSITION: Position = Position::new(0, 0);
+
+/// Plays a `Bot` as an `AsyncBot` on a thread of its own, so that a bot that takes its time,
+/// like a search or an external process, doesn't hold up the other games of the runtime.
+///
+/// A bot that panics stops its thread, and reports a failure through `get_failure` from then
+/// on, so that `Game` forfeits it rather than the runtime's task panicking.
+pub struct ThreadBot<B>
+where
+    B: Bot + Send + 'static,
+{
+    jobs: Sender<Job<B>>,
+    failure: Option<String>,
+}
+
+impl<B> ThreadBot<B>
+where
+    B: Bot + Send + 'static,
+{
+    pub fn new(mut bot: B) -> Self {
+        let (jobs, receiver) = mpsc::channel::<Job<B>>();
+
+        // The thread ends once the `ThreadBot` is dropped, closing the channel.
+        thread::spawn(move || {
+            for job in receiver {
+                job(&mut bot);
+            }
+        });
+
+        Self {
+            jobs,
+            failure: None,
+        }
+    }
+
+    // Runs the job on the bot's thread, answering once it's done.
+    fn call<T>(&self, job: impl FnOnce(&mut B) -> T + Send + 'static) -> Reply<T>
+    where
+        T: Send + 'static,
+    {
+        let state = Arc::new(Mutex::new(ReplyState::Waiting(None)));
+        let answer = Answer {
+            state: state.clone(),
+        };
+
+        // A bot whose thread is gone drops the job, and with it the answer.
+        let _ = self.jobs.send(Box::new(move |bot| {
+            let result = job(bot);
+            answer.settle(ReplyState::Answered(result));
+        }));
+
+        Reply { state }
+    }
+
+    // Like `call`, failing for good if the bot's thread stopped.
+    async fn ask<T>(&mut self, job: impl FnOnce(&mut B) -> T + Send + 'static) -> Option<T>
+    where
+        T: Send + 'static,
+    {
+        let answer = self.call(job).await;
+
+        if answer.is_none() {
+            self.failure = Some(THREAD_STOPPED.to_string());
+        }
+
+        answer
+    }
+
+    // A move along with the failure the bot reports for it.
+    async fn get_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+        budget: Option<Duration>,
+    ) -> (Position, Position) {
+        let board = board.clone();
+        let answer = self
+            .ask(move |bot| {
+                let positions = match budget {
+                    Some(budget) => bot.get_timed_move(&board, token, budget),
+                    None => bot.get_next_move(&board, token),
+                };
+
+                (positions, bot.get_failure(token))
+            })
+            .await;
+
+        match answer {
+            Some((positions, failure)) => {
+                self.failure = failure;
+                positions
+            }
+            None => (NO_POSITION, NO_POSITION),
+        }
+    }
+}
+
+impl<B> AsyncBot for ThreadBot<B>
+where
+    B: Bot + Send + 'static,
+{
+    fn get_next_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = (Position, Position)> + Send {
+        self.get_move(board, token, None)
+    }
+
+    fn get_timed_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+        budget: Duration,
+    ) -> impl Future<Output = (Position, Position)> + Send {
+        self.get_move(board, token, Some(budget))
+    }
+
+    fn get_failure(&mut self, _token: Token) -> Option<String> {
+        self.failure.clone()
+    }
+
+    async fn choose_collapse(&mut self, board: &Board, token: Token) -> Position {
+        let board = board.clone();
+        let answer = self
+            .ask(move |bot| {
+                let position = bot.choose_collapse(&board, token);
+                (position, bot.get_failure(token))
+            })
+            .await;
+
+        match answer {
+            Some((position, failure)) => {
+                self.failure = failure;
+                position
+            }
+            None => NO_POSITION,
+        }
+    }
+
+    async fn new_game(&mut self, rules: Rules, token: Token) {
+        self.ask(move |bot| bot.new_game(rules, token)).await;
+    }
+
+    async fn get_offer(&mut self, board: &Board, token: Token) -> Option<Offer> {
+        let board = board.clone();
+        self.ask(move |bot| bot.get_offer(&board, token))
+            .await
+            .flatten()
+    }
+
+    async fn accept_draw(&mut self, board: &Board, token: Token) -> bool {
+        let board = board.clone();
+        self.ask(move |bot| bot.accept_draw(&board, token))
+            .await
+            .unwrap_or(false)
+    }
+
+    async fn notify_move(&mut self, board: &Board, token: Token, positions: (Position, Position)) {
+        let board = board.clone();
+        self.ask(move |bot| bot.notify_move(&board, token, positions))
+            .await;
+    }
+
+    async fn notify_collapse(
+        &mut self,
+        board: &Board,
+        token: Token,
+        spooky_mark: SpookyMark,
+        position: Position,
+    ) {
+        let board = board.clone();
+        self.ask(move |bot| bot.notify_collapse(&board, token, spooky_mark, position))
+            .await;
+    }
+
+    async fn game_over(&mut self, board: &Board, token: Token, score: (f32, f32)) {
+        let board = board.clone();
+        self.ask(move |bot| bot.game_over(&board, token, score))
+            .await;
+    }
+
+    // The bot's thread runs its jobs in order, so there's no need to wait for these.
+    fn start_pondering(&mut self, board: &Board, token: Token) {
+        let board = board.clone();
+        self.call(move |bot| bot.start_pondering(&board, token));
+    }
+
+    fn stop_pondering(&mut self, token: Token) {
+        self.call(move |bot| bot.stop_pondering(token));
+    }
+}
+
+// Wakes the task waiting on a `Timeout` once its time is up, on a thread of its own.
+struct Timer {
+    state: Arc<Mutex<TimerState>>,
+    thread: Thread,
+}
+
+struct TimerState {
+    waker: Waker,
+    cancelled: bool,
+}
+
+impl Timer {
+    fn start(deadline: Instant, waker: Waker) -> Self {
+        let state = Arc::new(Mutex::new(TimerState {
+            waker,
+            cancelled: false,
+        }));
+        let thread_state = state.clone();
+
+        let handle = thread::spawn(move || loop {
+            let now = Instant::now();
+            let state = thread_state.lock().unwrap();
+
+            if state.cancelled {
+                return;
+            }
+            if now >= deadline {
+                state.waker.wake_by_ref();
+                return;
+            }
+
+            drop(state);
+            thread::park_timeout(deadline - now);
+        });
+
+        Self {
+            state,
+            thread: handle.thread().clone(),
+        }
+    }
+}
+
+/// The output of the future, or `None` if the time limit passes first. Doesn't depend on the
+/// timers of a runtime, so that `Game` keeps its clocks on any of them.
+pub(crate) struct Timeout<F>
+where
+    F: Future,
+{
+    future: Pin<Box<F>>,
+    deadline: Instant,
+    timer: Option<Timer>,
+}
+
+pub(crate) fn timeout<F>(future: F, limit: Duration) -> Timeout<F>
+where
+    F: Future,
+{
+    Timeout {
+        future: Box::pin(future),
+        deadline: Instant::now() + limit,
+        timer: None,
+    }
+}
+
+impl<F> Future for Timeout<F>
+where
+    F: Future,
+{
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, context: &mut Context) -> Poll<Option<F::Output>> {
+        if let Poll::Ready(output) = self.future.as_mut().poll(context) {
+            return Poll::Ready(Some(output));
+        }
+
+        if Instant::now() >= self.deadline {
+            return Poll::Ready(None);
+        }
+
+        match &self.timer {
+            Some(timer) => timer.state.lock().unwrap().waker = context.waker().clone(),
+            None => self.timer = Some(Timer::start(self.deadline, context.waker().clone())),
+        }
+
+        Poll::Pending
+    }
+}
+
+impl<F> Drop for Timeout<F>
+where
+    F: Future,
+{
+    fn drop(&mut self) {
+        if let Some(timer) = &self.timer {
+            timer.state.lock().unwrap().cancelled = true;
+            timer.thread.unpark();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_async_bot {
+    use super::*;
+
+    use std::sync::mpsc::Receiver;
+    use std::task::Wake;
+    use std::thread::Thread;
+
+    use crate::bot::RandomBot;
+    use crate::game::{Forfeit, Game, GameRecord, TimeControl};
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    // Polls all the futures on this thread until they are all done, as a runtime would.
+    fn run_all<F>(futures: Vec<F>) -> Vec<F::Output>
+    where
+        F: Future,
+    {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut futures = futures.into_iter().map(Box::pin).collect::<Vec<_>>();
+        let mut outputs = futures.iter().map(|_| None).collect::<Vec<_>>();
+
+        while outputs.iter().any(Option::is_none) {
+            for (future, output) in futures.iter_mut().zip(&mut outputs) {
+                if output.is_none() {
+                    if let Poll::Ready(result) = future.as_mut().poll(&mut context) {
+                        *output = Some(result);
+                    }
+                }
+            }
+
+            if outputs.iter().any(Option::is_none) {
+                thread::park_timeout(Duration::from_millis(10));
+            }
+        }
+
+        outputs.into_iter().map(Option::unwrap).collect()
+    }
+
+    type Request = (Board, Token, Sender<(Position, Position)>);
+
+    // A remote player, whose moves come from another thread through a channel.
+    struct RemoteBot {
+        requests: Sender<Request>,
+    }
+
+    impl RemoteBot {
+        fn new(seed: u64) -> Self {
+            let (requests, receiver) = mpsc::channel::<Request>();
+
+            thread::spawn(move || {
+                let mut bot = RandomBot::new(seed);
+                for (board, token, answer) in receiver {
+                    thread::sleep(Duration::from_millis(1));
+                    let _ = answer.send(bot.get_next_move(&board, token));
+                }
+            });
+
+            Self { requests }
+        }
+    }
+
+    // Waits for the answer on a channel without blocking the thread polling it.
+    struct Answer(Receiver<(Position, Position)>);
+
+    impl Future for Answer {
+        type Output = (Position, Position);
+
+        fn poll(self: Pin<&mut Self>, context: &mut Context) -> Poll<Self::Output> {
+            match self.0.try_recv() {
+                Ok(positions) => Poll::Ready(positions),
+                Err(_) => {
+                    // Good enough for a test: the runtime looks again soon.
+                    context.waker().wake_by_ref();
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    impl AsyncBot for RemoteBot {
+        fn get_next_move(
+            &mut self,
+            board: &Board,
+            token: Token,
+        ) -> impl Future<Output = (Position, Position)> + Send {
+            let (sender, receiver) = mpsc::channel();
+            self.requests.send((board.clone(), token, sender)).unwrap();
+
+            Answer(receiver)
+        }
+    }
+
+    fn assert_send<T>(_: &T)
+    where
+        T: Send,
+    {
+    }
+
+    fn play_sync_game(seed: u64) -> GameRecord {
+        let bots = BotPair {
+            bot_x: RandomBot::new(2 * seed),
+            bot_o: RandomBot::new(2 * seed + 1),
+        };
+        let mut game = Game::new(bots, seed);
+        game.set_verbose(false);
+        game.play_whole_game();
+
+        game.get_record()
+    }
+
+    #[test]
+    fn test_concurrent_games() {
+        let mut games = (0..8)
+            .map(|seed| {
+                let bots = BotPair {
+                    bot_x: RemoteBot::new(2 * seed),
+                    bot_o: ThreadBot::new(RandomBot::new(2 * seed + 1)),
+                };
+                let mut game = Game::new(bots, seed);
+                game.set_verbose(false);
+                game
+            })
+            .collect::<Vec<_>>();
+
+        run_all(
+            games
+                .iter_mut()
+                .map(|game| game.play_whole_game_async())
+                .collect(),
+        );
+
+        // The games are played the same way as by the blocking driver.
+        for (seed, game) in games.iter().enumerate() {
+            assert!(game.is_game_over());
+            assert_eq!(play_sync_game(seed as u64), game.get_record());
+        }
+    }
+
+    // Plays at random, until it panics on the given turn.
+    struct PanickingBot {
+        bot: RandomBot,
+        turn: u8,
+    }
+
+    impl Bot for PanickingBot {
+        fn get_next_move(&mut self, board: &Board, token: Token) -> (Position, Position) {
+            assert!(board.turn < self.turn, "Panicking on purpose");
+            self.bot.get_next_move(board, token)
+        }
+    }
+
+    #[test]
+    fn test_thread_bot_panic_forfeits() {
+        let bots = BotPair {
+            bot_x: ThreadBot::new(PanickingBot {
+                bot: RandomBot::new(0),
+                turn: 3,
+            }),
+            bot_o: SyncBot(RandomBot::new(1)),
+        };
+        let mut game = Game::new(bots, 0);
+        game.set_verbose(false);
+
+        run_all(vec![game.play_whole_game_async()]);
+
+        assert_eq!(
+            Some(&(Token::X, Forfeit::Failure(THREAD_STOPPED.to_string()))),
+            game.get_forfeit()
+        );
+        assert_eq!(2, game.get_record().turns.len());
+    }
+
+    #[test]
+    fn test_timeout_wakes_the_task() {
+        let waker = Waker::from(Arc::new(ThreadWaker(thread::current())));
+        let mut context = Context::from_waker(&waker);
+        let mut future = Box::pin(timeout(future::pending::<()>(), Duration::from_millis(20)));
+        let start = Instant::now();
+
+        while future.as_mut().poll(&mut context).is_pending() {
+            thread::park_timeout(Duration::from_secs(5));
+            assert!(
+                start.elapsed() < Duration::from_secs(5),
+                "The timer never woke the task"
+            );
+        }
+
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    // A remote player who went away.
+    struct SilentBot;
+
+    impl AsyncBot for SilentBot {
+        fn get_next_move(
+            &mut self,
+            _board: &Board,
+            _token: Token,
+        ) -> impl Future<Output = (Position, Position)> + Send {
+            future::pending()
+        }
+    }
+
+    #[test]
+    fn test_silent_bot_runs_out_of_time() {
+        let bots = BotPair {
+            bot_x: SyncBot(RandomBot::new(0)),
+            bot_o: SilentBot,
+        };
+        let mut game = Game::new(bots, 0);
+        game.set_verbose(false);
+        game.set_time_control(TimeControl::Clock {
+            initial: Duration::from_millis(50),
+            increment: Duration::ZERO,
+        });
+
+        run_all(vec![game.play_whole_game_async()]);
+
+        assert_eq!(Some(Token::O), game.get_time_forfeit());
+        assert_eq!(Some(Duration::ZERO), game.get_clock(Token::O));
+        assert_eq!(1, game.get_record().turns.len());
+    }
+
+    #[test]
+    fn test_sync_bot_adapter() {
+        let bots = BotPair {
+            bot_x: SyncBot(RandomBot::new(6)),
+            bot_o: SyncBot(RandomBot::new(7)),
+        };
+        let mut game = Game::new(bots, 3);
+        game.set_verbose(false);
+
+        let future = game.play_whole_game_async();
+        assert_send(&future);
+        run_all(vec![future]);
+
+        assert_eq!(play_sync_game(3), game.get_record());
+    }
+}
@@ -0,0 +1,62 @@
+use std::env;
+
+use tiqtaqtoe::board::Board;
+use tiqtaqtoe::book::OpeningBook;
+use tiqtaqtoe::bot::{BotPair, RandomBot};
+use tiqtaqtoe::game::{Game, Rules};
+use tiqtaqtoe::solver::Solver;
+
+const USAGE: &str = "Usage:
+  book search <rules> <plies> <output>
+  book self-play <games> <plies> <min games> <output>";
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let args = args.iter().map(String::as_str).collect::<Vec<_>>();
+
+    let (book, output) = match args[..] {
+        ["search", rules, plies, output] => {
+            let rules: Rules = serde_json::from_value(rules.into())?;
+            let mut solver = Solver::new(rules);
+
+            (
+                OpeningBook::from_search(&mut solver, &Board::new(), plies.parse()?),
+                output,
+            )
+        }
+        ["self-play", games, plies, min_games, output] => {
+            let records = (0..games.parse::<u64>()?)
+                .map(|seed| {
+                    let bots = BotPair {
+                        bot_x: RandomBot::new(2 * seed),
+                        bot_o: RandomBot::new(2 * seed + 1),
+                    };
+                    let mut game = Game::new(bots, seed);
+                    game.set_verbose(false);
+                    game.play_whole_game();
+
+                    game.get_record()
+                })
+                .collect::<Vec<_>>();
+
+            (
+                OpeningBook::from_games(
+                    Rules::RandomCollapse,
+                    &records,
+                    plies.parse()?,
+                    min_games.parse()?,
+                ),
+                output,
+            )
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(1);
+        }
+    };
+
+    book.save(output)?;
+    println!("Saved {} positions to {output}", book.len());
+
+    Ok(())
+}
@@ -0,0 +1,37 @@
+use std::env;
+use std::time::Instant;
+
+use tiqtaqtoe::difficulty::calibrate_levels;
+use tiqtaqtoe::simulation::get_available_threads;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let (games, threads) = match &args[..] {
+        [games] => (games.parse()?, get_available_threads()),
+        [games, threads] => (games.parse()?, threads.parse()?),
+        _ => {
+            eprintln!("Usage: calibrate <games per level> [threads]");
+            std::process::exit(1);
+        }
+    };
+
+    let start = Instant::now();
+
+    println!("| Level | Opponent | Wins | Losses |");
+    println!("| --- | --- | --- | --- |");
+
+    for calibration in calibrate_levels(games, threads, 0) {
+        println!(
+            "| {} | {} | {:.0}% | {:.0}% |",
+            calibration.level.get_name(),
+            calibration.opponent.get_name(),
+            100.0 * calibration.get_win_rate(),
+            100.0 * calibration.get_loss_rate(),
+        );
+    }
+
+    eprintln!("{games} games per level in {:.2?}", start.elapsed());
+
+    Ok(())
+}
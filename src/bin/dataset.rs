@@ -0,0 +1,38 @@
+use std::env;
+
+use tiqtaqtoe::bot::{create_bot, get_bot_names, BotPair};
+use tiqtaqtoe::dataset::Dataset;
+use tiqtaqtoe::simulation::{get_available_threads, simulate_games};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let [bot_x, bot_o, games, csv_output, npy_output] = &args[..] else {
+        eprintln!("Usage: dataset <bot x> <bot o> <games> <csv output> <npy output directory>");
+        std::process::exit(1);
+    };
+
+    if create_bot(bot_x, 0).is_none() || create_bot(bot_o, 0).is_none() {
+        eprintln!(
+            "Unknown bot, expected one of {}",
+            get_bot_names().join(", ")
+        );
+        std::process::exit(1);
+    }
+
+    let records = simulate_games(games.parse()?, get_available_threads(), 0, |seed| BotPair {
+        bot_x: create_bot(bot_x, seed).unwrap(),
+        bot_o: create_bot(bot_o, seed.wrapping_add(1)).unwrap(),
+    });
+
+    let mut dataset = Dataset::new();
+    for record in &records {
+        dataset.add_game(record);
+    }
+
+    dataset.save_csv(csv_output)?;
+    dataset.save_npy(npy_output)?;
+    println!("Saved {} distinct positions", dataset.len());
+
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::env;
+use std::io;
+use std::time::Duration;
+
+use tiqtaqtoe::bot::{create_bot, get_bot_names, Bot};
+use tiqtaqtoe::evaluation::HeuristicEvaluator;
+use tiqtaqtoe::mcts::{MctsBot, RolloutGuide};
+use tiqtaqtoe::protocol::Engine;
+use tiqtaqtoe::search::SearchBot;
+
+fn main() -> io::Result<()> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    let name = args.first().map(String::as_str).unwrap_or("search");
+
+    let bot: Box<dyn Bot> = match name {
+        "search" => Box::new(SearchBot::new(
+            HeuristicEvaluator::default(),
+            Duration::from_secs(1),
+        )),
+        "mcts" => Box::new(MctsBot::new(RolloutGuide::new(0), 1000, 0)),
+        _ => match create_bot(name, 0) {
+            Some(bot) => bot,
+            None => {
+                let names = ["search", "mcts"].into_iter().chain(get_bot_names());
+                eprintln!("Usage: engine [{}]", names.collect::<Vec<_>>().join(" | "));
+                std::process::exit(1);
+            }
+        },
+    };
+
+    Engine::new(bot, &format!("tiqtaqtoe {name}")).run(io::stdin().lock(), io::stdout().lock())
+}
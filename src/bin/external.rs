@@ -0,0 +1,61 @@
+use std::env;
+use std::process::Command;
+
+use tiqtaqtoe::bot::{create_bot, get_bot_names, Bot, BotPair};
+use tiqtaqtoe::game::Game;
+use tiqtaqtoe::process::ProcessBot;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let (bot, games, program, program_args) = match &args[..] {
+        [bot, games, program, program_args @ ..] => (bot, games.parse()?, program, program_args),
+        _ => {
+            eprintln!("Usage: external <bot> <games> <engine command> [engine arguments]");
+            std::process::exit(1);
+        }
+    };
+
+    if create_bot(bot, 0).is_none() {
+        eprintln!(
+            "Unknown bot, expected one of {}",
+            get_bot_names().join(", ")
+        );
+        std::process::exit(1);
+    }
+
+    let mut engine_points = 0.0;
+
+    for seed in 0..games {
+        let mut command = Command::new(program);
+        command.args(program_args);
+        let mut engine = ProcessBot::spawn(command)?;
+        engine.set_echo(true);
+
+        let engine: Box<dyn Bot> = Box::new(engine);
+        let bot: Box<dyn Bot> = create_bot(bot, seed).unwrap();
+
+        // The engine plays X in even games and O in odd ones.
+        let engine_plays_x = seed % 2 == 0;
+        let (bot_x, bot_o) = if engine_plays_x {
+            (engine, bot)
+        } else {
+            (bot, engine)
+        };
+
+        let mut game = Game::new(BotPair { bot_x, bot_o }, seed);
+        game.set_verbose(false);
+        game.play_whole_game();
+
+        let score = game.get_score();
+        engine_points += if engine_plays_x { score.0 } else { score.1 };
+
+        if let Some((token, forfeit)) = game.get_forfeit() {
+            println!("Game {seed}: {token} forfeited: {forfeit}");
+        }
+    }
+
+    println!("The engine scored {engine_points} points in {games} games");
+
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use std::env;
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use tiqtaqtoe::multiplayer::MultiplayerServer;
+
+fn main() -> std::io::Result<()> {
+    let address = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8081".to_string());
+    let seed = match env::args().nth(2) {
+        Some(seed) => seed.parse().map_err(std::io::Error::other)?,
+        None => rand::random(),
+    };
+
+    let listener = TcpListener::bind(&address)?;
+    println!("Serving quantum tic-tac-toe rooms on ws://{address}");
+
+    Arc::new(MultiplayerServer::new(seed)).serve(listener)
+}
@@ -0,0 +1,37 @@
+use std::env;
+use std::io;
+
+use tiqtaqtoe::network::{Network, SelfPlayConfig};
+
+const HIDDEN_SIZE: usize = 64;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let [iterations, games_per_iteration, output] = &args[..] else {
+        eprintln!("Usage: network <iterations> <games per iteration> <output>");
+        std::process::exit(1);
+    };
+
+    // Keep training a saved network, but never overwrite one that couldn't be read.
+    let mut network = match Network::load(output) {
+        Ok(network) => network,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Network::new(HIDDEN_SIZE, 0),
+        Err(error) => return Err(format!("Unable to load {output}: {error}").into()),
+    };
+
+    let config = SelfPlayConfig {
+        iterations: iterations.parse()?,
+        games_per_iteration: games_per_iteration.parse()?,
+        ..SelfPlayConfig::default()
+    };
+
+    for (iteration, loss) in network.train_by_self_play(&config).iter().enumerate() {
+        println!("Iteration {}: loss {loss}", iteration + 1);
+    }
+
+    network.save(output)?;
+    println!("Saved the network to {output}");
+
+    Ok(())
+}
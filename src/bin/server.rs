@@ -0,0 +1,16 @@
+use std::env;
+use std::net::TcpListener;
+use std::sync::Arc;
+
+use tiqtaqtoe::server::Server;
+
+fn main() -> std::io::Result<()> {
+    let address = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "127.0.0.1:8080".to_string());
+
+    let listener = TcpListener::bind(&address)?;
+    println!("Serving quantum tic-tac-toe on http://{address}");
+
+    Arc::new(Server::new()).serve(listener)
+}
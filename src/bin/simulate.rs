@@ -0,0 +1,44 @@
+use std::env;
+use std::time::Instant;
+
+use tiqtaqtoe::bot::{create_bot, get_bot_names, BotPair};
+use tiqtaqtoe::simulation::{get_available_threads, simulate_games};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let (bot_x, bot_o, games, threads) = match &args[..] {
+        [bot_x, bot_o, games] => (bot_x, bot_o, games, get_available_threads()),
+        [bot_x, bot_o, games, threads] => (bot_x, bot_o, games, threads.parse()?),
+        _ => {
+            eprintln!("Usage: simulate <bot x> <bot o> <games> [threads]");
+            std::process::exit(1);
+        }
+    };
+
+    if create_bot(bot_x, 0).is_none() || create_bot(bot_o, 0).is_none() {
+        eprintln!(
+            "Unknown bot, expected one of {}",
+            get_bot_names().join(", ")
+        );
+        std::process::exit(1);
+    }
+
+    let start = Instant::now();
+    let records = simulate_games(games.parse()?, threads, 0, |seed| BotPair {
+        bot_x: create_bot(bot_x, seed).unwrap(),
+        bot_o: create_bot(bot_o, seed.wrapping_add(1)).unwrap(),
+    });
+
+    let (points_x, points_o) = records.iter().fold((0.0, 0.0), |points, record| {
+        (points.0 + record.score.0, points.1 + record.score.1)
+    });
+
+    println!(
+        "{} games on {threads} threads in {:.2?}: X scored {points_x}, O scored {points_o}",
+        records.len(),
+        start.elapsed()
+    );
+
+    Ok(())
+}
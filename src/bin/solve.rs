@@ -0,0 +1,52 @@
+use std::{env, fs};
+
+use tiqtaqtoe::board::Board;
+use tiqtaqtoe::game::Rules;
+use tiqtaqtoe::solver::{Solver, Step};
+
+/// Solves the empty board, or the board saved as JSON in the given file, under the given rules.
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = env::args().skip(1);
+
+    let rules = args.next().unwrap_or_else(|| "random_collapse".to_string());
+    let rules: Rules = serde_json::from_value(rules.into())?;
+
+    let mut board = match args.next() {
+        Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+        None => Board::new(),
+    };
+
+    let mut solver = Solver::new(rules);
+    let solution = solver.solve(&board);
+
+    println!(
+        "Value for X: {} ({} positions searched)",
+        solution.value,
+        solver.get_nodes()
+    );
+    println!("Principal variation:");
+
+    for step in solution.principal_variation {
+        match step {
+            Step::Move(position_1, position_2) => {
+                let token = board.get_current_token();
+
+                if position_1 == position_2 {
+                    println!("  Classical {token}{} in {position_1}", board.turn);
+                } else {
+                    println!("  {token}{} in {position_1} and {position_2}", board.turn);
+                }
+
+                board.do_turn(position_1, position_2, token);
+            }
+            Step::Collapse(position) => {
+                println!("  Collapse to {position}");
+                board.collapse(position)?;
+            }
+        }
+    }
+
+    println!("{board}");
+
+    Ok(())
+}
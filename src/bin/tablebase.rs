@@ -0,0 +1,21 @@
+use std::env;
+
+use tiqtaqtoe::game::Rules;
+use tiqtaqtoe::tablebase::Tablebase;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let [rules, max_free_squares, output] = &args[..] else {
+        eprintln!("Usage: tablebase <rules> <max free squares> <output>");
+        std::process::exit(1);
+    };
+
+    let rules: Rules = serde_json::from_value(rules.as_str().into())?;
+    let tablebase = Tablebase::generate(rules, max_free_squares.parse()?)?;
+
+    tablebase.save(output)?;
+    println!("Saved {} positions to {output}", tablebase.len());
+
+    Ok(())
+}
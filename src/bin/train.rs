@@ -0,0 +1,36 @@
+use std::env;
+use std::io;
+
+use tiqtaqtoe::game::Rules;
+use tiqtaqtoe::reinforcement::{TrainingConfig, ValueTable};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let [rules, episodes, output] = &args[..] else {
+        eprintln!("Usage: train <rules> <episodes> <output>");
+        std::process::exit(1);
+    };
+
+    let rules: Rules = serde_json::from_value(rules.as_str().into())?;
+
+    // Keep training a saved table, but never overwrite one that couldn't be read.
+    let mut table = match ValueTable::load(output) {
+        Ok(table) if table.rules != rules => {
+            return Err(format!("{output} was trained under {:?} rules", table.rules).into());
+        }
+        Ok(table) => table,
+        Err(error) if error.kind() == io::ErrorKind::NotFound => ValueTable::new(rules),
+        Err(error) => return Err(format!("Unable to load {output}: {error}").into()),
+    };
+
+    table.train(&TrainingConfig {
+        episodes: episodes.parse()?,
+        ..TrainingConfig::default()
+    });
+
+    table.save(output)?;
+    println!("Saved {} positions to {output}", table.len());
+
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use std::env;
+
+use tiqtaqtoe::evaluation::Weights;
+use tiqtaqtoe::tuning::{self, TuningConfig};
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args = env::args().skip(1).collect::<Vec<_>>();
+
+    let (iterations, games_per_match, weights_output, curve_output, start) = match &args[..] {
+        [iterations, games, weights_output, curve_output] => (
+            iterations,
+            games,
+            weights_output,
+            curve_output,
+            Weights::default(),
+        ),
+        [iterations, games, weights_output, curve_output, start] => (
+            iterations,
+            games,
+            weights_output,
+            curve_output,
+            Weights::load(start)?,
+        ),
+        _ => {
+            eprintln!(
+                "Usage: tune <iterations> <games per match> <weights output> <learning curve output> [start weights]"
+            );
+            std::process::exit(1);
+        }
+    };
+
+    let config = TuningConfig {
+        iterations: iterations.parse()?,
+        games_per_match: games_per_match.parse()?,
+        ..TuningConfig::default()
+    };
+    let result = tuning::tune(start, &config);
+
+    result.best.weights.save(weights_output)?;
+    result.save_learning_curve(curve_output)?;
+    println!(
+        "Best weights at iteration {}, scoring {} against the starting weights: {:?}",
+        result.best.iteration, result.best.fitness, result.best.weights
+    );
+
+    Ok(())
+}
@@ -1,11 +1,18 @@
 use std::collections::{HashSet, VecDeque};
 use std::fmt;
 
-use derive_more::Display;
+use arrayvec::ArrayVec;
+use derive_more::{Display, Error};
 use itertools::Itertools;
 use rand::Rng;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Copy, Debug, Display, Eq, Hash, PartialEq)]
+use crate::symmetry::{self, Symmetry};
+
+// Positions are ordered left to right, top to bottom.
+#[derive(
+    Clone, Copy, Debug, Deserialize, Display, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize,
+)]
 #[display("{{ row: {}, column: {} }}", row, column)]
 pub struct Position {
     row: usize,
@@ -13,31 +20,61 @@ pub struct Position {
 }
 
 impl Position {
-    pub fn new(row: usize, column: usize) -> Position {
+    pub const fn new(row: usize, column: usize) -> Position {
         Self { row, column }
     }
 
+    pub fn row(&self) -> usize {
+        self.row
+    }
+
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
     pub fn is_valid(&self) -> bool {
         self.row < BOARD_SIZE && self.column < BOARD_SIZE
     }
-}
 
-// TODO: Consider adding an ordering for position (left to right, top to bottom)
+    /// The index of the position in the board, counting left to right, top to bottom.
+    pub(crate) fn get_index(&self) -> usize {
+        self.row * BOARD_SIZE + self.column
+    }
+}
 
-#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Display, Eq, PartialEq, Serialize)]
 #[repr(u8)] // TODO: Does this actually do anything?
 pub enum Token {
     X,
     O,
 }
 
-#[derive(Clone, Copy, Debug, Display, Eq, PartialEq)]
+impl Token {
+    pub fn get_opponent(self) -> Token {
+        match self {
+            Token::X => Token::O,
+            Token::O => Token::X,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Display, Eq, PartialEq, Serialize)]
 #[repr(u8)] // TODO: Does this actually do anything?
 pub enum TurnToken {
     X(u8),
     O(u8),
 }
 
+impl TurnToken {
+    /// The token followed by its turn subscript, like `X1`.
+    pub fn get_label(&self) -> String {
+        match self {
+            TurnToken::X(turn) => format!("X{turn}"),
+            TurnToken::O(turn) => format!("O{turn}"),
+        }
+    }
+}
+
 impl From<&TurnToken> for Token {
     fn from(token: &TurnToken) -> Self {
         match token {
@@ -47,33 +84,193 @@ impl From<&TurnToken> for Token {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct SpookyMark(pub Position, pub Position, pub TurnToken);
 
-const BOARD_SIZE: usize = 3;
+#[derive(Clone, Copy, Debug, Display, Eq, Error, PartialEq, Serialize)]
+#[serde(tag = "kind", content = "details", rename_all = "snake_case")]
+pub enum MoveError {
+    #[display("Invalid position {_0}!")]
+    InvalidPosition(#[error(not(source))] Position),
+    #[display("Invalid move, position {_0} already occupied!")]
+    Occupied(#[error(not(source))] Position),
+    #[display("Invalid move, position {_0} is entangled and can't take a classical mark!")]
+    Entangled(#[error(not(source))] Position),
+    #[display("Invalid move, it is {_0}'s turn!")]
+    WrongTurn(#[error(not(source))] Token),
+    #[display("Invalid move, a collapse has to be resolved first!")]
+    CollapsePending,
+    #[display("Invalid collapse, there is no cycle to collapse!")]
+    NoCollapsePending,
+    #[display("Invalid collapse, position {_0} is not part of the collapsing spooky mark!")]
+    InvalidCollapse(#[error(not(source))] Position),
+    #[display("Invalid move, the game is over!")]
+    GameOver,
+}
 
+/// A board read from JSON that no game could have reached.
+#[derive(Clone, Copy, Debug, Display, Eq, Error, PartialEq)]
+pub enum BoardError {
+    #[display("Invalid board, turn {_0} doesn't follow the marks on the board!")]
+    InvalidTurn(#[error(not(source))] u8),
+    #[display("Invalid board, mark {} wasn't played on its turn!", _0.get_label())]
+    InvalidMark(#[error(not(source))] TurnToken),
+    #[display("Invalid board, position {_0} is off the board or holds more than one mark!")]
+    InvalidPosition(#[error(not(source))] Position),
+    #[display("Invalid board, a cycle was left open before the last mark!")]
+    OpenCycle,
+}
+
+pub(crate) const BOARD_SIZE: usize = 3;
+const LINES: usize = 2 * BOARD_SIZE + 2;
+/// Every turn places at most one spooky mark, and the game is over after a mark in every square.
+pub const MAX_SPOOKY_MARKS: usize = BOARD_SIZE * BOARD_SIZE;
+
+pub type SpookyMarks = ArrayVec<SpookyMark, MAX_SPOOKY_MARKS>;
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(try_from = "BoardState")]
 pub struct Board {
-    pub positions: Vec<Position>,
-    pub rows_columns_and_diagonals: Vec<[Position; BOARD_SIZE]>,
+    // Fixed-size arrays rather than vectors, so cloning a board doesn't allocate.
+    #[serde(skip, default = "Board::get_positions")]
+    pub positions: [Position; BOARD_SIZE * BOARD_SIZE],
+    #[serde(skip, default = "Board::get_rows_columns_and_diagonals")]
+    pub rows_columns_and_diagonals: [[Position; BOARD_SIZE]; LINES],
     pub board: [[Option<TurnToken>; BOARD_SIZE]; BOARD_SIZE], // the board is only updated on collapses
     pub turn: u8,
-    pub spooky_marks: Vec<SpookyMark>,
+    pub spooky_marks: SpookyMarks,
+    // Zobrist hashes of the board seen through each of `Symmetry::ALL`, kept up to date on
+    // every mark. Writing to the public fields directly leaves them stale.
+    #[serde(skip)]
+    hashes: [u64; 8],
+    // Found again after every mark, since searches ask for it on every node.
+    #[serde(skip)]
+    pending_collapse: Option<SpookyMark>,
+}
+
+// The serialized fields, from which the hashes are recomputed when loading a board.
+#[derive(Clone, Deserialize)]
+struct BoardState {
+    board: [[Option<TurnToken>; BOARD_SIZE]; BOARD_SIZE],
+    turn: u8,
+    spooky_marks: SpookyMarks,
+}
+
+impl Board {
+    // Without the checks of `try_from`, for boards built from marks of real games.
+    fn from_state(state: BoardState) -> Self {
+        let mut board = Self {
+            board: state.board,
+            turn: state.turn,
+            spooky_marks: state.spooky_marks,
+            ..Self::new()
+        };
+        board.hashes = board.compute_hashes();
+        board.pending_collapse = board.find_pending_collapse();
+
+        board
+    }
+}
+
+// Boards read from outside are checked to be reachable in a game, since the hashes and searches
+// rely on it.
+impl TryFrom<BoardState> for Board {
+    type Error = BoardError;
+
+    fn try_from(state: BoardState) -> Result<Self, BoardError> {
+        let turns = BOARD_SIZE * BOARD_SIZE + 1;
+        if !(1..=turns).contains(&(state.turn as usize)) {
+            return Err(BoardError::InvalidTurn(state.turn));
+        }
+
+        // Every turn before the current one left exactly one mark.
+        let mut played = [false; BOARD_SIZE * BOARD_SIZE + 1];
+        let turn_tokens = state.board.iter().flatten().flatten();
+        for turn_token in turn_tokens.chain(state.spooky_marks.iter().map(|m| &m.2)) {
+            let (turn, parity) = match *turn_token {
+                TurnToken::X(turn) => (turn, 1),
+                TurnToken::O(turn) => (turn, 0),
+            };
+
+            if turn == 0 || turn >= state.turn || turn % 2 != parity || played[turn as usize] {
+                return Err(BoardError::InvalidMark(*turn_token));
+            }
+            played[turn as usize] = true;
+        }
+        if played[1..state.turn as usize].contains(&false) {
+            return Err(BoardError::InvalidTurn(state.turn));
+        }
+
+        for SpookyMark(position_1, position_2, _) in &state.spooky_marks {
+            for position in [position_1, position_2] {
+                if !position.is_valid()
+                    || position_1 == position_2
+                    || state.board[position.row][position.column].is_some()
+                {
+                    return Err(BoardError::InvalidPosition(*position));
+                }
+            }
+        }
+
+        // Only the last mark may have closed a cycle, which is then still pending.
+        let last_marks = [TurnToken::X(state.turn - 1), TurnToken::O(state.turn - 1)];
+        let earlier = Board::from_state(BoardState {
+            spooky_marks: state
+                .spooky_marks
+                .iter()
+                .filter(|spooky_mark| !last_marks.contains(&spooky_mark.2))
+                .copied()
+                .collect(),
+            ..state.clone()
+        });
+        if earlier.get_pending_collapse().is_some() {
+            return Err(BoardError::OpenCycle);
+        }
+
+        Ok(Board::from_state(state))
+    }
+}
+
+impl Default for Board {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Board {
     pub fn new() -> Self {
         Self {
-            positions: (0..BOARD_SIZE)
-                .flat_map(|row| (0..BOARD_SIZE).map(move |column| Position::new(row, column)))
-                .collect_vec(),
+            positions: Self::get_positions(),
             rows_columns_and_diagonals: Self::get_rows_columns_and_diagonals(),
             board: [[None; BOARD_SIZE]; BOARD_SIZE],
             turn: 1,
-            spooky_marks: vec![],
+            spooky_marks: SpookyMarks::new(),
+            hashes: [0; 8],
+            pending_collapse: None,
         }
     }
 
-    fn get_rows_columns_and_diagonals() -> Vec<[Position; BOARD_SIZE]> {
+    /// A board with the given marks, as if they had been played in the order of their subscripts.
+    ///
+    /// Panics with more than `MAX_SPOOKY_MARKS` spooky marks.
+    pub fn from_marks(
+        board: [[Option<TurnToken>; BOARD_SIZE]; BOARD_SIZE],
+        spooky_marks: Vec<SpookyMark>,
+    ) -> Self {
+        let marks = board.iter().flatten().flatten().count() + spooky_marks.len();
+
+        Self::from_state(BoardState {
+            board,
+            turn: marks as u8 + 1,
+            spooky_marks: spooky_marks.into_iter().collect(),
+        })
+    }
+
+    fn get_positions() -> [Position; BOARD_SIZE * BOARD_SIZE] {
+        std::array::from_fn(|index| Position::new(index / BOARD_SIZE, index % BOARD_SIZE))
+    }
+
+    fn get_rows_columns_and_diagonals() -> [[Position; BOARD_SIZE]; LINES] {
         // TODO: This method is only correct for BOARD_SIZE <= 4.
         let mut rows_columns_and_diagonals: Vec<[Position; BOARD_SIZE]> = vec![];
 
@@ -113,7 +310,7 @@ impl Board {
                 .unwrap(),
         );
 
-        rows_columns_and_diagonals
+        rows_columns_and_diagonals.try_into().unwrap()
     }
 
     pub fn get_mark(&self, position: Position) -> Option<TurnToken> {
@@ -121,8 +318,6 @@ impl Board {
     }
 
     fn set_mark(&mut self, position: Position, turn_token: TurnToken) {
-        println!("Putting classical turn token {turn_token} in {position}");
-
         if let Some(spot) = self.board[position.row].get_mut(position.column) {
             match spot {
                 Some(_) => panic!("Invalid move, position {position} already occupied!"),
@@ -131,6 +326,10 @@ impl Board {
         } else {
             panic!("I seem to be out of bounds at {position}!")
         }
+
+        for (hash, symmetry) in self.hashes.iter_mut().zip(Symmetry::ALL) {
+            *hash ^= symmetry::get_classical_key(symmetry.apply(position), turn_token);
+        }
     }
 
     fn set_spooky_mark(
@@ -139,19 +338,215 @@ impl Board {
         position_2: Position,
         turn_token: TurnToken,
     ) {
-        println!("Putting {turn_token:?} in {position_1} and {position_2}");
-
         self.spooky_marks
             .push(SpookyMark(position_1, position_2, turn_token));
+        self.toggle_spooky_mark_hashes(SpookyMark(position_1, position_2, turn_token));
+    }
+
+    fn remove_spooky_mark(&mut self, spooky_mark: SpookyMark) {
+        self.spooky_marks.retain(|m| *m != spooky_mark);
+        self.toggle_spooky_mark_hashes(spooky_mark);
+    }
+
+    fn toggle_spooky_mark_hashes(
+        &mut self,
+        SpookyMark(position_1, position_2, turn_token): SpookyMark,
+    ) {
+        for (hash, symmetry) in self.hashes.iter_mut().zip(Symmetry::ALL) {
+            *hash ^= symmetry::get_spooky_key(
+                symmetry.apply(position_1),
+                symmetry.apply(position_2),
+                turn_token,
+            );
+        }
+    }
+
+    fn compute_hashes(&self) -> [u64; 8] {
+        Symmetry::ALL.map(|symmetry| {
+            let classical_keys = self.positions.iter().filter_map(|position| {
+                self.get_mark(*position).map(|turn_token| {
+                    symmetry::get_classical_key(symmetry.apply(*position), turn_token)
+                })
+            });
+            let spooky_keys = self
+                .spooky_marks
+                .iter()
+                .map(|SpookyMark(p1, p2, turn_token)| {
+                    symmetry::get_spooky_key(symmetry.apply(*p1), symmetry.apply(*p2), *turn_token)
+                });
+
+            classical_keys
+                .chain(spooky_keys)
+                .fold(0, |hash, key| hash ^ key)
+        })
+    }
+
+    /// The Zobrist hash of the marks on the board, updated incrementally by every move.
+    ///
+    /// Turn subscripts always run from 1 to the number of turns played, so two boards with the
+    /// same hash also agree on the order their marks were played in.
+    pub fn get_hash(&self) -> u64 {
+        self.hashes[0]
+    }
+
+    /// A hash shared by all boards that are the same up to a symmetry of the square.
+    pub fn get_canonical_hash(&self) -> u64 {
+        self.hashes[self.get_canonical_index()]
+    }
+
+    fn get_canonical_index(&self) -> usize {
+        (0..Symmetry::ALL.len())
+            .min_by_key(|index| self.hashes[*index])
+            .unwrap()
+    }
+
+    /// The symmetry that takes this board to its canonical form, without building it.
+    pub fn get_canonical_symmetry(&self) -> Symmetry {
+        Symmetry::ALL[self.get_canonical_index()]
+    }
+
+    /// The board seen through the given symmetry.
+    pub fn transformed(&self, symmetry: Symmetry) -> Board {
+        let mut board = Board {
+            turn: self.turn,
+            spooky_marks: self
+                .spooky_marks
+                .iter()
+                .map(|SpookyMark(p1, p2, turn_token)| {
+                    SpookyMark(symmetry.apply(*p1), symmetry.apply(*p2), *turn_token)
+                })
+                .collect(),
+            ..Board::new()
+        };
+
+        for position in &self.positions {
+            let transformed = symmetry.apply(*position);
+            board.board[transformed.row][transformed.column] = self.get_mark(*position);
+        }
+
+        board.hashes = board.compute_hashes();
+        board.pending_collapse = board.find_pending_collapse();
+
+        board
+    }
+
+    /// The canonical form of the board, shared by all boards that are the same up to a symmetry
+    /// of the square, along with the symmetry that takes this board to it.
+    ///
+    /// Moves found on the canonical board map back to this one with `symmetry.inverse()`.
+    pub fn canonicalize(&self) -> (Board, Symmetry) {
+        let symmetry = self.get_canonical_symmetry();
+
+        (self.transformed(symmetry), symmetry)
+    }
+
+    pub fn get_current_token(&self) -> Token {
+        if self.turn % 2 == 1 {
+            Token::X
+        } else {
+            Token::O
+        }
+    }
+
+    fn is_entangled(&self, position: Position) -> bool {
+        self.spooky_marks
+            .iter()
+            .any(|SpookyMark(p1, p2, _)| *p1 == position || *p2 == position)
+    }
+
+    pub fn get_legal_moves(&self) -> Vec<(Position, Position)> {
+        let mut legal_moves = vec![];
+
+        for position_1 in &self.positions {
+            if self.get_mark(*position_1).is_some() {
+                continue;
+            }
+
+            for position_2 in &self.positions {
+                if self.get_mark(*position_2).is_some() {
+                    continue;
+                }
+
+                if position_1 == position_2 && self.is_entangled(*position_1) {
+                    continue;
+                }
+
+                legal_moves.push((*position_1, *position_2));
+            }
+        }
+
+        legal_moves
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        if self.get_pending_collapse().is_some() {
+            return false;
+        }
+
+        self.turn as usize > BOARD_SIZE * BOARD_SIZE
+            || self.get_score() != (0.0, 0.0)
+            || !self.has_legal_moves()
+    }
+
+    fn has_legal_moves(&self) -> bool {
+        let mut free_positions = self
+            .positions
+            .iter()
+            .filter(|position| self.get_mark(**position).is_none());
+
+        match (free_positions.next(), free_positions.next()) {
+            (None, _) => false,
+            (Some(position), None) => !self.is_entangled(*position),
+            (Some(_), Some(_)) => true,
+        }
     }
 
     pub fn do_turn(&mut self, position_1: Position, position_2: Position, token: Token) {
-        if !position_1.is_valid() {
-            panic!("Invalid position {position_1}!");
+        if let Err(error) = self.try_do_turn(position_1, position_2, token) {
+            panic!("{error}");
+        }
+    }
+
+    /// Whether any move can be played: not while a collapse is pending, nor once the game is
+    /// over.
+    pub fn check_can_move(&self) -> Result<(), MoveError> {
+        if self.get_pending_collapse().is_some() {
+            return Err(MoveError::CollapsePending);
         }
 
-        if !position_2.is_valid() {
-            panic!("Invalid position {position_2}!");
+        if self.is_game_over() {
+            return Err(MoveError::GameOver);
+        }
+
+        Ok(())
+    }
+
+    pub fn try_do_turn(
+        &mut self,
+        position_1: Position,
+        position_2: Position,
+        token: Token,
+    ) -> Result<(), MoveError> {
+        self.check_can_move()?;
+
+        if token != self.get_current_token() {
+            return Err(MoveError::WrongTurn(self.get_current_token()));
+        }
+
+        for position in [position_1, position_2] {
+            if !position.is_valid() {
+                return Err(MoveError::InvalidPosition(position));
+            }
+        }
+
+        for position in [position_1, position_2] {
+            if self.get_mark(position).is_some() {
+                return Err(MoveError::Occupied(position));
+            }
+        }
+
+        if position_1 == position_2 && self.is_entangled(position_1) {
+            return Err(MoveError::Entangled(position_1));
         }
 
         let turn_token = match token {
@@ -163,9 +558,12 @@ impl Board {
             self.set_mark(position_1, turn_token);
         } else {
             self.set_spooky_mark(position_1, position_2, turn_token);
+            self.pending_collapse = self.find_pending_collapse();
         }
 
         self.turn += 1;
+
+        Ok(())
     }
 
     fn depth_first_search(&self) -> Option<(Position, Position)> {
@@ -186,22 +584,23 @@ impl Board {
             }
         }
 
-        let mut roots: VecDeque<Position> = self.positions.iter().cloned().collect();
-        let mut visited_roots = HashSet::new();
+        // Visited positions are kept in arrays by index, since this runs after every move.
+        let mut visited_roots = [false; BOARD_SIZE * BOARD_SIZE];
+        let mut queue: VecDeque<(Option<Position>, Position)> = VecDeque::new();
 
-        while let Some(root) = roots.pop_front() {
-            if visited_roots.contains(&root) {
+        for root in &self.positions {
+            if visited_roots[root.get_index()] {
                 continue;
             }
 
-            let mut queue: VecDeque<(Option<Position>, Position)> = VecDeque::new();
-            let mut visited: HashSet<Position> = HashSet::new();
+            let mut visited = [false; BOARD_SIZE * BOARD_SIZE];
 
-            queue.push_front((None, root));
+            queue.clear();
+            queue.push_front((None, *root));
 
             while let Some((from, current)) = queue.pop_front() {
-                visited.insert(current);
-                visited_roots.insert(current);
+                visited[current.get_index()] = true;
+                visited_roots[current.get_index()] = true;
 
                 for SpookyMark(position_1, position_2, _) in &self.spooky_marks {
                     let target;
@@ -218,7 +617,7 @@ impl Board {
                         continue;
                     }
 
-                    if from.is_some() && visited.contains(&target) {
+                    if from.is_some() && visited[target.get_index()] {
                         return Some((current, target));
                     }
 
@@ -230,34 +629,57 @@ impl Board {
         None
     }
 
-    pub fn collapse_loop(&mut self) {
-        // Find if there is a loop
-        let loop_edge = self.depth_first_search();
-
-        if loop_edge.is_none() {
-            return;
-        }
+    /// The spooky mark that has to be collapsed to resolve the current cycle, if there is one.
+    pub fn get_pending_collapse(&self) -> Option<SpookyMark> {
+        self.pending_collapse
+    }
 
-        let (start_loop, end_loop) = loop_edge.unwrap();
+    fn find_pending_collapse(&self) -> Option<SpookyMark> {
+        let (start_loop, end_loop) = self.depth_first_search()?;
 
-        // Resolve loop, if there is one, by randomly choosing option.
-        // collapse the first edge
-        let first = *self
-            .spooky_marks
+        self.spooky_marks
             .iter()
             .find(|SpookyMark(p1, p2, _)| {
                 (*p1 == start_loop && *p2 == end_loop) || (*p2 == start_loop && *p1 == end_loop)
             })
-            .unwrap();
+            .cloned()
+    }
+
+    /// Resolves the current cycle, if there is one, by a coin flip from the given RNG.
+    ///
+    /// Returns the collapsed spooky mark and the position it ended up in.
+    pub fn collapse_loop(&mut self, rng: &mut impl Rng) -> Option<(SpookyMark, Position)> {
+        // Find if there is a loop
+        let first = self.get_pending_collapse()?;
 
-        let mut rng = rand::rng();
+        // Resolve loop, if there is one, by randomly choosing option.
         let choice = rng.random_bool(0.5);
 
         let position = if choice { first.0 } else { first.1 };
 
-        self.board[position.row][position.column] = Some(first.2);
+        self.collapse_spooky_mark(first, position);
+
+        Some((first, position))
+    }
+
+    /// Resolves the current cycle by putting the pending spooky mark in the chosen position.
+    pub fn collapse(&mut self, position: Position) -> Result<(), MoveError> {
+        let first = self
+            .get_pending_collapse()
+            .ok_or(MoveError::NoCollapsePending)?;
+
+        if position != first.0 && position != first.1 {
+            return Err(MoveError::InvalidCollapse(position));
+        }
+
+        self.collapse_spooky_mark(first, position);
 
-        self.spooky_marks.retain(|m| *m != first);
+        Ok(())
+    }
+
+    fn collapse_spooky_mark(&mut self, first: SpookyMark, position: Position) {
+        self.remove_spooky_mark(first);
+        self.set_mark(position, first.2);
 
         let mut collapsed_positions: HashSet<Position> = HashSet::new();
         collapsed_positions.insert(position);
@@ -268,18 +690,20 @@ impl Board {
             .cloned()
             .find(|m| collapsed_positions.contains(&m.0) || collapsed_positions.contains(&m.1))
         {
-            self.spooky_marks.retain(|m| *m != to_collapse);
+            self.remove_spooky_mark(to_collapse);
 
             if self.get_mark(to_collapse.0).is_some() {
-                self.board[to_collapse.1.row][to_collapse.1.column] = Some(to_collapse.2);
+                self.set_mark(to_collapse.1, to_collapse.2);
                 collapsed_positions.insert(to_collapse.1);
             } else if self.get_mark(to_collapse.1).is_some() {
-                self.board[to_collapse.0.row][to_collapse.0.column] = Some(to_collapse.2);
+                self.set_mark(to_collapse.0, to_collapse.2);
                 collapsed_positions.insert(to_collapse.0);
             } else {
                 continue;
             }
         }
+
+        self.pending_collapse = self.find_pending_collapse();
     }
 
     fn find_win(&self, token: Token) -> Option<u8> {
@@ -429,6 +853,38 @@ mod test_basic_board_functionality {
         board.do_turn(Position::new(0, 0), Position::new(0, 0), Token::X);
         board.do_turn(Position::new(0, 0), Position::new(0, 0), Token::O);
     }
+
+    #[test]
+    fn test_move_errors() {
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::X);
+
+        assert_eq!(
+            Err(MoveError::WrongTurn(Token::O)),
+            board.try_do_turn(Position::new(0, 1), Position::new(0, 2), Token::X)
+        );
+        assert_eq!(
+            Err(MoveError::Entangled(Position::new(1, 1))),
+            board.try_do_turn(Position::new(1, 1), Position::new(1, 1), Token::O)
+        );
+        assert_eq!(
+            Err(MoveError::InvalidPosition(Position::new(0, 5))),
+            board.try_do_turn(Position::new(0, 1), Position::new(0, 5), Token::O)
+        );
+        assert_eq!(2, board.turn);
+    }
+
+    #[test]
+    fn test_legal_moves() {
+        let mut board = Board::new();
+        assert_eq!(81, board.get_legal_moves().len());
+
+        board.do_turn(Position::new(0, 0), Position::new(0, 0), Token::X);
+        board.do_turn(Position::new(1, 1), Position::new(2, 2), Token::O);
+
+        // 8 free squares, two of which can't take a classical mark.
+        assert_eq!(62, board.get_legal_moves().len());
+    }
 }
 
 #[cfg(test)]
@@ -460,7 +916,7 @@ mod test_searches_and_collapses {
         board.do_turn(position0, position1, Token::X);
         board.do_turn(position1, position0, Token::O);
 
-        board.collapse_loop();
+        board.collapse_loop(&mut rand::rng());
 
         let option1 = board.get_mark(position0) == Some(TurnToken::X(1))
             && board.get_mark(position1) == Some(TurnToken::O(2));
@@ -481,7 +937,7 @@ mod test_searches_and_collapses {
         board.do_turn(position1, position2, Token::O);
         board.do_turn(position2, position0, Token::X);
 
-        board.collapse_loop();
+        board.collapse_loop(&mut rand::rng());
 
         let option1 = board.get_mark(position0) == Some(TurnToken::X(1))
             && board.get_mark(position1) == Some(TurnToken::O(2))
@@ -494,6 +950,34 @@ mod test_searches_and_collapses {
         assert!(option1 || option2);
     }
 
+    #[test]
+    fn test_chosen_collapse() {
+        let position0 = Position::new(0, 0);
+        let position1 = Position::new(1, 1);
+        let position2 = Position::new(2, 2);
+
+        let mut board = Board::new();
+        board.do_turn(position0, position1, Token::X);
+        board.do_turn(position1, position2, Token::O);
+        board.do_turn(position2, position0, Token::X);
+
+        let pending = board.get_pending_collapse().unwrap();
+        assert_eq!(
+            Err(MoveError::CollapsePending),
+            board.try_do_turn(Position::new(0, 1), Position::new(0, 2), Token::O)
+        );
+        assert_eq!(
+            Err(MoveError::InvalidCollapse(Position::new(0, 1))),
+            board.collapse(Position::new(0, 1))
+        );
+
+        board.collapse(pending.0).unwrap();
+
+        assert_eq!(Some(pending.2), board.get_mark(pending.0));
+        assert!(board.spooky_marks.is_empty());
+        assert_eq!(Err(MoveError::NoCollapsePending), board.collapse(pending.1));
+    }
+
     #[test]
     fn test_mixed_loop_size_two() {
         let mut board = create_board(vec![
@@ -503,7 +987,7 @@ mod test_searches_and_collapses {
             ((0, 0), (2, 2)),
         ]);
 
-        board.collapse_loop();
+        board.collapse_loop(&mut rand::rng());
     }
 
     #[test]
@@ -519,7 +1003,7 @@ mod test_searches_and_collapses {
             ((0, 2), (1, 1)),
         ]);
 
-        board.collapse_loop();
+        board.collapse_loop(&mut rand::rng());
 
         let (score_x, score_o) = board.get_score();
         assert!(score_x == 0.0 || score_x == 0.5);
@@ -539,7 +1023,7 @@ mod test_searches_and_collapses {
             ((1, 1), (0, 2)),
         ]);
 
-        board.collapse_loop();
+        board.collapse_loop(&mut rand::rng());
 
         assert_eq!(None, board.get_mark(Position::new(0, 0)));
         assert_eq!(
@@ -573,3 +1057,142 @@ mod test_searches_and_collapses {
         assert_eq!(1.0, score_o);
     }
 }
+
+#[cfg(test)]
+mod test_hashing {
+    use super::*;
+
+    type Move = ((usize, usize), (usize, usize));
+
+    fn play(board: &mut Board, moves: &[Move], symmetry: Symmetry) {
+        for (position_1, position_2) in moves {
+            board.do_turn(
+                symmetry.apply(Position::new(position_1.0, position_1.1)),
+                symmetry.apply(Position::new(position_2.0, position_2.1)),
+                board.get_current_token(),
+            );
+        }
+    }
+
+    #[test]
+    fn test_incremental_hash() {
+        let mut board = Board::new();
+        play(
+            &mut board,
+            &[((0, 0), (0, 1)), ((0, 1), (1, 1)), ((1, 1), (0, 0))],
+            Symmetry::Identity,
+        );
+        assert_eq!(board.compute_hashes(), board.hashes);
+
+        board.collapse(Position::new(0, 0)).unwrap();
+        assert_eq!(board.compute_hashes(), board.hashes);
+        assert_ne!(0, board.get_hash());
+
+        let loaded: Board = serde_json::from_str(&serde_json::to_string(&board).unwrap()).unwrap();
+        assert_eq!(board, loaded);
+    }
+
+    fn load(turn: u8, classical: &str, spooky_marks: &str) -> Result<Board, BoardError> {
+        let mut board = vec![vec!["null".to_string(); BOARD_SIZE]; BOARD_SIZE];
+        if !classical.is_empty() {
+            board[1][1] = classical.to_string();
+        }
+        let board = board
+            .iter()
+            .map(|row| format!("[{}]", row.join(",")))
+            .join(",");
+
+        let json =
+            format!("{{\"board\":[{board}],\"turn\":{turn},\"spooky_marks\":[{spooky_marks}]}}");
+        Board::try_from(serde_json::from_str::<BoardState>(&json).unwrap())
+    }
+
+    fn spooky(position_1: (usize, usize), position_2: (usize, usize), turn_token: &str) -> String {
+        format!(
+            "[{{\"row\":{},\"column\":{}}},{{\"row\":{},\"column\":{}}},{turn_token}]",
+            position_1.0, position_1.1, position_2.0, position_2.1
+        )
+    }
+
+    #[test]
+    fn test_loading_checks_the_board() {
+        let mark_1 = spooky((0, 0), (0, 1), "{\"X\":1}");
+        let mark_2 = spooky((0, 0), (0, 1), "{\"O\":2}");
+        let mark_3 = spooky((2, 0), (2, 1), "{\"X\":3}");
+
+        assert!(load(2, "", &mark_1).is_ok());
+        assert!(load(2, "{\"X\":1}", "").is_ok());
+        // A cycle closed by the last mark is pending.
+        assert!(load(3, "", &format!("{mark_1},{mark_2}"))
+            .unwrap()
+            .get_pending_collapse()
+            .is_some());
+
+        assert_eq!(Err(BoardError::InvalidTurn(0)), load(0, "", ""));
+        assert_eq!(Err(BoardError::InvalidTurn(11)), load(11, "", ""));
+        assert_eq!(Err(BoardError::InvalidTurn(3)), load(3, "", &mark_1));
+        assert_eq!(
+            Err(BoardError::InvalidMark(TurnToken::X(12))),
+            load(2, "", &spooky((0, 0), (0, 1), "{\"X\":12}"))
+        );
+        assert_eq!(
+            Err(BoardError::InvalidMark(TurnToken::O(1))),
+            load(2, "", &spooky((0, 0), (0, 1), "{\"O\":1}"))
+        );
+        assert_eq!(
+            Err(BoardError::InvalidMark(TurnToken::X(1))),
+            load(2, "{\"X\":1}", &mark_1)
+        );
+        assert_eq!(
+            Err(BoardError::InvalidPosition(Position::new(1, 1))),
+            load(3, "{\"X\":1}", &spooky((1, 1), (0, 1), "{\"O\":2}"))
+        );
+        assert_eq!(
+            Err(BoardError::InvalidPosition(Position::new(3, 0))),
+            load(2, "", &spooky((3, 0), (0, 1), "{\"X\":1}"))
+        );
+        assert_eq!(
+            Err(BoardError::OpenCycle),
+            load(4, "", &format!("{mark_1},{mark_2},{mark_3}"))
+        );
+    }
+
+    #[test]
+    fn test_symmetric_boards_share_canonical_form() {
+        let moves = [((0, 0), (0, 1)), ((2, 2), (2, 2)), ((0, 1), (1, 2))];
+
+        let mut board = Board::new();
+        play(&mut board, &moves, Symmetry::Identity);
+
+        for symmetry in Symmetry::ALL {
+            let mut other = Board::new();
+            play(&mut other, &moves, symmetry);
+
+            assert_eq!(board.get_canonical_hash(), other.get_canonical_hash());
+            assert_eq!(board.canonicalize().0, other.canonicalize().0);
+
+            let (canonical, canonical_symmetry) = other.canonicalize();
+            assert_eq!(other, canonical.transformed(canonical_symmetry.inverse()));
+        }
+    }
+
+    #[test]
+    fn test_move_order_changes_hash() {
+        let mut board = Board::new();
+        play(
+            &mut board,
+            &[((0, 0), (0, 0)), ((1, 1), (1, 1)), ((0, 1), (0, 1))],
+            Symmetry::Identity,
+        );
+
+        let mut other = Board::new();
+        play(
+            &mut other,
+            &[((0, 1), (0, 1)), ((1, 1), (1, 1)), ((0, 0), (0, 0))],
+            Symmetry::Identity,
+        );
+
+        assert_ne!(board.get_hash(), other.get_hash());
+        assert_ne!(board.get_canonical_hash(), other.get_canonical_hash());
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::time::Duration;
+
+use rand::prelude::{IndexedRandom, SeedableRng, StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Board, Position, SpookyMark, Token};
+use crate::bot::{Bot, Offer};
+use crate::game::{GameRecord, Rules};
+use crate::solver::{self, Solver};
+use crate::symmetry::Symmetry;
+
+const VERSION: u32 = 1;
+
+/// A recommended move, as seen on the canonical board.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BookMove {
+    pub positions: (Position, Position),
+    /// How often the move should be picked, relative to the other moves of the position.
+    pub weight: u32,
+    /// The number of games the move was played in, or 0 for moves found by search.
+    pub games: u32,
+    /// The mean score of the move for the player who made it, as their score minus the
+    /// opponent's, or its exact value for moves found by search.
+    pub score: f32,
+}
+
+/// Recommended moves keyed by `Board::get_canonical_hash`.
+///
+/// Books are saved as JSON, like
+/// `{"version": 1, "rules": "random_collapse", "positions": {"<hash>": [<book move>, ...]}}`,
+/// where every move is given in the canonical orientation of its position.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct OpeningBook {
+    version: u32,
+    pub rules: Rules,
+    positions: BTreeMap<u64, Vec<BookMove>>,
+}
+
+fn transform_move(
+    symmetry: Symmetry,
+    (position_1, position_2): (Position, Position),
+) -> (Position, Position) {
+    let position_1 = symmetry.apply(position_1);
+    let position_2 = symmetry.apply(position_2);
+
+    (position_1.min(position_2), position_1.max(position_2))
+}
+
+fn get_sign(token: Token) -> f32 {
+    match token {
+        Token::X => 1.0,
+        Token::O => -1.0,
+    }
+}
+
+impl OpeningBook {
+    pub fn new(rules: Rules) -> Self {
+        Self {
+            version: VERSION,
+            rules,
+            positions: BTreeMap::new(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let book: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        if book.version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported opening book version {}", book.version),
+            ));
+        }
+
+        Ok(book)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    /// The number of positions in the book.
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
+
+    /// The book moves of the board, in the board's own orientation.
+    pub fn get_moves(&self, board: &Board) -> Vec<BookMove> {
+        let inverse = board.get_canonical_symmetry().inverse();
+
+        self.positions
+            .get(&board.get_canonical_hash())
+            .into_iter()
+            .flatten()
+            .map(|book_move| BookMove {
+                positions: transform_move(inverse, book_move.positions),
+                ..*book_move
+            })
+            .collect()
+    }
+
+    /// A book of the best moves of every position up to `plies` moves after the board, valued
+    /// by the solver, whose rules the book takes.
+    ///
+    /// Every reply of the opponent is followed, so the book grows quickly with `plies`.
+    pub fn from_search(solver: &mut Solver, board: &Board, plies: usize) -> Self {
+        let mut book = Self::new(solver.get_rules());
+        book.add_searched_positions(solver, board, plies);
+
+        book
+    }
+
+    fn add_searched_positions(&mut self, solver: &mut Solver, board: &Board, plies: usize) {
+        if let Some(spooky_mark) = board.get_pending_collapse() {
+            for position in [spooky_mark.0, spooky_mark.1] {
+                let mut child = board.clone();
+                child.collapse(position).unwrap();
+                self.add_searched_positions(solver, &child, plies);
+            }
+
+            return;
+        }
+
+        let hash = board.get_canonical_hash();
+
+        if plies == 0 || board.is_game_over() || self.positions.contains_key(&hash) {
+            return;
+        }
+
+        let symmetry = board.get_canonical_symmetry();
+        let token = board.get_current_token();
+
+        // Moves that are the same up to a symmetry of the board only need to be searched once.
+        let mut seen = HashSet::new();
+        let mut children = vec![];
+
+        for (position_1, position_2) in solver::get_distinct_moves(board) {
+            let mut child = board.clone();
+            child.do_turn(position_1, position_2, token);
+
+            if seen.insert(child.get_canonical_hash()) {
+                let score = get_sign(token) * solver.get_value(&child);
+                children.push(((position_1, position_2), child, score));
+            }
+        }
+
+        let best_score = children
+            .iter()
+            .map(|(_, _, score)| *score)
+            .fold(f32::NEG_INFINITY, f32::max);
+
+        let book_moves = children
+            .iter()
+            .filter(|(_, _, score)| *score == best_score)
+            .map(|(positions, _, score)| BookMove {
+                positions: transform_move(symmetry, *positions),
+                weight: 1,
+                games: 0,
+                score: *score,
+            })
+            .collect();
+
+        self.positions.insert(hash, book_moves);
+
+        for (_, child, _) in children {
+            self.add_searched_positions(solver, &child, plies - 1);
+        }
+    }
+
+    /// A book of the moves played in the first `plies` turns of the games, keeping only the
+    /// moves played in at least `min_games` of them.
+    ///
+    /// A move is weighted by the number of games in which the player who made it didn't lose.
+    pub fn from_games(rules: Rules, records: &[GameRecord], plies: usize, min_games: u32) -> Self {
+        let mut book = Self::new(rules);
+
+        for record in records {
+            let mut board = Board::new();
+
+            for turn in record.turns.iter().take(plies) {
+                let score = get_sign(turn.token) * (record.score.0 - record.score.1);
+
+                let symmetry = board.get_canonical_symmetry();
+                let positions = transform_move(symmetry, turn.positions);
+
+                let book_moves = book
+                    .positions
+                    .entry(board.get_canonical_hash())
+                    .or_default();
+
+                let index = match book_moves.iter().position(|m| m.positions == positions) {
+                    Some(index) => index,
+                    None => {
+                        book_moves.push(BookMove {
+                            positions,
+                            weight: 0,
+                            games: 0,
+                            score: 0.0,
+                        });
+                        book_moves.len() - 1
+                    }
+                };
+
+                let book_move = &mut book_moves[index];
+                book_move.score = (book_move.score * book_move.games as f32 + score)
+                    / (book_move.games + 1) as f32;
+                book_move.games += 1;
+                if score >= 0.0 {
+                    book_move.weight += 1;
+                }
+
+                board.do_turn(turn.positions.0, turn.positions.1, turn.token);
+                if let Some((_, position)) = turn.collapse {
+                    board.collapse(position).unwrap();
+                }
+            }
+        }
+
+        for book_moves in book.positions.values_mut() {
+            book_moves.retain(|book_move| book_move.games >= min_games);
+        }
+        book.positions
+            .retain(|_, book_moves| !book_moves.is_empty());
+
+        book
+    }
+}
+
+/// Plays from the opening book while it has moves for the board, and lets another bot play
+/// once out of book.
+#[derive(Clone)]
+pub struct BookBot<B>
+where
+    B: Bot,
+{
+    book: OpeningBook,
+    fallback: B,
+    rng: StdRng,
+    // Whether the last move came from the fallback, whose failures are then the bot's own.
+    used_fallback: bool,
+}
+
+impl<B> BookBot<B>
+where
+    B: Bot,
+{
+    /// The seed picks between the book moves of a position, by weight.
+    pub fn new(book: OpeningBook, fallback: B, seed: u64) -> Self {
+        Self {
+            book,
+            fallback,
+            rng: StdRng::seed_from_u64(seed),
+            used_fallback: false,
+        }
+    }
+
+    fn get_book_move(&mut self, board: &Board) -> Option<(Position, Position)> {
+        self.used_fallback = false;
+        let legal_moves = board.get_legal_moves();
+
+        // Skipping illegal moves guards against the rare position sharing a hash with another.
+        let book_moves = self
+            .book
+            .get_moves(board)
+            .into_iter()
+            .filter(|book_move| legal_moves.contains(&book_move.positions))
+            .collect::<Vec<_>>();
+
+        book_moves
+            .choose_weighted(&mut self.rng, |book_move| book_move.weight)
+            .ok()
+            .map(|book_move| book_move.positions)
+    }
+}
+
+impl<B> Bot for BookBot<B>
+where
+    B: Bot,
+{
+    fn get_next_move(&mut self, board: &Board, token: Token) -> (Position, Position) {
+        match self.get_book_move(board) {
+            Some(positions) => positions,
+            None => {
+                self.used_fallback = true;
+                self.fallback.get_next_move(board, token)
+            }
+        }
+    }
+
+    fn get_timed_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+        budget: Duration,
+    ) -> (Position, Position) {
+        match self.get_book_move(board) {
+            Some(positions) => positions,
+            None => {
+                self.used_fallback = true;
+                self.fallback.get_timed_move(board, token, budget)
+            }
+        }
+    }
+
+    fn get_failure(&mut self, token: Token) -> Option<String> {
+        if self.used_fallback {
+            self.fallback.get_failure(token)
+        } else {
+            None
+        }
+    }
+
+    fn choose_collapse(&mut self, board: &Board, token: Token) -> Position {
+        self.used_fallback = true;
+        self.fallback.choose_collapse(board, token)
+    }
+
+    fn new_game(&mut self, rules: Rules, token: Token) {
+        self.fallback.new_game(rules, token)
+    }
+
+    fn get_offer(&mut self, board: &Board, token: Token) -> Option<Offer> {
+        self.fallback.get_offer(board, token)
+    }
+
+    fn accept_draw(&mut self, board: &Board, token: Token) -> bool {
+        self.fallback.accept_draw(board, token)
+    }
+
+    fn notify_move(&mut self, board: &Board, token: Token, positions: (Position, Position)) {
+        self.fallback.notify_move(board, token, positions)
+    }
+
+    fn notify_collapse(
+        &mut self,
+        board: &Board,
+        token: Token,
+        spooky_mark: SpookyMark,
+        position: Position,
+    ) {
+        self.fallback
+            .notify_collapse(board, token, spooky_mark, position)
+    }
+
+    fn game_over(&mut self, board: &Board, token: Token, score: (f32, f32)) {
+        self.fallback.game_over(board, token, score)
+    }
+
+    fn start_pondering(&mut self, board: &Board, token: Token) {
+        self.fallback.start_pondering(board, token)
+    }
+
+    fn stop_pondering(&mut self, token: Token) {
+        self.fallback.stop_pondering(token)
+    }
+}
+
+#[cfg(test)]
+mod test_opening_book {
+    use super::*;
+
+    use crate::bot::{BotPair, RandomBot};
+    use crate::game::Game;
+
+    struct NoMoveBot;
+
+    impl Bot for NoMoveBot {
+        fn get_next_move(&mut self, _board: &Board, _token: Token) -> (Position, Position) {
+            panic!("The book should have had a move!");
+        }
+    }
+
+    fn create_board() -> Board {
+        let mut board = Board::new();
+
+        for (position_1, position_2) in [
+            ((0, 0), (0, 0)),
+            ((1, 0), (1, 0)),
+            ((0, 1), (0, 1)),
+            ((1, 1), (1, 1)),
+            ((2, 2), (2, 2)),
+        ] {
+            board.do_turn(
+                Position::new(position_1.0, position_1.1),
+                Position::new(position_2.0, position_2.1),
+                board.get_current_token(),
+            );
+        }
+
+        board
+    }
+
+    #[test]
+    fn test_search_book() {
+        let board = create_board();
+        let mut solver = Solver::new(Rules::PlayerCollapse);
+        let book = OpeningBook::from_search(&mut solver, &board, 2);
+
+        // O completes the middle row, and anything else does no better.
+        let book_moves = book.get_moves(&board);
+        let win = (Position::new(1, 2), Position::new(1, 2));
+        assert!(book_moves
+            .iter()
+            .any(|book_move| book_move.positions == win));
+        assert!(book_moves.iter().all(|book_move| book_move.score == 1.0));
+        assert!(book.len() > 1);
+
+        // The book also knows the mirrored board.
+        let mirrored = board.transformed(Symmetry::FlipHorizontal);
+        let mut bot = BookBot::new(book, NoMoveBot, 0);
+        let book_move = bot.get_next_move(&mirrored, Token::O);
+        assert!(book_moves
+            .iter()
+            .any(|m| transform_move(Symmetry::FlipHorizontal, m.positions) == book_move));
+    }
+
+    #[test]
+    fn test_self_play_book() {
+        let records = (0..50)
+            .map(|seed| {
+                let bots = BotPair {
+                    bot_x: RandomBot::new(2 * seed),
+                    bot_o: RandomBot::new(2 * seed + 1),
+                };
+                let mut game = Game::new(bots, seed);
+                game.set_verbose(false);
+                game.play_whole_game();
+
+                game.get_record()
+            })
+            .collect::<Vec<_>>();
+
+        let book = OpeningBook::from_games(Rules::RandomCollapse, &records, 2, 1);
+        let book_moves = book.get_moves(&Board::new());
+        assert_eq!(50, book_moves.iter().map(|m| m.games).sum::<u32>());
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("book.json");
+        book.save(&path).unwrap();
+        assert_eq!(book, OpeningBook::load(&path).unwrap());
+
+        // Out of book, the fallback plays.
+        let mut bot = BookBot::new(
+            OpeningBook::new(Rules::RandomCollapse),
+            RandomBot::new(0),
+            0,
+        );
+        let (position_1, position_2) = bot.get_next_move(&Board::new(), Token::X);
+        assert!(Board::new()
+            .try_do_turn(position_1, position_2, Token::X)
+            .is_ok());
+    }
+}
@@ -1,11 +1,321 @@
+use std::time::Duration;
+
 use rand::prelude::{IndexedRandom, SeedableRng, StdRng};
 
 use crate::board::{Board, Position, SpookyMark, Token};
+use crate::difficulty::{DifficultyBot, Level};
+use crate::evaluation::{Evaluator, HeuristicEvaluator};
+use crate::game::Rules;
+
+/// What a player can do instead of moving.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Offer {
+    Resign,
+    /// Ends the game as a tie if the opponent accepts, and is played on otherwise.
+    Draw,
+}
 
+/// A player of the game. Besides moving, a bot is told how the game goes through hooks that do
+/// nothing by default, which stateful bots and frontends can follow.
+///
+/// Every method is given the token of the player it is called for, so a bot playing both sides
+/// is called once for each of them.
 pub trait Bot {
     fn get_next_move(&mut self, board: &Board, token: Token) -> (Position, Position);
+
+    /// The next move, found within about the given time. Bots that don't search ignore the
+    /// budget.
+    fn get_timed_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+        _budget: Duration,
+    ) -> (Position, Position) {
+        self.get_next_move(board, token)
+    }
+
+    /// Why the bot couldn't come up with its last move for the token, like an external engine
+    /// that crashed or timed out, in which case `Game` forfeits the game instead of playing it.
+    fn get_failure(&mut self, _token: Token) -> Option<String> {
+        None
+    }
+
+    /// The position the spooky mark closing a cycle collapses to, for the player choosing it
+    /// under `Rules::PlayerCollapse`. By default, the one the heuristic evaluation prefers.
+    fn choose_collapse(&mut self, board: &Board, token: Token) -> Position {
+        get_best_collapse(
+            board,
+            token,
+            &HeuristicEvaluator {
+                rules: Rules::PlayerCollapse,
+                ..HeuristicEvaluator::default()
+            },
+        )
+    }
+
+    /// Called before the game's first move.
+    fn new_game(&mut self, _rules: Rules, _token: Token) {}
+
+    /// Asked before every move of the player, who can resign or offer a draw instead.
+    fn get_offer(&mut self, _board: &Board, _token: Token) -> Option<Offer> {
+        None
+    }
+
+    /// Whether the player accepts the draw offered by their opponent.
+    fn accept_draw(&mut self, _board: &Board, _token: Token) -> bool {
+        false
+    }
+
+    /// Called after the opponent of the player moved, with the board before any collapse.
+    fn notify_move(&mut self, _board: &Board, _token: Token, _positions: (Position, Position)) {}
+
+    /// Called after a cycle collapsed, with the collapsed spooky mark and where it went.
+    fn notify_collapse(
+        &mut self,
+        _board: &Board,
+        _token: Token,
+        _spooky_mark: SpookyMark,
+        _position: Position,
+    ) {
+    }
+
+    /// Called once the game is over, with its final score.
+    fn game_over(&mut self, _board: &Board, _token: Token, _score: (f32, f32)) {}
+
+    /// Called after the player moved, when the game ponders, so that the bot can think about
+    /// the board while the opponent does, in the background.
+    fn start_pondering(&mut self, _board: &Board, _token: Token) {}
+
+    /// Called before the player is asked for their next move, or once the game is over.
+    fn stop_pondering(&mut self, _token: Token) {}
+}
+
+/// The position of the pending collapse whose board the evaluator likes best for the token.
+pub fn get_best_collapse<E>(board: &Board, token: Token, evaluator: &E) -> Position
+where
+    E: Evaluator,
+{
+    let spooky_mark = board.get_pending_collapse().unwrap();
+    let sign = match token {
+        Token::X => 1.0,
+        Token::O => -1.0,
+    };
+
+    let value_of = |position| {
+        let mut child = board.clone();
+        child.collapse(position).unwrap();
+
+        sign * evaluator.evaluate(&child)
+    };
+
+    if value_of(spooky_mark.0) >= value_of(spooky_mark.1) {
+        spooky_mark.0
+    } else {
+        spooky_mark.1
+    }
+}
+
+impl<B> Bot for Box<B>
+where
+    B: Bot + ?Sized,
+{
+    fn get_next_move(&mut self, board: &Board, token: Token) -> (Position, Position) {
+        (**self).get_next_move(board, token)
+    }
+
+    fn get_timed_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+        budget: Duration,
+    ) -> (Position, Position) {
+        (**self).get_timed_move(board, token, budget)
+    }
+
+    fn get_failure(&mut self, token: Token) -> Option<String> {
+        (**self).get_failure(token)
+    }
+
+    fn choose_collapse(&mut self, board: &Board, token: Token) -> Position {
+        (**self).choose_collapse(board, token)
+    }
+
+    fn new_game(&mut self, rules: Rules, token: Token) {
+        (**self).new_game(rules, token)
+    }
+
+    fn get_offer(&mut self, board: &Board, token: Token) -> Option<Offer> {
+        (**self).get_offer(board, token)
+    }
+
+    fn accept_draw(&mut self, board: &Board, token: Token) -> bool {
+        (**self).accept_draw(board, token)
+    }
+
+    fn notify_move(&mut self, board: &Board, token: Token, positions: (Position, Position)) {
+        (**self).notify_move(board, token, positions)
+    }
+
+    fn notify_collapse(
+        &mut self,
+        board: &Board,
+        token: Token,
+        spooky_mark: SpookyMark,
+        position: Position,
+    ) {
+        (**self).notify_collapse(board, token, spooky_mark, position)
+    }
+
+    fn game_over(&mut self, board: &Board, token: Token, score: (f32, f32)) {
+        (**self).game_over(board, token, score)
+    }
+
+    fn start_pondering(&mut self, board: &Board, token: Token) {
+        (**self).start_pondering(board, token)
+    }
+
+    fn stop_pondering(&mut self, token: Token) {
+        (**self).stop_pondering(token)
+    }
+}
+
+/// Two bots playing against each other, each one only asked for the moves of its own token.
+///
+/// Both are either a `Bot` or an `AsyncBot`.
+#[derive(Clone)]
+pub struct BotPair<X, O> {
+    pub bot_x: X,
+    pub bot_o: O,
+}
+
+impl<X, O> Bot for BotPair<X, O>
+where
+    X: Bot,
+    O: Bot,
+{
+    fn get_next_move(&mut self, board: &Board, token: Token) -> (Position, Position) {
+        match token {
+            Token::X => self.bot_x.get_next_move(board, token),
+            Token::O => self.bot_o.get_next_move(board, token),
+        }
+    }
+
+    fn get_timed_move(
+        &mut self,
+        board: &Board,
+        token: Token,
+        budget: Duration,
+    ) -> (Position, Position) {
+        match token {
+            Token::X => self.bot_x.get_timed_move(board, token, budget),
+            Token::O => self.bot_o.get_timed_move(board, token, budget),
+        }
+    }
+
+    fn get_failure(&mut self, token: Token) -> Option<String> {
+        match token {
+            Token::X => self.bot_x.get_failure(token),
+            Token::O => self.bot_o.get_failure(token),
+        }
+    }
+
+    fn choose_collapse(&mut self, board: &Board, token: Token) -> Position {
+        match token {
+            Token::X => self.bot_x.choose_collapse(board, token),
+            Token::O => self.bot_o.choose_collapse(board, token),
+        }
+    }
+
+    fn new_game(&mut self, rules: Rules, token: Token) {
+        match token {
+            Token::X => self.bot_x.new_game(rules, token),
+            Token::O => self.bot_o.new_game(rules, token),
+        }
+    }
+
+    fn get_offer(&mut self, board: &Board, token: Token) -> Option<Offer> {
+        match token {
+            Token::X => self.bot_x.get_offer(board, token),
+            Token::O => self.bot_o.get_offer(board, token),
+        }
+    }
+
+    fn accept_draw(&mut self, board: &Board, token: Token) -> bool {
+        match token {
+            Token::X => self.bot_x.accept_draw(board, token),
+            Token::O => self.bot_o.accept_draw(board, token),
+        }
+    }
+
+    fn notify_move(&mut self, board: &Board, token: Token, positions: (Position, Position)) {
+        match token {
+            Token::X => self.bot_x.notify_move(board, token, positions),
+            Token::O => self.bot_o.notify_move(board, token, positions),
+        }
+    }
+
+    fn notify_collapse(
+        &mut self,
+        board: &Board,
+        token: Token,
+        spooky_mark: SpookyMark,
+        position: Position,
+    ) {
+        match token {
+            Token::X => self
+                .bot_x
+                .notify_collapse(board, token, spooky_mark, position),
+            Token::O => self
+                .bot_o
+                .notify_collapse(board, token, spooky_mark, position),
+        }
+    }
+
+    fn game_over(&mut self, board: &Board, token: Token, score: (f32, f32)) {
+        match token {
+            Token::X => self.bot_x.game_over(board, token, score),
+            Token::O => self.bot_o.game_over(board, token, score),
+        }
+    }
+
+    fn start_pondering(&mut self, board: &Board, token: Token) {
+        match token {
+            Token::X => self.bot_x.start_pondering(board, token),
+            Token::O => self.bot_o.start_pondering(board, token),
+        }
+    }
+
+    fn stop_pondering(&mut self, token: Token) {
+        match token {
+            Token::X => self.bot_x.stop_pondering(token),
+            Token::O => self.bot_o.stop_pondering(token),
+        }
+    }
 }
 
+/// The names `create_bot` knows: "random", "greedy", and the names of the difficulty levels.
+pub fn get_bot_names() -> Vec<&'static str> {
+    ["random", "greedy"]
+        .into_iter()
+        .chain(Level::ALL.map(Level::get_name))
+        .collect()
+}
+
+/// Creates one of the bots by name, for frontends that let their users pick one: "random",
+/// "greedy", or the name of a difficulty `Level`.
+pub fn create_bot(name: &str, seed: u64) -> Option<Box<dyn Bot + Send + Sync>> {
+    match name {
+        "random" => Some(Box::new(RandomBot::new(seed))),
+        "greedy" => Some(Box::new(GreedyBot::new(
+            HeuristicEvaluator::default(),
+            seed,
+        ))),
+        _ => Level::from_name(name)
+            .map(|level| Box::new(DifficultyBot::new(level, seed)) as Box<dyn Bot + Send + Sync>),
+    }
+}
+
+#[derive(Clone)]
 pub struct RandomBot {
     rng: StdRng,
 }
@@ -20,28 +330,176 @@ impl RandomBot {
 
 impl Bot for RandomBot {
     fn get_next_move(&mut self, board: &Board, _token: Token) -> (Position, Position) {
-        let mut possible_moves = vec![];
+        *board.get_legal_moves().choose(&mut self.rng).unwrap()
+    }
+}
 
-        for position_1 in &board.positions {
-            if board.get_mark(*position_1).is_some() {
-                continue;
-            }
+/// Plays the move whose resulting board the evaluator likes best, looking one move ahead.
+///
+/// The rules of the game, given by `Bot::new_game`, decide how a cycle its move closes is
+/// valued. Under `Rules::PlayerCollapse` the opponent's choice of collapse is played out, so
+/// boards with a pending cycle, and the `collapse_control` weight, are never evaluated.
+#[derive(Clone)]
+pub struct GreedyBot<E>
+where
+    E: Evaluator,
+{
+    evaluator: E,
+    rules: Rules,
+    rng: StdRng,
+}
 
-            for position_2 in &board.positions {
-                if board.get_mark(*position_2).is_some() {
-                    continue;
-                }
+impl<E> GreedyBot<E>
+where
+    E: Evaluator,
+{
+    /// The seed breaks ties between equally good moves.
+    pub fn new(evaluator: E, seed: u64) -> Self {
+        Self {
+            evaluator,
+            rules: Rules::default(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
 
-                if board.spooky_marks.iter().any(|SpookyMark(p1, p2, _)| {
-                    position_1 == position_2 && (position_1 == p1 || position_1 == p2)
-                }) {
-                    continue;
-                }
+    // Moves that close a cycle are valued by both ways it can collapse: as a coin flip, or as
+    // the choice of the opponent, who collapses it under the player rules.
+    fn evaluate_move(&self, board: &Board, (position_1, position_2): (Position, Position)) -> f32 {
+        let mut child = board.clone();
+        child.do_turn(position_1, position_2, board.get_current_token());
+
+        match child.get_pending_collapse() {
+            Some(spooky_mark) => {
+                let values = [spooky_mark.0, spooky_mark.1].map(|position| {
+                    let mut collapsed = child.clone();
+                    collapsed.collapse(position).unwrap();
 
-                possible_moves.push((*position_1, *position_2));
+                    self.evaluator.evaluate(&collapsed)
+                });
+
+                match (self.rules, child.get_current_token()) {
+                    (Rules::RandomCollapse, _) => (values[0] + values[1]) / 2.0,
+                    (Rules::PlayerCollapse, Token::X) => values[0].max(values[1]),
+                    (Rules::PlayerCollapse, Token::O) => values[0].min(values[1]),
+                }
             }
+            None => self.evaluator.evaluate(&child),
+        }
+    }
+}
+
+impl<E> Bot for GreedyBot<E>
+where
+    E: Evaluator,
+{
+    fn get_next_move(&mut self, board: &Board, token: Token) -> (Position, Position) {
+        let sign = match token {
+            Token::X => 1.0,
+            Token::O => -1.0,
+        };
+
+        let values = board
+            .get_legal_moves()
+            .into_iter()
+            .map(|legal_move| (legal_move, sign * self.evaluate_move(board, legal_move)))
+            .collect::<Vec<_>>();
+
+        let best_value = values
+            .iter()
+            .map(|(_, value)| *value)
+            .fold(f32::NEG_INFINITY, f32::max);
+
+        let best_moves = values
+            .into_iter()
+            .filter(|(_, value)| *value == best_value)
+            .map(|(best_move, _)| best_move)
+            .collect::<Vec<_>>();
+
+        *best_moves.choose(&mut self.rng).unwrap()
+    }
+
+    fn choose_collapse(&mut self, board: &Board, token: Token) -> Position {
+        get_best_collapse(board, token, &self.evaluator)
+    }
+
+    fn new_game(&mut self, rules: Rules, _token: Token) {
+        self.rules = rules;
+    }
+}
+
+#[cfg(test)]
+mod test_greedy_bot {
+    use super::*;
+
+    use crate::test_support::assert_beats_random_bot;
+
+    #[test]
+    fn test_bot_names() {
+        for name in get_bot_names() {
+            assert!(create_bot(name, 0).is_some(), "{name}");
         }
+        assert!(create_bot("impossible", 0).is_none());
+    }
+
+    #[test]
+    fn test_greedy_bot_beats_random_bot() {
+        assert_beats_random_bot(20, Token::X, |seed| {
+            GreedyBot::new(HeuristicEvaluator::default(), seed)
+        });
+    }
+
+    #[test]
+    fn test_greedy_bot_takes_a_win() {
+        let mut board = Board::new();
+        for (position, token) in [
+            ((0, 0), Token::X),
+            ((1, 0), Token::O),
+            ((0, 1), Token::X),
+            ((1, 1), Token::O),
+        ] {
+            board.do_turn(
+                Position::new(position.0, position.1),
+                Position::new(position.0, position.1),
+                token,
+            );
+        }
+
+        let mut bot = create_bot("greedy", 0).unwrap();
+        assert_eq!(
+            (Position::new(0, 2), Position::new(0, 2)),
+            bot.get_next_move(&board, Token::X)
+        );
+    }
+
+    #[test]
+    fn test_greedy_bot_values_collapses_by_rules() {
+        // X's second move closes the cycle of a1, b1 and c1, which O collapses.
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(0, 1), Token::X);
+        board.do_turn(Position::new(0, 1), Position::new(0, 2), Token::O);
+        let cycle_move = (Position::new(0, 2), Position::new(0, 0));
+
+        let mut child = board.clone();
+        child.do_turn(cycle_move.0, cycle_move.1, Token::X);
+        let spooky_mark = child.get_pending_collapse().unwrap();
+        let evaluator = HeuristicEvaluator::default();
+        let values = [spooky_mark.0, spooky_mark.1].map(|position| {
+            let mut collapsed = child.clone();
+            collapsed.collapse(position).unwrap();
+            evaluator.evaluate(&collapsed)
+        });
+        assert_ne!(values[0], values[1]);
+
+        let mut bot = GreedyBot::new(evaluator, 0);
+        assert_eq!(
+            (values[0] + values[1]) / 2.0,
+            bot.evaluate_move(&board, cycle_move)
+        );
 
-        *possible_moves.choose(&mut self.rng).unwrap()
+        bot.new_game(Rules::PlayerCollapse, Token::X);
+        assert_eq!(
+            values[0].min(values[1]),
+            bot.evaluate_move(&board, cycle_move)
+        );
     }
 }
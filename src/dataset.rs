@@ -0,0 +1,268 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use crate::board::Board;
+use crate::game::GameRecord;
+use crate::network::{encode, get_move_index, INPUT_SIZE, POLICY_SIZE};
+use crate::solver;
+
+/// A position of a game, as seen on its canonical board.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Sample {
+    /// `Board::get_canonical_hash` of the position.
+    pub hash: u64,
+    /// The canonical board encoded by `network::encode`.
+    pub input: Vec<f32>,
+    /// The policy indices of the legal moves, as given by `network::get_move_index`.
+    pub legal_moves: Vec<usize>,
+    /// The policy index of the move played.
+    pub chosen_move: usize,
+    /// The final score of the game, as `Board::get_score` of its last board.
+    pub score: (f32, f32),
+}
+
+/// Positions of played games, keeping only the first time a position is seen up to the
+/// symmetries of the board.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Dataset {
+    samples: Vec<Sample>,
+    hashes: HashSet<u64>,
+}
+
+// A `.npy` file of a little endian array, in the version 1.0 format of NumPy.
+fn write_npy(path: &Path, descr: &str, shape: &[usize], data: &[u8]) -> io::Result<()> {
+    let shape = match shape {
+        [length] => format!("({length},)"),
+        _ => format!(
+            "({})",
+            shape
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    };
+    let mut header = format!("{{'descr': '{descr}', 'fortran_order': False, 'shape': {shape}, }}");
+
+    // The data starts at a multiple of 64 bytes, after the 10 byte preamble and a newline.
+    let padding = (64 - (10 + header.len() + 1) % 64) % 64;
+    header += &" ".repeat(padding);
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(10 + header.len() + data.len());
+    bytes.extend_from_slice(b"\x93NUMPY\x01\x00");
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    bytes.extend_from_slice(data);
+
+    fs::write(path, bytes)
+}
+
+impl Dataset {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of distinct positions.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn get_samples(&self) -> &[Sample] {
+        &self.samples
+    }
+
+    /// Replays the game, adding every position before a move that isn't in the dataset yet.
+    pub fn add_game(&mut self, record: &GameRecord) {
+        let mut board = Board::new();
+
+        for turn in &record.turns {
+            let (canonical, symmetry) = board.canonicalize();
+            let hash = canonical.get_canonical_hash();
+
+            if self.hashes.insert(hash) {
+                let chosen_move = (
+                    symmetry.apply(turn.positions.0),
+                    symmetry.apply(turn.positions.1),
+                );
+
+                self.samples.push(Sample {
+                    hash,
+                    input: encode(&canonical),
+                    legal_moves: solver::get_distinct_moves(&canonical)
+                        .into_iter()
+                        .map(get_move_index)
+                        .collect(),
+                    chosen_move: get_move_index(chosen_move),
+                    score: record.score,
+                });
+            }
+
+            board.do_turn(turn.positions.0, turn.positions.1, turn.token);
+            if let Some((_, position)) = turn.collapse {
+                board.collapse(position).unwrap();
+            }
+        }
+    }
+
+    /// Writes a CSV file with a header and a row per position: the hash, both scores, the
+    /// chosen move, the legal moves separated by spaces, and the `INPUT_SIZE` inputs.
+    pub fn save_csv(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut csv = String::from("hash,score_x,score_o,chosen_move,legal_moves");
+        for index in 0..INPUT_SIZE {
+            csv += &format!(",input_{index}");
+        }
+        csv.push('\n');
+
+        for sample in &self.samples {
+            let legal_moves = sample
+                .legal_moves
+                .iter()
+                .map(|index| index.to_string())
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            csv += &format!(
+                "{},{},{},{},{legal_moves}",
+                sample.hash, sample.score.0, sample.score.1, sample.chosen_move
+            );
+            for x in &sample.input {
+                csv += &format!(",{x}");
+            }
+            csv.push('\n');
+        }
+
+        fs::write(path, csv)
+    }
+
+    /// Writes the dataset as NumPy arrays in the directory, one row per position:
+    /// `inputs.npy` (`float32`, `INPUT_SIZE` columns), `legal_moves.npy` (`uint8`, 1 for every
+    /// legal move of the `POLICY_SIZE` policy indices), `chosen_moves.npy` (`int64`),
+    /// `scores.npy` (`float32`, X's and O's score) and `hashes.npy` (`uint64`).
+    pub fn save_npy(&self, directory: impl AsRef<Path>) -> io::Result<()> {
+        let directory = directory.as_ref();
+        fs::create_dir_all(directory)?;
+
+        let count = self.samples.len();
+        let mut inputs = Vec::with_capacity(4 * INPUT_SIZE * count);
+        let mut legal_moves = Vec::with_capacity(POLICY_SIZE * count);
+        let mut chosen_moves = Vec::with_capacity(8 * count);
+        let mut scores = Vec::with_capacity(8 * count);
+        let mut hashes = Vec::with_capacity(8 * count);
+
+        for sample in &self.samples {
+            for x in &sample.input {
+                inputs.extend_from_slice(&x.to_le_bytes());
+            }
+
+            let mut mask = [0u8; POLICY_SIZE];
+            for index in &sample.legal_moves {
+                mask[*index] = 1;
+            }
+            legal_moves.extend_from_slice(&mask);
+
+            chosen_moves.extend_from_slice(&(sample.chosen_move as i64).to_le_bytes());
+            scores.extend_from_slice(&sample.score.0.to_le_bytes());
+            scores.extend_from_slice(&sample.score.1.to_le_bytes());
+            hashes.extend_from_slice(&sample.hash.to_le_bytes());
+        }
+
+        write_npy(
+            &directory.join("inputs.npy"),
+            "<f4",
+            &[count, INPUT_SIZE],
+            &inputs,
+        )?;
+        write_npy(
+            &directory.join("legal_moves.npy"),
+            "|u1",
+            &[count, POLICY_SIZE],
+            &legal_moves,
+        )?;
+        write_npy(
+            &directory.join("chosen_moves.npy"),
+            "<i8",
+            &[count],
+            &chosen_moves,
+        )?;
+        write_npy(&directory.join("scores.npy"), "<f4", &[count, 2], &scores)?;
+        write_npy(&directory.join("hashes.npy"), "<u8", &[count], &hashes)
+    }
+}
+
+#[cfg(test)]
+mod test_dataset {
+    use super::*;
+
+    use crate::bot::{BotPair, RandomBot};
+    use crate::game::Game;
+
+    fn create_dataset() -> Dataset {
+        let mut dataset = Dataset::new();
+
+        for seed in 0..10 {
+            let bots = BotPair {
+                bot_x: RandomBot::new(2 * seed),
+                bot_o: RandomBot::new(2 * seed + 1),
+            };
+            let mut game = Game::new(bots, seed);
+            game.set_verbose(false);
+            game.play_whole_game();
+
+            dataset.add_game(&game.get_record());
+        }
+
+        dataset
+    }
+
+    #[test]
+    fn test_deduplication() {
+        let dataset = create_dataset();
+
+        // Every game starts from the empty board, but it is only kept once.
+        let samples = dataset.get_samples();
+        assert_eq!(
+            1,
+            samples
+                .iter()
+                .filter(|s| s.input[INPUT_SIZE - 1] == 0.0)
+                .count()
+        );
+        assert_eq!(
+            dataset.len(),
+            samples.iter().map(|s| s.hash).collect::<HashSet<_>>().len()
+        );
+        assert!(samples
+            .iter()
+            .all(|sample| sample.legal_moves.contains(&sample.chosen_move)));
+    }
+
+    #[test]
+    fn test_file_formats() {
+        let dataset = create_dataset();
+        let temporary = tempfile::tempdir().unwrap();
+        let directory = temporary.path().join("dataset");
+
+        dataset.save_csv(directory.with_extension("csv")).unwrap();
+        let csv = fs::read_to_string(directory.with_extension("csv")).unwrap();
+        assert_eq!(dataset.len() + 1, csv.lines().count());
+        assert_eq!(
+            5 + INPUT_SIZE,
+            csv.lines().nth(1).unwrap().split(',').count()
+        );
+
+        dataset.save_npy(&directory).unwrap();
+        let npy = fs::read(directory.join("scores.npy")).unwrap();
+        let header_length = u16::from_le_bytes([npy[8], npy[9]]) as usize;
+        assert_eq!(0, (10 + header_length) % 64);
+        assert_eq!(10 + header_length + 8 * dataset.len(), npy.len());
+        assert!(String::from_utf8_lossy(&npy[10..10 + header_length])
+            .contains(&format!("'shape': ({}, 2)", dataset.len())));
+    }
+}
@@ -0,0 +1,261 @@
+use std::time::Duration;
+
+use rand::prelude::{IndexedRandom, Rng, SeedableRng, StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Board, Position, Token};
+use crate::bot::{get_best_collapse, Bot, BotPair};
+use crate::evaluation::HeuristicEvaluator;
+use crate::game::Rules;
+use crate::search::SearchBot;
+use crate::simulation::simulate_games;
+
+/// The strengths of `DifficultyBot`, from weakest to strongest.
+///
+/// Every level searches a few moves deep with the heuristic evaluation, and plays a random
+/// move instead some of the time. Measured against the next level, as by `calibrate_levels`,
+/// every level loses more games than it wins.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Level {
+    Beginner,
+    Easy,
+    Medium,
+    Hard,
+    Expert,
+}
+
+impl Level {
+    pub const ALL: [Level; 5] = [
+        Level::Beginner,
+        Level::Easy,
+        Level::Medium,
+        Level::Hard,
+        Level::Expert,
+    ];
+
+    pub fn get_name(self) -> &'static str {
+        match self {
+            Level::Beginner => "beginner",
+            Level::Easy => "easy",
+            Level::Medium => "medium",
+            Level::Hard => "hard",
+            Level::Expert => "expert",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|level| level.get_name() == name)
+    }
+
+    /// How many moves ahead the level searches. Searching deeper than 3 moves with the
+    /// heuristic evaluation plays worse, as measured, besides taking much longer.
+    pub fn get_depth(self) -> usize {
+        match self {
+            Level::Beginner | Level::Easy => 1,
+            Level::Medium => 2,
+            Level::Hard | Level::Expert => 3,
+        }
+    }
+
+    /// The chance that the level plays a random move or collapse instead of its best one.
+    pub fn get_randomness(self) -> f64 {
+        match self {
+            Level::Beginner => 0.5,
+            Level::Easy => 0.2,
+            Level::Medium => 0.1,
+            Level::Hard => 0.2,
+            Level::Expert => 0.0,
+        }
+    }
+
+    /// The next stronger level, if any.
+    pub fn get_next(self) -> Option<Self> {
+        Self::ALL.get(self as usize + 1).copied()
+    }
+}
+
+/// A bot of a given difficulty for casual play, mixing a depth-limited search with random
+/// moves.
+///
+/// The search doesn't look at the clock, so time budgets are ignored and the bot runs where
+/// there is no clock, as in WebAssembly.
+#[derive(Clone)]
+pub struct DifficultyBot {
+    level: Level,
+    search: SearchBot<HeuristicEvaluator>,
+    rng: StdRng,
+}
+
+impl DifficultyBot {
+    /// The seed drives the random moves.
+    pub fn new(level: Level, seed: u64) -> Self {
+        Self {
+            level,
+            search: SearchBot::new(HeuristicEvaluator::default(), Duration::MAX),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn get_level(&self) -> Level {
+        self.level
+    }
+
+    fn plays_at_random(&mut self) -> bool {
+        self.rng.random_bool(self.level.get_randomness())
+    }
+}
+
+impl Bot for DifficultyBot {
+    fn get_next_move(&mut self, board: &Board, _token: Token) -> (Position, Position) {
+        if self.plays_at_random() {
+            return *board.get_legal_moves().choose(&mut self.rng).unwrap();
+        }
+
+        self.search.search_to_depth(board, self.level.get_depth())
+    }
+
+    fn choose_collapse(&mut self, board: &Board, token: Token) -> Position {
+        let spooky_mark = board.get_pending_collapse().unwrap();
+
+        if self.plays_at_random() {
+            return *[spooky_mark.0, spooky_mark.1]
+                .choose(&mut self.rng)
+                .unwrap();
+        }
+
+        // Collapses are only chosen under `Rules::PlayerCollapse`, so the evaluation credits
+        // whoever chooses the next one, as in the default of `Bot`.
+        get_best_collapse(
+            board,
+            token,
+            &HeuristicEvaluator {
+                rules: Rules::PlayerCollapse,
+                ..HeuristicEvaluator::default()
+            },
+        )
+    }
+
+    fn new_game(&mut self, rules: Rules, token: Token) {
+        self.search.new_game(rules, token);
+    }
+}
+
+/// How a level fared against an opponent, over games where each played X in about half.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Calibration {
+    pub level: Level,
+    pub opponent: Level,
+    pub games: usize,
+    /// Games where the level scored more than the opponent.
+    pub wins: usize,
+    /// Games where the opponent scored more than the level.
+    pub losses: usize,
+}
+
+impl Calibration {
+    /// The share of games the level won.
+    pub fn get_win_rate(&self) -> f32 {
+        self.wins as f32 / self.games.max(1) as f32
+    }
+
+    /// The share of games the level lost.
+    pub fn get_loss_rate(&self) -> f32 {
+        self.losses as f32 / self.games.max(1) as f32
+    }
+}
+
+/// Plays the level against the opponent through `simulate_games`, the level playing X in the
+/// first half of the games and O in the second, and counts who won.
+pub fn calibrate(
+    level: Level,
+    opponent: Level,
+    games: usize,
+    threads: usize,
+    seed: u64,
+) -> Calibration {
+    let games_as_x = games.div_ceil(2);
+
+    let records_as_x = simulate_games(games_as_x, threads, seed, |seed| BotPair {
+        bot_x: DifficultyBot::new(level, seed),
+        bot_o: DifficultyBot::new(opponent, seed.wrapping_add(1)),
+    });
+    let records_as_o = simulate_games(games - games_as_x, threads, !seed, |seed| BotPair {
+        bot_x: DifficultyBot::new(opponent, seed),
+        bot_o: DifficultyBot::new(level, seed.wrapping_add(1)),
+    });
+
+    // The level's score first.
+    let scores = records_as_x.iter().map(|record| record.score).chain(
+        records_as_o
+            .iter()
+            .map(|record| (record.score.1, record.score.0)),
+    );
+
+    let mut calibration = Calibration {
+        level,
+        opponent,
+        games,
+        wins: 0,
+        losses: 0,
+    };
+
+    for (score, opponent_score) in scores {
+        if score > opponent_score {
+            calibration.wins += 1;
+        } else if score < opponent_score {
+            calibration.losses += 1;
+        }
+    }
+
+    calibration
+}
+
+/// Calibrates every level but the strongest against the next one.
+pub fn calibrate_levels(games: usize, threads: usize, seed: u64) -> Vec<Calibration> {
+    Level::ALL
+        .into_iter()
+        .filter_map(|level| {
+            let opponent = level.get_next()?;
+            Some(calibrate(level, opponent, games, threads, seed))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test_difficulty {
+    use super::*;
+
+    use crate::test_support::assert_beats_random_bot;
+
+    #[test]
+    fn test_level_names() {
+        for level in Level::ALL {
+            assert_eq!(Some(level), Level::from_name(level.get_name()));
+        }
+        assert_eq!(None, Level::from_name("impossible"));
+        assert_eq!(Some(Level::Easy), Level::Beginner.get_next());
+        assert_eq!(None, Level::Expert.get_next());
+    }
+
+    #[test]
+    fn test_medium_beats_random_bot() {
+        assert_beats_random_bot(10, Token::X, |seed| DifficultyBot::new(Level::Medium, seed));
+    }
+
+    #[test]
+    fn test_calibration() {
+        let calibration = calibrate(Level::Beginner, Level::Medium, 40, 1, 0);
+
+        assert_eq!(40, calibration.games);
+        assert!(calibration.wins + calibration.losses <= 40);
+        assert!(
+            calibration.get_loss_rate() > calibration.get_win_rate(),
+            "{calibration:?}"
+        );
+        assert_eq!(
+            calibration,
+            calibrate(Level::Beginner, Level::Medium, 40, 2, 0)
+        );
+    }
+}
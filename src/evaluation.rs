@@ -0,0 +1,250 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Board, SpookyMark, Token, TurnToken};
+use crate::game::Rules;
+use crate::solver;
+
+/// A static estimate of how good a board is for X.
+pub trait Evaluator {
+    /// A value between -1 (O wins) and 1 (X wins), exact for boards whose game is over.
+    fn evaluate(&self, board: &Board) -> f32;
+}
+
+impl<E> Evaluator for Box<E>
+where
+    E: Evaluator + ?Sized,
+{
+    fn evaluate(&self, board: &Board) -> f32 {
+        (**self).evaluate(board)
+    }
+}
+
+/// The weights of the features of `HeuristicEvaluator`, each counted for X minus for O.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Weights {
+    /// Lines without classical marks of the opponent, by how many classical marks they hold.
+    pub open_lines: f32,
+    /// Lines whose squares all hold a classical mark or a spooky mark of the player, so a
+    /// collapse could complete them.
+    pub threatened_lines: f32,
+    /// Groups of entangled squares, by their size times the player's share of their spooky marks.
+    pub entanglement: f32,
+    /// Whether the player chooses how the pending cycle collapses.
+    pub collapse_control: f32,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Self {
+            open_lines: 0.1,
+            threatened_lines: 0.3,
+            entanglement: 0.05,
+            collapse_control: 0.5,
+        }
+    }
+}
+
+impl Weights {
+    /// Loads weights from a JSON file like `{"open_lines": 0.1, "threatened_lines": 0.3, ...}`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)
+    }
+}
+
+/// The largest heuristic score, below the half point of the closest finished games, so a
+/// search always prefers a win to any unfinished board.
+pub const HEURISTIC_RANGE: f32 = 0.4;
+
+fn get_sign(token: Token) -> f32 {
+    match token {
+        Token::X => 1.0,
+        Token::O => -1.0,
+    }
+}
+
+/// The default evaluation: a weighted sum of features, squashed into `HEURISTIC_RANGE`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HeuristicEvaluator {
+    pub rules: Rules,
+    pub weights: Weights,
+}
+
+impl HeuristicEvaluator {
+    pub fn new(rules: Rules, weights: Weights) -> Self {
+        Self { rules, weights }
+    }
+
+    fn get_open_lines(&self, board: &Board) -> f32 {
+        board
+            .rows_columns_and_diagonals
+            .iter()
+            .map(|line| {
+                let tokens = line
+                    .iter()
+                    .filter_map(|position| board.get_mark(*position))
+                    .map(|turn_token| Token::from(&turn_token))
+                    .collect::<Vec<_>>();
+
+                match tokens.first() {
+                    Some(token) if tokens.iter().all(|t| t == token) => {
+                        get_sign(*token) * tokens.len() as f32
+                    }
+                    _ => 0.0,
+                }
+            })
+            .sum()
+    }
+
+    fn get_threatened_lines(&self, board: &Board) -> f32 {
+        [Token::X, Token::O]
+            .into_iter()
+            .map(|token| {
+                let threatened = board.rows_columns_and_diagonals.iter().filter(|line| {
+                    let covered = line.iter().all(|position| match board.get_mark(*position) {
+                        Some(turn_token) => Token::from(&turn_token) == token,
+                        None => board.spooky_marks.iter().any(|SpookyMark(p1, p2, t)| {
+                            Token::from(t) == token && (p1 == position || p2 == position)
+                        }),
+                    });
+
+                    let has_spooky_mark = line.iter().any(|p| board.get_mark(*p).is_none());
+
+                    covered && has_spooky_mark
+                });
+
+                get_sign(token) * threatened.count() as f32
+            })
+            .sum()
+    }
+
+    fn get_entanglement(&self, board: &Board) -> f32 {
+        // Groups of entangled squares, found by merging the squares of every spooky mark.
+        let mut groups: Vec<Vec<&SpookyMark>> = vec![];
+
+        for spooky_mark in &board.spooky_marks {
+            let (touching, mut others): (Vec<_>, Vec<_>) = groups.into_iter().partition(|group| {
+                group.iter().any(|m| {
+                    [m.0, m.1].contains(&spooky_mark.0) || [m.0, m.1].contains(&spooky_mark.1)
+                })
+            });
+
+            let mut merged = touching.into_iter().flatten().collect::<Vec<_>>();
+            merged.push(spooky_mark);
+            others.push(merged);
+
+            groups = others;
+        }
+
+        groups
+            .iter()
+            .map(|group| {
+                // A tree of spooky marks spans one more square than it has marks.
+                let size = group.len() as f32 + 1.0;
+                let share = group
+                    .iter()
+                    .map(|SpookyMark(_, _, turn_token)| match turn_token {
+                        TurnToken::X(_) => 1.0,
+                        TurnToken::O(_) => -1.0,
+                    })
+                    .sum::<f32>()
+                    / group.len() as f32;
+
+                size * share
+            })
+            .sum()
+    }
+
+    fn get_collapse_control(&self, board: &Board) -> f32 {
+        match (self.rules, board.get_pending_collapse()) {
+            (Rules::PlayerCollapse, Some(_)) => get_sign(board.get_current_token()),
+            _ => 0.0,
+        }
+    }
+}
+
+impl Evaluator for HeuristicEvaluator {
+    fn evaluate(&self, board: &Board) -> f32 {
+        if board.is_game_over() {
+            return solver::get_value(board);
+        }
+
+        let weights = &self.weights;
+
+        let score = weights.open_lines * self.get_open_lines(board)
+            + weights.threatened_lines * self.get_threatened_lines(board)
+            + weights.entanglement * self.get_entanglement(board)
+            + weights.collapse_control * self.get_collapse_control(board);
+
+        HEURISTIC_RANGE * score.tanh()
+    }
+}
+
+#[cfg(test)]
+mod test_heuristic_evaluator {
+    use super::*;
+
+    use crate::board::Position;
+
+    #[test]
+    fn test_features() {
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(0, 0), Token::X);
+        board.do_turn(Position::new(1, 1), Position::new(2, 2), Token::O);
+        board.do_turn(Position::new(0, 1), Position::new(0, 2), Token::X);
+
+        let evaluator = HeuristicEvaluator::default();
+
+        // X's corner opens a row, a column and a diagonal, and X's marks cover the top row.
+        assert_eq!(3.0, evaluator.get_open_lines(&board));
+        assert_eq!(1.0, evaluator.get_threatened_lines(&board));
+        assert_eq!(0.0, evaluator.get_entanglement(&board));
+        assert!(evaluator.evaluate(&board) > 0.0);
+
+        board.do_turn(Position::new(0, 2), Position::new(1, 1), Token::O);
+        assert!((evaluator.get_entanglement(&board) + 4.0 / 3.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_collapse_control_and_weights_file() {
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::X);
+        board.do_turn(Position::new(1, 1), Position::new(0, 0), Token::O);
+
+        let weights = Weights {
+            open_lines: 0.0,
+            threatened_lines: 0.0,
+            entanglement: 0.0,
+            collapse_control: 1.0,
+        };
+
+        // X chooses how O's cycle collapses.
+        let evaluator = HeuristicEvaluator::new(Rules::PlayerCollapse, weights);
+        assert_eq!(HEURISTIC_RANGE * 1.0f32.tanh(), evaluator.evaluate(&board));
+
+        // However strong, a heuristic score stays below a finished game won on half a point.
+        let evaluator = HeuristicEvaluator::new(
+            Rules::PlayerCollapse,
+            Weights {
+                collapse_control: 100.0,
+                ..weights
+            },
+        );
+        assert!(evaluator.evaluate(&board) < 0.5);
+
+        let evaluator = HeuristicEvaluator::new(Rules::RandomCollapse, weights);
+        assert_eq!(0.0, evaluator.evaluate(&board));
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("weights.json");
+        weights.save(&path).unwrap();
+        assert_eq!(weights, Weights::load(&path).unwrap());
+    }
+}
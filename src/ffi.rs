@@ -0,0 +1,484 @@
+//! A C interface to the board, for hosts that embed the engine.
+//!
+//! The header is in `include/tiqtaqtoe.h`, regenerated by building with the `c-header` feature.
+
+use std::ffi::c_char;
+use std::ptr;
+
+use rand::prelude::{SeedableRng, StdRng};
+
+use crate::board::{Board, MoveError, Position, SpookyMark, Token};
+
+/// An opaque handle to a board, with its own RNG for random collapses.
+pub struct TqtBoard {
+    board: Board,
+    rng: StdRng,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TqtPosition {
+    pub row: u8,
+    pub column: u8,
+}
+
+impl From<TqtPosition> for Position {
+    fn from(position: TqtPosition) -> Self {
+        Position::new(position.row as usize, position.column as usize)
+    }
+}
+
+impl From<Position> for TqtPosition {
+    fn from(position: Position) -> Self {
+        Self {
+            row: position.row() as u8,
+            column: position.column() as u8,
+        }
+    }
+}
+
+/// A move, or a spooky mark, between two positions. Both are the same for a classical move.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TqtMove {
+    pub first: TqtPosition,
+    pub second: TqtPosition,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TqtToken {
+    X = 0,
+    O = 1,
+}
+
+impl From<TqtToken> for Token {
+    fn from(token: TqtToken) -> Self {
+        match token {
+            TqtToken::X => Token::X,
+            TqtToken::O => Token::O,
+        }
+    }
+}
+
+impl From<Token> for TqtToken {
+    fn from(token: Token) -> Self {
+        match token {
+            Token::X => TqtToken::X,
+            Token::O => TqtToken::O,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TqtStatus {
+    Ok = 0,
+    InvalidPosition,
+    Occupied,
+    Entangled,
+    WrongTurn,
+    CollapsePending,
+    NoCollapsePending,
+    InvalidCollapse,
+    GameOver,
+    NullPointer,
+}
+
+impl From<Result<(), MoveError>> for TqtStatus {
+    fn from(result: Result<(), MoveError>) -> Self {
+        match result {
+            Ok(()) => TqtStatus::Ok,
+            Err(MoveError::InvalidPosition(_)) => TqtStatus::InvalidPosition,
+            Err(MoveError::Occupied(_)) => TqtStatus::Occupied,
+            Err(MoveError::Entangled(_)) => TqtStatus::Entangled,
+            Err(MoveError::WrongTurn(_)) => TqtStatus::WrongTurn,
+            Err(MoveError::CollapsePending) => TqtStatus::CollapsePending,
+            Err(MoveError::NoCollapsePending) => TqtStatus::NoCollapsePending,
+            Err(MoveError::InvalidCollapse(_)) => TqtStatus::InvalidCollapse,
+            Err(MoveError::GameOver) => TqtStatus::GameOver,
+        }
+    }
+}
+
+/// Creates an empty board, whose random collapses are driven by the seed.
+/// Free it with `tqt_board_free`.
+#[no_mangle]
+pub extern "C" fn tqt_board_new(seed: u64) -> *mut TqtBoard {
+    Box::into_raw(Box::new(TqtBoard {
+        board: Board::new(),
+        rng: StdRng::seed_from_u64(seed),
+    }))
+}
+
+/// Copies a board, including the state of its RNG. Returns null if `board` is null.
+///
+/// # Safety
+///
+/// `board` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_clone(board: *const TqtBoard) -> *mut TqtBoard {
+    match board.as_ref() {
+        Some(board) => Box::into_raw(Box::new(TqtBoard {
+            board: board.board.clone(),
+            rng: board.rng.clone(),
+        })),
+        None => ptr::null_mut(),
+    }
+}
+
+/// # Safety
+///
+/// `board` must be null or a handle from this library that isn't used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_free(board: *mut TqtBoard) {
+    if !board.is_null() {
+        drop(Box::from_raw(board));
+    }
+}
+
+/// The turn number, starting at 1, or 0 if `board` is null.
+///
+/// # Safety
+///
+/// `board` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_turn(board: *const TqtBoard) -> u8 {
+    board.as_ref().map_or(0, |board| board.board.turn)
+}
+
+/// Whose turn it is. A null `board` gives X as well, so check handles for null beforehand.
+///
+/// # Safety
+///
+/// `board` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_current_token(board: *const TqtBoard) -> TqtToken {
+    board
+        .as_ref()
+        .map_or(TqtToken::X, |board| board.board.get_current_token().into())
+}
+
+/// Plays a move for `token`, a `TqtToken` passed as an integer, since C can pass any value for
+/// an enum. Values other than `TQT_TOKEN_X` and `TQT_TOKEN_O` are refused as the wrong turn.
+///
+/// # Safety
+///
+/// `board` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_do_turn(
+    board: *mut TqtBoard,
+    position_1: TqtPosition,
+    position_2: TqtPosition,
+    token: u8,
+) -> TqtStatus {
+    let Some(board) = board.as_mut() else {
+        return TqtStatus::NullPointer;
+    };
+    let token = match token {
+        0 => TqtToken::X,
+        1 => TqtToken::O,
+        _ => return TqtStatus::WrongTurn,
+    };
+
+    board
+        .board
+        .try_do_turn(position_1.into(), position_2.into(), token.into())
+        .into()
+}
+
+/// Writes the spooky mark that has to be collapsed into `spooky_mark`, if there is one.
+///
+/// # Safety
+///
+/// `board` must be null or a live handle from this library, and `spooky_mark` null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_pending_collapse(
+    board: *const TqtBoard,
+    spooky_mark: *mut TqtMove,
+) -> bool {
+    let Some(SpookyMark(position_1, position_2, _)) = board
+        .as_ref()
+        .and_then(|board| board.board.get_pending_collapse())
+    else {
+        return false;
+    };
+
+    if let Some(spooky_mark) = spooky_mark.as_mut() {
+        *spooky_mark = TqtMove {
+            first: position_1.into(),
+            second: position_2.into(),
+        };
+    }
+
+    true
+}
+
+/// Resolves the pending cycle by putting its spooky mark in the chosen position.
+///
+/// # Safety
+///
+/// `board` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_collapse(
+    board: *mut TqtBoard,
+    position: TqtPosition,
+) -> TqtStatus {
+    match board.as_mut() {
+        Some(board) => {
+            if !Position::from(position).is_valid() {
+                return TqtStatus::InvalidPosition;
+            }
+
+            board.board.collapse(position.into()).into()
+        }
+        None => TqtStatus::NullPointer,
+    }
+}
+
+/// Resolves the pending cycle by a coin flip from the board's RNG.
+///
+/// # Safety
+///
+/// `board` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_collapse_random(board: *mut TqtBoard) -> TqtStatus {
+    match board.as_mut() {
+        Some(board) => match board.board.collapse_loop(&mut board.rng) {
+            Some(_) => TqtStatus::Ok,
+            None => TqtStatus::NoCollapsePending,
+        },
+        None => TqtStatus::NullPointer,
+    }
+}
+
+/// Writes up to `capacity` legal moves into `moves` and returns how many there are in total.
+///
+/// # Safety
+///
+/// `board` must be null or a live handle from this library, and `moves` must be null or point
+/// to at least `capacity` writable moves.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_legal_moves(
+    board: *const TqtBoard,
+    moves: *mut TqtMove,
+    capacity: usize,
+) -> usize {
+    let Some(board) = board.as_ref() else {
+        return 0;
+    };
+
+    let legal_moves = board.board.get_legal_moves();
+
+    if !moves.is_null() {
+        for (index, (position_1, position_2)) in legal_moves.iter().take(capacity).enumerate() {
+            *moves.add(index) = TqtMove {
+                first: (*position_1).into(),
+                second: (*position_2).into(),
+            };
+        }
+    }
+
+    legal_moves.len()
+}
+
+/// # Safety
+///
+/// `board` must be null or a live handle from this library.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_is_game_over(board: *const TqtBoard) -> bool {
+    board
+        .as_ref()
+        .is_some_and(|board| board.board.is_game_over())
+}
+
+/// Writes the scores of X and O, each 0, 0.5 or 1.
+///
+/// # Safety
+///
+/// `board` must be null or a live handle from this library, and `score_x` and `score_o` null
+/// or writable.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_score(
+    board: *const TqtBoard,
+    score_x: *mut f32,
+    score_o: *mut f32,
+) -> TqtStatus {
+    let Some(board) = board.as_ref() else {
+        return TqtStatus::NullPointer;
+    };
+
+    let (x, o) = board.board.get_score();
+
+    if let Some(score_x) = score_x.as_mut() {
+        *score_x = x;
+    }
+
+    if let Some(score_o) = score_o.as_mut() {
+        *score_o = o;
+    }
+
+    TqtStatus::Ok
+}
+
+/// Renders the board as UTF-8 text into `buffer`, like `snprintf`: at most `capacity` bytes
+/// are written, always null terminated, and the length of the whole text is returned.
+///
+/// # Safety
+///
+/// `board` must be null or a live handle from this library, and `buffer` must be null or point
+/// to at least `capacity` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn tqt_board_render(
+    board: *const TqtBoard,
+    buffer: *mut c_char,
+    capacity: usize,
+) -> usize {
+    let Some(board) = board.as_ref() else {
+        return 0;
+    };
+
+    let text = board.board.to_string();
+
+    if !buffer.is_null() && capacity > 0 {
+        let length = text.len().min(capacity - 1);
+        ptr::copy_nonoverlapping(text.as_ptr().cast::<c_char>(), buffer, length);
+        *buffer.add(length) = 0;
+    }
+
+    text.len()
+}
+
+#[cfg(test)]
+mod test_ffi {
+    use super::*;
+
+    use std::ffi::CStr;
+
+    fn position(row: u8, column: u8) -> TqtPosition {
+        TqtPosition { row, column }
+    }
+
+    #[test]
+    fn test_turns_and_collapse() {
+        unsafe {
+            let board = tqt_board_new(7);
+
+            assert_eq!(81, tqt_board_legal_moves(board, ptr::null_mut(), 0));
+            assert_eq!(
+                TqtStatus::Ok,
+                tqt_board_do_turn(board, position(0, 0), position(1, 1), TqtToken::X as u8)
+            );
+            assert_eq!(
+                TqtStatus::WrongTurn,
+                tqt_board_do_turn(board, position(0, 1), position(1, 2), TqtToken::X as u8)
+            );
+            assert_eq!(
+                TqtStatus::InvalidPosition,
+                tqt_board_do_turn(board, position(0, 1), position(4, 2), TqtToken::O as u8)
+            );
+            assert_eq!(
+                TqtStatus::WrongTurn,
+                tqt_board_do_turn(board, position(0, 1), position(1, 2), 2)
+            );
+            assert_eq!(
+                TqtStatus::Ok,
+                tqt_board_do_turn(board, position(1, 1), position(0, 0), TqtToken::O as u8)
+            );
+
+            let mut spooky_mark = TqtMove::default();
+            assert!(tqt_board_pending_collapse(board, &mut spooky_mark));
+            assert_eq!(position(0, 0), spooky_mark.first);
+
+            let copy = tqt_board_clone(board);
+            assert_eq!(
+                TqtStatus::InvalidCollapse,
+                tqt_board_collapse(copy, position(2, 2))
+            );
+            assert_eq!(TqtStatus::Ok, tqt_board_collapse(copy, position(1, 1)));
+            assert!(!tqt_board_pending_collapse(copy, ptr::null_mut()));
+            tqt_board_free(copy);
+
+            assert_eq!(TqtStatus::Ok, tqt_board_collapse_random(board));
+            assert_eq!(
+                TqtStatus::NoCollapsePending,
+                tqt_board_collapse_random(board)
+            );
+
+            let mut moves = [TqtMove::default(); 4];
+            assert_eq!(49, tqt_board_legal_moves(board, moves.as_mut_ptr(), 4));
+            assert_eq!(position(0, 1), moves[0].first);
+            assert_eq!(TqtToken::X, tqt_board_current_token(board));
+
+            let (mut score_x, mut score_o) = (1.0, 1.0);
+            tqt_board_score(board, &mut score_x, &mut score_o);
+            assert_eq!((0.0, 0.0), (score_x, score_o));
+
+            tqt_board_free(board);
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        unsafe {
+            let board = tqt_board_new(0);
+            tqt_board_do_turn(board, position(0, 0), position(0, 0), TqtToken::X as u8);
+
+            let length = tqt_board_render(board, ptr::null_mut(), 0);
+            let mut buffer = vec![0 as c_char; length + 1];
+            assert_eq!(
+                length,
+                tqt_board_render(board, buffer.as_mut_ptr(), buffer.len())
+            );
+
+            let text = CStr::from_ptr(buffer.as_ptr()).to_str().unwrap();
+            assert_eq!((*board).board.to_string(), text);
+
+            let mut short = [1 as c_char; 4];
+            tqt_board_render(board, short.as_mut_ptr(), short.len());
+            assert_eq!(0, short[3]);
+
+            tqt_board_free(board);
+        }
+    }
+
+    #[test]
+    fn test_null_handles() {
+        unsafe {
+            assert_eq!(
+                TqtStatus::NullPointer,
+                tqt_board_do_turn(
+                    ptr::null_mut(),
+                    position(0, 0),
+                    position(0, 0),
+                    TqtToken::X as u8
+                )
+            );
+            assert!(tqt_board_clone(ptr::null()).is_null());
+            assert_eq!(0, tqt_board_turn(ptr::null()));
+            assert_eq!(TqtToken::X, tqt_board_current_token(ptr::null()));
+            assert!(!tqt_board_is_game_over(ptr::null()));
+            assert_eq!(0, tqt_board_legal_moves(ptr::null(), ptr::null_mut(), 0));
+            tqt_board_free(ptr::null_mut());
+        }
+    }
+
+    // The build script generates the header into OUT_DIR. Run with UPDATE_C_HEADER=1 to write
+    // it over the committed one.
+    #[cfg(feature = "c-header")]
+    #[test]
+    fn test_header_is_up_to_date() {
+        let generated = include_str!(concat!(env!("OUT_DIR"), "/tiqtaqtoe.h"));
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/include/tiqtaqtoe.h");
+
+        if std::env::var_os("UPDATE_C_HEADER").is_some() {
+            std::fs::write(path, generated).unwrap();
+        }
+
+        assert_eq!(
+            generated,
+            std::fs::read_to_string(path).unwrap(),
+            "include/tiqtaqtoe.h is out of date, run the test with UPDATE_C_HEADER=1"
+        );
+    }
+}
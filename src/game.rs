@@ -1,51 +1,412 @@
-use crate::board::{Board, Token};
-use crate::bot::Bot;
+use std::future::{self, Future};
+use std::pin::pin;
+use std::task::{Context, Poll, Waker};
+use std::time::{Duration, Instant};
 
-pub struct Game<B>
-where
-    B: Bot,
-{
+use derive_more::Display;
+use rand::prelude::{SeedableRng, StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::async_bot::{timeout, AsyncBot};
+use crate::board::{Board, MoveError, Position, SpookyMark, Token};
+use crate::bot::{Bot, Offer};
+
+/// How cycles of spooky marks are resolved.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rules {
+    /// The collapse is decided by a coin flip.
+    #[default]
+    RandomCollapse,
+    /// The player who didn't close the cycle chooses how it collapses.
+    PlayerCollapse,
+}
+
+/// How much time the players get for their moves.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TimeControl {
+    /// Every move is given this budget.
+    PerMove(Duration),
+    /// Each player has a clock for the whole game, starting at `initial` and gaining
+    /// `increment` after each of their moves. A player whose clock runs out loses.
+    Clock {
+        initial: Duration,
+        increment: Duration,
+    },
+}
+
+/// Why a player lost the game before it ended on the board.
+#[derive(Clone, Debug, Display, Eq, PartialEq)]
+pub enum Forfeit {
+    #[display("out of time")]
+    Time,
+    #[display("{_0}")]
+    IllegalMove(MoveError),
+    /// The bot couldn't come up with a move, as told by `Bot::get_failure`.
+    #[display("{_0}")]
+    Failure(String),
+    #[display("resigned")]
+    Resigned,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct TurnRecord {
+    pub token: Token,
+    pub positions: (Position, Position),
+    /// The spooky mark collapsed after this turn, and the position it collapsed to.
+    pub collapse: Option<(SpookyMark, Position)>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct GameRecord {
+    pub turns: Vec<TurnRecord>,
+    pub score: (f32, f32),
+}
+
+/// A game between the players of a `Bot`, or of an `AsyncBot` through the `_async` methods.
+pub struct Game<B> {
     bot: B,
     board: Board,
     tokens: [Token; 2],
+    rng: StdRng,
+    verbose: bool,
+    turns: Vec<TurnRecord>,
+    time_control: Option<TimeControl>,
+    clocks: [Duration; 2],
+    forfeit: Option<(Token, Forfeit)>,
+    draw_agreed: bool,
+    rules: Rules,
+    started: bool,
+    pondering: bool,
 }
 
-impl<B> Game<B>
-where
-    B: Bot,
-{
-    pub fn new(bot: B) -> Self {
+impl<B> Game<B> {
+    /// Creates a game whose random collapses are driven by the seed, so that a game between
+    /// seeded bots is reproducible.
+    pub fn new(bot: B, seed: u64) -> Self {
         Self {
             bot,
             board: Board::new(),
             tokens: [Token::X, Token::O],
+            rng: StdRng::seed_from_u64(seed),
+            verbose: true,
+            turns: vec![],
+            time_control: None,
+            clocks: [Duration::ZERO; 2],
+            forfeit: None,
+            draw_agreed: false,
+            rules: Rules::default(),
+            started: false,
+            pondering: false,
         }
     }
 
-    pub fn play_turn(&mut self) {
+    /// Whether every turn and board is printed while playing.
+    pub fn set_verbose(&mut self, verbose: bool) {
+        self.verbose = verbose;
+    }
+
+    /// How cycles are collapsed, at random by default. Under `Rules::PlayerCollapse`, the
+    /// player who didn't close the cycle chooses through `Bot::choose_collapse`.
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+
+    /// Whether the players think on their opponent's time, through `Bot::start_pondering` after
+    /// each of their turns and `Bot::stop_pondering` before the next one. Off by default.
+    pub fn set_pondering(&mut self, pondering: bool) {
+        self.pondering = pondering;
+    }
+
+    /// Plays the following moves with time budgets, through `Bot::get_timed_move`.
+    pub fn set_time_control(&mut self, time_control: TimeControl) {
+        self.time_control = Some(time_control);
+
+        if let TimeControl::Clock { initial, .. } = time_control {
+            self.clocks = [initial; 2];
+        }
+    }
+
+    /// The time left on the player's clock, when playing with one.
+    pub fn get_clock(&self, token: Token) -> Option<Duration> {
+        match self.time_control {
+            Some(TimeControl::Clock { .. }) => Some(self.clocks[token as usize]),
+            _ => None,
+        }
+    }
+
+    /// The player who forfeited the game and why, if any.
+    pub fn get_forfeit(&self) -> Option<&(Token, Forfeit)> {
+        self.forfeit.as_ref()
+    }
+
+    /// The player who ran out of time, if any.
+    pub fn get_time_forfeit(&self) -> Option<Token> {
+        match self.forfeit {
+            Some((token, Forfeit::Time)) => Some(token),
+            _ => None,
+        }
+    }
+
+    /// Whether the players agreed to a draw, through `Offer::Draw`.
+    pub fn is_draw_agreed(&self) -> bool {
+        self.draw_agreed
+    }
+
+    pub fn is_game_over(&self) -> bool {
+        self.forfeit.is_some() || self.draw_agreed || self.board.is_game_over()
+    }
+
+    /// The score of the board, a whole point for the opponent of a player who forfeited, or no
+    /// points for an agreed draw, as for a tie on the board.
+    pub fn get_score(&self) -> (f32, f32) {
+        match self.forfeit {
+            Some((Token::X, _)) => (0.0, 1.0),
+            Some((Token::O, _)) => (1.0, 0.0),
+            None if self.draw_agreed => (0.0, 0.0),
+            None => self.board.get_score(),
+        }
+    }
+
+    fn forfeit(&mut self, token: Token, forfeit: Forfeit) {
+        if self.verbose {
+            println!("{token} forfeits: {forfeit}");
+        }
+
+        self.forfeit = Some((token, forfeit));
+    }
+
+    // The budget of the next move: a share of the clock spread over the player's remaining
+    // moves, plus the increment.
+    fn get_budget(&self, time_control: TimeControl, token: Token) -> Duration {
+        match time_control {
+            TimeControl::PerMove(budget) => budget,
+            TimeControl::Clock { increment, .. } => {
+                let clock = self.clocks[token as usize];
+                let moves_left = (10 - self.board.turn as u32).div_ceil(2).max(1);
+
+                (clock / moves_left + increment).min(clock)
+            }
+        }
+    }
+
+    pub fn get_board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn get_record(&self) -> GameRecord {
+        GameRecord {
+            turns: self.turns.clone(),
+            score: self.get_score(),
+        }
+    }
+
+    /// The bot, or the pair of bots, playing the game.
+    pub fn get_bot(&self) -> &B {
+        &self.bot
+    }
+
+    // Starts the game if it hasn't started yet, plays the next turn, and ends the game if it's
+    // over after it.
+    async fn play_turn_with<D>(&mut self)
+    where
+        D: Driver<B>,
+    {
+        if self.is_game_over() {
+            return;
+        }
+
+        if !self.started {
+            self.started = true;
+            for token in self.tokens {
+                D::new_game(&mut self.bot, self.rules, token).await;
+            }
+        }
+
         let token = self.tokens[(self.board.turn - 1) as usize % 2];
+        if self.pondering {
+            D::stop_pondering(&mut self.bot, token);
+        }
 
-        let random_move = self.bot.get_next_move(&self.board, token);
+        self.take_turn::<D>().await;
 
-        self.board.do_turn(random_move.0, random_move.1, token);
+        if self.is_game_over() {
+            let score = self.get_score();
+            for token in self.tokens {
+                if self.pondering {
+                    D::stop_pondering(&mut self.bot, token);
+                }
+                D::game_over(&mut self.bot, &self.board, token, score).await;
+            }
+        } else if self.pondering {
+            D::start_pondering(&mut self.bot, &self.board, token);
+        }
+    }
 
-        println!("Board before collapse:");
-        print!("{}", self.board);
-        self.board.collapse_loop();
-        println!("Board after collapse:");
-        print!("{}", self.board);
+    async fn take_turn<D>(&mut self)
+    where
+        D: Driver<B>,
+    {
+        let token = self.tokens[(self.board.turn - 1) as usize % 2];
+
+        match D::get_offer(&mut self.bot, &self.board, token).await {
+            Some(Offer::Resign) => {
+                self.forfeit(token, Forfeit::Resigned);
+                return;
+            }
+            Some(Offer::Draw) => {
+                let accepted =
+                    D::accept_draw(&mut self.bot, &self.board, token.get_opponent()).await;
+
+                if self.verbose {
+                    let answer = if accepted { "accepted" } else { "declined" };
+                    println!(
+                        "{token} offers a draw, which {} {answer}",
+                        token.get_opponent()
+                    );
+                }
+
+                if accepted {
+                    self.draw_agreed = true;
+                    return;
+                }
+            }
+            None => {}
+        }
+
+        let random_move = match self.time_control {
+            Some(time_control) => {
+                let budget = self.get_budget(time_control, token);
+                let start = Instant::now();
+                let get_move = D::get_move(&mut self.bot, &self.board, token, Some(budget));
+
+                // A player isn't waited for past the end of their clock.
+                let random_move = match time_control {
+                    TimeControl::PerMove(_) => get_move.await,
+                    TimeControl::Clock { .. } => {
+                        match D::within(get_move, self.clocks[token as usize]).await {
+                            Some(random_move) => random_move,
+                            None => {
+                                self.clocks[token as usize] = Duration::ZERO;
+                                self.forfeit(token, Forfeit::Time);
+                                return;
+                            }
+                        }
+                    }
+                };
+
+                if let TimeControl::Clock { increment, .. } = time_control {
+                    let clock = &mut self.clocks[token as usize];
+
+                    match clock.checked_sub(start.elapsed()) {
+                        Some(left) => *clock = left + increment,
+                        None => {
+                            *clock = Duration::ZERO;
+                            self.forfeit(token, Forfeit::Time);
+                            return;
+                        }
+                    }
+                }
+
+                random_move
+            }
+            None => D::get_move(&mut self.bot, &self.board, token, None).await,
+        };
+
+        if let Some(failure) = D::get_failure(&mut self.bot, token) {
+            self.forfeit(token, Forfeit::Failure(failure));
+            return;
+        }
+
+        if self.verbose {
+            if random_move.0 == random_move.1 {
+                println!("Putting classical {token} in {}", random_move.0);
+            } else {
+                println!("Putting {token} in {} and {}", random_move.0, random_move.1);
+            }
+        }
+
+        if let Err(error) = self.board.try_do_turn(random_move.0, random_move.1, token) {
+            self.forfeit(token, Forfeit::IllegalMove(error));
+            return;
+        }
+
+        D::notify_move(
+            &mut self.bot,
+            &self.board,
+            token.get_opponent(),
+            random_move,
+        )
+        .await;
+
+        if self.verbose {
+            println!("Board before collapse:");
+            print!("{}", self.board);
+        }
+
+        let collapse = match self.collapse::<D>().await {
+            Ok(collapse) => collapse,
+            Err((chooser, forfeit)) => {
+                self.turns.push(TurnRecord {
+                    token,
+                    positions: random_move,
+                    collapse: None,
+                });
+                self.forfeit(chooser, forfeit);
+                return;
+            }
+        };
+
+        if self.verbose {
+            println!("Board after collapse:");
+            print!("{}", self.board);
+        }
+
+        if let Some((spooky_mark, position)) = collapse {
+            for token in self.tokens {
+                D::notify_collapse(&mut self.bot, &self.board, token, spooky_mark, position).await;
+            }
+        }
+
+        self.turns.push(TurnRecord {
+            token,
+            positions: random_move,
+            collapse,
+        });
     }
 
-    pub fn play_whole_game(&mut self) {
-        while self.board.turn <= 9 && self.board.get_score() == (0.0, 0.0) {
-            self.play_turn();
+    // Resolves the pending cycle, if any, by the rules, or tells who forfeited choosing it.
+    async fn collapse<D>(&mut self) -> Result<Option<(SpookyMark, Position)>, (Token, Forfeit)>
+    where
+        D: Driver<B>,
+    {
+        let Some(spooky_mark) = self.board.get_pending_collapse() else {
+            return Ok(None);
+        };
+
+        match self.rules {
+            Rules::RandomCollapse => Ok(self.board.collapse_loop(&mut self.rng)),
+            Rules::PlayerCollapse => {
+                // The player who didn't close the cycle is the one to move next.
+                let chooser = self.board.get_current_token();
+                let position = D::choose_collapse(&mut self.bot, &self.board, chooser).await;
+
+                if let Some(failure) = D::get_failure(&mut self.bot, chooser) {
+                    return Err((chooser, Forfeit::Failure(failure)));
+                }
+
+                self.board
+                    .collapse(position)
+                    .map(|_| Some((spooky_mark, position)))
+                    .map_err(|error| (chooser, Forfeit::IllegalMove(error)))
+            }
         }
     }
 
     pub fn print_winner(&self) {
         println!("Final board:");
         print!("{}", self.board);
-        let board_score = self.board.get_score();
+        let board_score = self.get_score();
 
         let winner = if board_score.0 == 1.0 {
             Some(Token::X)
@@ -69,20 +430,598 @@ where
     }
 }
 
+impl<B> Game<B>
+where
+    B: Bot,
+{
+    /// Plays the next turn, starting the game with `Bot::new_game` first if it hasn't started
+    /// yet, and ending it with `Bot::game_over` if it's over after the turn.
+    pub fn play_turn(&mut self) {
+        now(self.play_turn_with::<Blocking>());
+    }
+
+    pub fn play_whole_game(&mut self) {
+        while !self.is_game_over() {
+            self.play_turn();
+        }
+    }
+}
+
+impl<B> Game<B>
+where
+    B: AsyncBot,
+{
+    /// Plays the next turn like `play_turn`, waiting on the bots without blocking the thread.
+    ///
+    /// Under `TimeControl::Clock`, a player who hasn't moved when their clock runs out forfeits
+    /// right away. Moves are waited for as long as they take otherwise, so players that might
+    /// never answer need a clock, or a timeout of the caller's.
+    pub async fn play_turn_async(&mut self) {
+        self.play_turn_with::<Waiting>().await;
+    }
+
+    pub async fn play_whole_game_async(&mut self) {
+        while !self.is_game_over() {
+            self.play_turn_async().await;
+        }
+    }
+}
+
+// Runs a future whose bot calls are all ready right away, as they are for a `Bot`.
+fn now<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    match pin!(future).poll(&mut Context::from_waker(Waker::noop())) {
+        Poll::Ready(output) => output,
+        Poll::Pending => unreachable!("A blocking bot always answers right away"),
+    }
+}
+
+// How a game calls its bot, so that the turns are written once for both kinds of bots: the
+// futures of a `Bot` are ready as soon as they are made, while those of an `AsyncBot` wait.
+trait Driver<B> {
+    fn get_move(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        budget: Option<Duration>,
+    ) -> impl Future<Output = (Position, Position)> + Send;
+
+    fn get_failure(bot: &mut B, token: Token) -> Option<String>;
+
+    // The output of the future, or `None` if it isn't ready within the limit.
+    fn within<F>(future: F, limit: Duration) -> impl Future<Output = Option<F::Output>> + Send
+    where
+        F: Future + Send;
+
+    fn choose_collapse(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = Position> + Send;
+
+    fn new_game(bot: &mut B, rules: Rules, token: Token) -> impl Future<Output = ()> + Send;
+
+    fn get_offer(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = Option<Offer>> + Send;
+
+    fn accept_draw(bot: &mut B, board: &Board, token: Token) -> impl Future<Output = bool> + Send;
+
+    fn notify_move(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        positions: (Position, Position),
+    ) -> impl Future<Output = ()> + Send;
+
+    fn notify_collapse(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        spooky_mark: SpookyMark,
+        position: Position,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn game_over(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        score: (f32, f32),
+    ) -> impl Future<Output = ()> + Send;
+
+    fn start_pondering(bot: &mut B, board: &Board, token: Token);
+
+    fn stop_pondering(bot: &mut B, token: Token);
+}
+
+struct Blocking;
+
+impl<B> Driver<B> for Blocking
+where
+    B: Bot,
+{
+    fn get_move(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        budget: Option<Duration>,
+    ) -> impl Future<Output = (Position, Position)> + Send {
+        future::ready(match budget {
+            Some(budget) => bot.get_timed_move(board, token, budget),
+            None => bot.get_next_move(board, token),
+        })
+    }
+
+    fn get_failure(bot: &mut B, token: Token) -> Option<String> {
+        bot.get_failure(token)
+    }
+
+    // The answer is already there, and the time it took is counted once it's played.
+    async fn within<F>(future: F, _limit: Duration) -> Option<F::Output>
+    where
+        F: Future + Send,
+    {
+        Some(future.await)
+    }
+
+    fn choose_collapse(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = Position> + Send {
+        future::ready(bot.choose_collapse(board, token))
+    }
+
+    fn new_game(bot: &mut B, rules: Rules, token: Token) -> impl Future<Output = ()> + Send {
+        bot.new_game(rules, token);
+        future::ready(())
+    }
+
+    fn get_offer(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = Option<Offer>> + Send {
+        future::ready(bot.get_offer(board, token))
+    }
+
+    fn accept_draw(bot: &mut B, board: &Board, token: Token) -> impl Future<Output = bool> + Send {
+        future::ready(bot.accept_draw(board, token))
+    }
+
+    fn notify_move(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        positions: (Position, Position),
+    ) -> impl Future<Output = ()> + Send {
+        bot.notify_move(board, token, positions);
+        future::ready(())
+    }
+
+    fn notify_collapse(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        spooky_mark: SpookyMark,
+        position: Position,
+    ) -> impl Future<Output = ()> + Send {
+        bot.notify_collapse(board, token, spooky_mark, position);
+        future::ready(())
+    }
+
+    fn game_over(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        score: (f32, f32),
+    ) -> impl Future<Output = ()> + Send {
+        bot.game_over(board, token, score);
+        future::ready(())
+    }
+
+    fn start_pondering(bot: &mut B, board: &Board, token: Token) {
+        bot.start_pondering(board, token)
+    }
+
+    fn stop_pondering(bot: &mut B, token: Token) {
+        bot.stop_pondering(token)
+    }
+}
+
+struct Waiting;
+
+impl<B> Driver<B> for Waiting
+where
+    B: AsyncBot,
+{
+    async fn get_move(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        budget: Option<Duration>,
+    ) -> (Position, Position) {
+        match budget {
+            Some(budget) => bot.get_timed_move(board, token, budget).await,
+            None => bot.get_next_move(board, token).await,
+        }
+    }
+
+    fn get_failure(bot: &mut B, token: Token) -> Option<String> {
+        bot.get_failure(token)
+    }
+
+    fn within<F>(future: F, limit: Duration) -> impl Future<Output = Option<F::Output>> + Send
+    where
+        F: Future + Send,
+    {
+        timeout(future, limit)
+    }
+
+    fn choose_collapse(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = Position> + Send {
+        bot.choose_collapse(board, token)
+    }
+
+    fn new_game(bot: &mut B, rules: Rules, token: Token) -> impl Future<Output = ()> + Send {
+        bot.new_game(rules, token)
+    }
+
+    fn get_offer(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+    ) -> impl Future<Output = Option<Offer>> + Send {
+        bot.get_offer(board, token)
+    }
+
+    fn accept_draw(bot: &mut B, board: &Board, token: Token) -> impl Future<Output = bool> + Send {
+        bot.accept_draw(board, token)
+    }
+
+    fn notify_move(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        positions: (Position, Position),
+    ) -> impl Future<Output = ()> + Send {
+        bot.notify_move(board, token, positions)
+    }
+
+    fn notify_collapse(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        spooky_mark: SpookyMark,
+        position: Position,
+    ) -> impl Future<Output = ()> + Send {
+        bot.notify_collapse(board, token, spooky_mark, position)
+    }
+
+    fn game_over(
+        bot: &mut B,
+        board: &Board,
+        token: Token,
+        score: (f32, f32),
+    ) -> impl Future<Output = ()> + Send {
+        bot.game_over(board, token, score)
+    }
+
+    fn start_pondering(bot: &mut B, board: &Board, token: Token) {
+        bot.start_pondering(board, token)
+    }
+
+    fn stop_pondering(bot: &mut B, token: Token) {
+        bot.stop_pondering(token)
+    }
+}
+
 #[cfg(test)]
 mod random_bot_game_tests {
     use rstest::rstest;
 
     use super::*;
 
-    use crate::bot::RandomBot;
+    use crate::bot::{BotPair, Offer, RandomBot};
+
+    fn play_game(seed: u64) -> GameRecord {
+        let random_bot = RandomBot::new(seed);
+        let mut game = Game::new(random_bot, seed);
+        game.set_verbose(false);
+
+        game.play_whole_game();
+
+        game.get_record()
+    }
 
     #[rstest]
     fn game_test(#[values(0, 1, 2, 13, 42, 100, 31415)] seed: u64) {
         let random_bot = RandomBot::new(seed);
-        let mut game = Game::new(random_bot);
+        let mut game = Game::new(random_bot, seed);
 
         // As long as it doesn't crash, we're probably fine.
         game.play_whole_game();
+
+        // And the same seed has to play out the same game.
+        assert_eq!(game.get_record(), play_game(seed));
+    }
+
+    #[rstest]
+    #[case(0, 9, (0.0, 0.0))]
+    #[case(1, 9, (1.0, 0.0))]
+    #[case(22, 9, (1.0, 0.5))]
+    #[case(34, 9, (0.5, 1.0))]
+    #[case(42, 8, (0.0, 1.0))]
+    fn game_regression_test(#[case] seed: u64, #[case] turns: usize, #[case] score: (f32, f32)) {
+        let record = play_game(seed);
+
+        assert_eq!(turns, record.turns.len());
+        assert_eq!(score, record.score);
+    }
+
+    #[test]
+    fn game_record_test() {
+        let bots = BotPair {
+            bot_x: RandomBot::new(1),
+            bot_o: RandomBot::new(2),
+        };
+        let mut game = Game::new(bots, 3);
+        game.set_verbose(false);
+
+        game.play_whole_game();
+
+        let record = game.get_record();
+        assert_eq!(game.get_board().turn as usize - 1, record.turns.len());
+        assert_eq!(game.get_board().get_score(), record.score);
+        assert!(record
+            .turns
+            .iter()
+            .zip([Token::X, Token::O].iter().cycle())
+            .all(|(turn, token)| turn.token == *token));
+    }
+
+    // Plays its first move at once, and takes ten times the clock of `time_forfeit_test` for
+    // every later one.
+    struct SlowBot {
+        moves: usize,
+    }
+
+    impl Bot for SlowBot {
+        fn get_next_move(&mut self, board: &Board, _token: Token) -> (Position, Position) {
+            if self.moves > 0 {
+                std::thread::sleep(Duration::from_millis(500));
+            }
+            self.moves += 1;
+
+            board.get_legal_moves()[0]
+        }
+    }
+
+    #[test]
+    fn time_forfeit_test() {
+        let bots = BotPair {
+            bot_x: RandomBot::new(1),
+            bot_o: SlowBot { moves: 0 },
+        };
+        let mut game = Game::new(bots, 3);
+        game.set_verbose(false);
+        game.set_time_control(TimeControl::Clock {
+            initial: Duration::from_millis(50),
+            increment: Duration::ZERO,
+        });
+
+        game.play_whole_game();
+
+        // O's second move runs out of time.
+        assert_eq!(Some(Token::O), game.get_time_forfeit());
+        assert_eq!(3, game.get_record().turns.len());
+        assert_eq!((1.0, 0.0), game.get_record().score);
+    }
+
+    // Plays at random, writing down the hooks it's called through, and makes its offer on the
+    // given turn.
+    struct HookBot {
+        bot: RandomBot,
+        events: Vec<String>,
+        offer: Option<(u8, Offer)>,
+        accepts_draws: bool,
+    }
+
+    impl HookBot {
+        fn new(offer: Option<(u8, Offer)>, accepts_draws: bool) -> Self {
+            Self {
+                bot: RandomBot::new(0),
+                events: vec![],
+                offer,
+                accepts_draws,
+            }
+        }
+    }
+
+    impl Bot for HookBot {
+        fn get_next_move(&mut self, board: &Board, token: Token) -> (Position, Position) {
+            self.events.push(format!("move {token}"));
+            self.bot.get_next_move(board, token)
+        }
+
+        fn choose_collapse(&mut self, board: &Board, token: Token) -> Position {
+            self.events.push(format!("choose {token}"));
+            board.get_pending_collapse().unwrap().1
+        }
+
+        fn new_game(&mut self, rules: Rules, token: Token) {
+            self.events.push(format!("new game {rules:?} {token}"));
+        }
+
+        fn get_offer(&mut self, board: &Board, _token: Token) -> Option<Offer> {
+            self.offer
+                .filter(|(turn, _)| *turn == board.turn)
+                .map(|(_, offer)| offer)
+        }
+
+        fn accept_draw(&mut self, _board: &Board, _token: Token) -> bool {
+            self.accepts_draws
+        }
+
+        fn notify_move(&mut self, _board: &Board, token: Token, _positions: (Position, Position)) {
+            self.events.push(format!("notify move {token}"));
+        }
+
+        fn notify_collapse(
+            &mut self,
+            _board: &Board,
+            token: Token,
+            spooky_mark: SpookyMark,
+            position: Position,
+        ) {
+            assert!(position == spooky_mark.0 || position == spooky_mark.1);
+            self.events.push(format!("notify collapse {token}"));
+        }
+
+        fn game_over(&mut self, _board: &Board, token: Token, score: (f32, f32)) {
+            self.events.push(format!("game over {token} {score:?}"));
+        }
+
+        fn start_pondering(&mut self, board: &Board, token: Token) {
+            assert_eq!(token.get_opponent(), board.get_current_token());
+            self.events.push(format!("ponder {token}"));
+        }
+
+        fn stop_pondering(&mut self, token: Token) {
+            self.events.push(format!("stop {token}"));
+        }
+    }
+
+    #[test]
+    fn hooks_test() {
+        let mut game = Game::new(HookBot::new(None, false), 0);
+        game.set_verbose(false);
+        game.set_rules(Rules::PlayerCollapse);
+
+        game.play_whole_game();
+
+        let events = &game.bot.events;
+        assert_eq!(
+            [
+                "new game PlayerCollapse X",
+                "new game PlayerCollapse O",
+                "move X",
+                "notify move O"
+            ],
+            events[..4]
+        );
+        assert_eq!(
+            2,
+            events.iter().filter(|e| e.starts_with("game over")).count()
+        );
+        assert!(events.last().unwrap().starts_with("game over"));
+        assert!(!events.iter().any(|e| e.starts_with("ponder")));
+
+        // Every collapse is chosen by the player who didn't close the cycle, and all are told.
+        let record = game.get_record();
+        let collapses = record.turns.iter().filter(|t| t.collapse.is_some()).count();
+        assert!(record
+            .turns
+            .iter()
+            .filter_map(|turn| turn.collapse)
+            .all(|(spooky_mark, position)| position == spooky_mark.1));
+        assert_eq!(
+            collapses,
+            events.iter().filter(|e| e.starts_with("choose")).count()
+        );
+        assert_eq!(
+            2 * collapses,
+            events
+                .iter()
+                .filter(|e| e.starts_with("notify collapse"))
+                .count()
+        );
+    }
+
+    #[test]
+    fn resign_test() {
+        let bots = BotPair {
+            bot_x: RandomBot::new(0),
+            bot_o: HookBot::new(Some((4, Offer::Resign)), false),
+        };
+        let mut game = Game::new(bots, 0);
+        game.set_verbose(false);
+
+        game.play_whole_game();
+
+        assert_eq!(Some(&(Token::O, Forfeit::Resigned)), game.get_forfeit());
+        assert_eq!(3, game.get_record().turns.len());
+        assert_eq!((1.0, 0.0), game.get_score());
+        assert_eq!(
+            Some(&"game over O (1.0, 0.0)".to_string()),
+            game.bot.bot_o.events.last()
+        );
+    }
+
+    #[rstest]
+    fn draw_offer_test(#[values(false, true)] accepted: bool) {
+        let bots = BotPair {
+            bot_x: HookBot::new(Some((3, Offer::Draw)), false),
+            bot_o: HookBot::new(None, accepted),
+        };
+        let mut game = Game::new(bots, 0);
+        game.set_verbose(false);
+
+        game.play_whole_game();
+
+        assert_eq!(accepted, game.is_draw_agreed());
+        assert_eq!(None, game.get_forfeit());
+
+        // A declined draw is played on.
+        if accepted {
+            assert_eq!(2, game.get_record().turns.len());
+            assert_eq!((0.0, 0.0), game.get_score());
+        } else {
+            assert!(game.get_record().turns.len() > 4);
+            assert_eq!(game.get_board().get_score(), game.get_score());
+        }
+    }
+
+    #[test]
+    fn pondering_test() {
+        let bots = BotPair {
+            bot_x: HookBot::new(None, false),
+            bot_o: HookBot::new(None, false),
+        };
+        let mut game = Game::new(bots, 0);
+        game.set_verbose(false);
+        game.set_pondering(true);
+
+        game.play_whole_game();
+
+        // Each player ponders after each of their moves but the last, and stops before moving
+        // again and when the game is over.
+        for (events, token) in [
+            (&game.bot.bot_x.events, Token::X),
+            (&game.bot.bot_o.events, Token::O),
+        ] {
+            let pondering = events
+                .iter()
+                .filter(|e| ["move", "ponder", "stop"].iter().any(|p| e.starts_with(p)))
+                .map(|e| e.as_str())
+                .collect::<Vec<_>>();
+
+            let moves = pondering.iter().filter(|e| e.starts_with("move")).count();
+            let turn = ["stop", "move", "ponder"].map(|event| format!("{event} {token}"));
+            let mut expected = vec![turn; moves].concat();
+            if game.get_record().turns.last().unwrap().token == token {
+                expected.pop();
+            }
+            expected.push(format!("stop {token}"));
+
+            assert_eq!(expected, pondering);
+        }
     }
 }
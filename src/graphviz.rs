@@ -0,0 +1,138 @@
+use std::collections::{HashSet, VecDeque};
+use std::fmt::Write;
+
+use crate::board::{Board, Position, SpookyMark};
+
+fn node_name(position: Position) -> String {
+    format!("r{}c{}", position.row(), position.column())
+}
+
+// A spooky mark is part of a cycle if its two ends stay connected without it.
+fn is_on_cycle(spooky_marks: &[SpookyMark], index: usize) -> bool {
+    let SpookyMark(start, end, _) = spooky_marks[index];
+
+    let mut queue = VecDeque::from([start]);
+    let mut visited = HashSet::from([start]);
+
+    while let Some(current) = queue.pop_front() {
+        if current == end {
+            return true;
+        }
+
+        for (other_index, SpookyMark(position_1, position_2, _)) in spooky_marks.iter().enumerate()
+        {
+            if other_index == index {
+                continue;
+            }
+
+            let target = if *position_1 == current {
+                *position_2
+            } else if *position_2 == current {
+                *position_1
+            } else {
+                continue;
+            };
+
+            if visited.insert(target) {
+                queue.push_back(target);
+            }
+        }
+    }
+
+    false
+}
+
+/// Renders the entanglement graph of the board in the Graphviz DOT language.
+///
+/// Every square is a node, labelled with its classical mark if it has one, and every spooky mark
+/// is an edge between its two squares. Edges that are part of a cycle are drawn bold and red.
+pub fn to_dot(board: &Board) -> String {
+    let mut dot = String::new();
+
+    writeln!(dot, "graph entanglement {{").unwrap();
+    writeln!(dot, "    node [shape=box];").unwrap();
+
+    for position in &board.positions {
+        let label = match board.get_mark(*position) {
+            Some(turn_token) => format!(
+                "({}, {})\\n{}",
+                position.row(),
+                position.column(),
+                turn_token.get_label()
+            ),
+            None => format!("({}, {})", position.row(), position.column()),
+        };
+
+        writeln!(dot, "    {} [label=\"{label}\"];", node_name(*position)).unwrap();
+    }
+
+    for (index, SpookyMark(position_1, position_2, turn_token)) in
+        board.spooky_marks.iter().enumerate()
+    {
+        let style = if is_on_cycle(&board.spooky_marks, index) {
+            ", color=red, style=bold"
+        } else {
+            ""
+        };
+
+        writeln!(
+            dot,
+            "    {} -- {} [label=\"{}\"{style}];",
+            node_name(*position_1),
+            node_name(*position_2),
+            turn_token.get_label(),
+        )
+        .unwrap();
+    }
+
+    writeln!(dot, "}}").unwrap();
+
+    dot
+}
+
+#[cfg(test)]
+mod test_dot_export {
+    use super::*;
+
+    use crate::board::Token;
+
+    #[test]
+    fn test_empty_board() {
+        let dot = to_dot(&Board::new());
+
+        assert!(dot.starts_with("graph entanglement {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert_eq!(9, dot.matches("[label=\"(").count());
+        assert!(!dot.contains("--"));
+    }
+
+    #[test]
+    fn test_classical_mark_and_edges() {
+        let mut board = Board::new();
+        board.do_turn(Position::new(1, 1), Position::new(1, 1), Token::X);
+        board.do_turn(Position::new(2, 0), Position::new(0, 0), Token::O);
+        board.do_turn(Position::new(0, 0), Position::new(0, 1), Token::X);
+        board.do_turn(Position::new(0, 1), Position::new(0, 2), Token::O);
+        board.do_turn(Position::new(0, 2), Position::new(0, 0), Token::X);
+
+        let dot = to_dot(&board);
+
+        assert!(dot.contains("    r1c1 [label=\"(1, 1)\\nX1\"];\n"));
+        assert!(dot.contains("    r0c0 [label=\"(0, 0)\"];\n"));
+        assert!(dot.contains("    r2c0 -- r0c0 [label=\"O2\"];\n"));
+        assert!(dot.contains("    r0c0 -- r0c1 [label=\"X3\", color=red, style=bold];\n"));
+        assert!(dot.contains("    r0c1 -- r0c2 [label=\"O4\", color=red, style=bold];\n"));
+        assert!(dot.contains("    r0c2 -- r0c0 [label=\"X5\", color=red, style=bold];\n"));
+    }
+
+    #[test]
+    fn test_size_two_cycle() {
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::X);
+        board.do_turn(Position::new(1, 1), Position::new(0, 0), Token::O);
+
+        let dot = to_dot(&board);
+
+        assert_eq!(2, dot.matches("color=red, style=bold").count());
+    }
+}
@@ -0,0 +1,30 @@
+pub mod async_bot;
+pub mod board;
+pub mod book;
+pub mod bot;
+pub mod dataset;
+pub mod difficulty;
+pub mod evaluation;
+pub mod ffi;
+pub mod game;
+pub mod graphviz;
+pub mod mcts;
+pub mod multiplayer;
+pub mod network;
+pub mod process;
+pub mod protocol;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod reinforcement;
+pub mod search;
+pub mod server;
+pub mod simulation;
+pub mod solver;
+pub mod symmetry;
+pub mod tablebase;
+#[cfg(test)]
+mod test_support;
+pub mod transposition;
+pub mod tuning;
+#[cfg(feature = "wasm")]
+pub mod wasm;
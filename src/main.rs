@@ -1,15 +1,11 @@
-mod board;
-mod bot;
-mod game;
-
-use crate::bot::RandomBot;
-use crate::game::Game;
+use tiqtaqtoe::bot::RandomBot;
+use tiqtaqtoe::game::Game;
 
 fn main() {
     println!("Hello! Let's play quantum tic-tac-toe!");
 
     let random_bot = RandomBot::new(42);
-    let mut game = Game::new(random_bot);
+    let mut game = Game::new(random_bot, 42);
 
     game.play_whole_game();
     game.print_winner();
@@ -0,0 +1,668 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use rand::prelude::{IndexedRandom, Rng, SeedableRng, StdRng};
+
+use crate::board::{Board, Position, Token};
+use crate::bot::Bot;
+use crate::solver;
+
+/// Moves with their prior probability.
+pub type Priors = Vec<((Position, Position), f32)>;
+
+/// Estimates that guide the tree search of `MctsBot`.
+pub trait Guide {
+    /// The value of a board whose game isn't over, for X between -1 and 1, and the prior
+    /// probability of each of its legal moves, as listed by `solver::get_distinct_moves`.
+    fn guide(&mut self, board: &Board) -> (f32, Priors);
+
+    /// Called on the copy of the guide each thread of a parallel search gets, so that guides
+    /// drawing random numbers don't all draw the same ones.
+    fn reseed(&mut self, _seed: u64) {}
+}
+
+impl<G> Guide for Box<G>
+where
+    G: Guide + ?Sized,
+{
+    fn guide(&mut self, board: &Board) -> (f32, Priors) {
+        (**self).guide(board)
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        (**self).reseed(seed)
+    }
+}
+
+/// Values boards by playing random moves until the game is over, with the same prior for
+/// every move.
+#[derive(Clone)]
+pub struct RolloutGuide {
+    rng: StdRng,
+}
+
+impl RolloutGuide {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Guide for RolloutGuide {
+    fn guide(&mut self, board: &Board) -> (f32, Priors) {
+        let legal_moves = solver::get_distinct_moves(board);
+        let prior = 1.0 / legal_moves.len() as f32;
+
+        let mut rollout = board.clone();
+        while !rollout.is_game_over() {
+            let (position_1, position_2) =
+                *rollout.get_legal_moves().choose(&mut self.rng).unwrap();
+            rollout.do_turn(position_1, position_2, rollout.get_current_token());
+            rollout.collapse_loop(&mut self.rng);
+        }
+
+        let priors = legal_moves
+            .into_iter()
+            .map(|legal_move| (legal_move, prior))
+            .collect();
+
+        (solver::get_value(&rollout), priors)
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+}
+
+const DEFAULT_EXPLORATION: f32 = 1.5;
+
+/// How large the tree grown while pondering gets at most, since the opponent may take a while.
+const MAX_PONDERING_NODES: usize = 1 << 16;
+
+#[derive(Clone)]
+struct Edge {
+    positions: (Position, Position),
+    prior: f32,
+    visits: u32,
+    value_sum: f32,
+    /// The node after the move, or after each way the cycle it closes collapses.
+    children: [Option<usize>; 2],
+}
+
+#[derive(Clone)]
+struct Node {
+    board: Board,
+    edges: Vec<Edge>,
+    visits: u32,
+    is_expanded: bool,
+}
+
+// A search running on its own thread until it's told to stop, giving back its tree.
+struct Pondering {
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<Vec<Node>>>,
+}
+
+impl Pondering {
+    fn finish(mut self) -> Vec<Node> {
+        self.stop.store(true, Ordering::Relaxed);
+        // A search that panicked leaves nothing to carry on from.
+        self.handle.take().unwrap().join().unwrap_or_default()
+    }
+}
+
+impl Drop for Pondering {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+// The subtree under the node, with the node as its root.
+fn get_subtree(nodes: Vec<Node>, root: usize) -> Vec<Node> {
+    let mut new_indices = HashMap::from([(root, 0)]);
+    let mut order = vec![root];
+
+    let mut i = 0;
+    while i < order.len() {
+        let children = nodes[order[i]].edges.iter().flat_map(|edge| edge.children);
+        for child in children.flatten() {
+            new_indices.entry(child).or_insert_with(|| {
+                order.push(child);
+                order.len() - 1
+            });
+        }
+        i += 1;
+    }
+
+    let mut nodes = nodes.into_iter().map(Some).collect::<Vec<_>>();
+    order
+        .into_iter()
+        .map(|index| {
+            let mut node = nodes[index].take().unwrap();
+            for edge in &mut node.edges {
+                edge.children = edge.children.map(|child| child.map(|c| new_indices[&c]));
+            }
+            node
+        })
+        .collect()
+}
+
+fn get_sign(token: Token) -> f32 {
+    match token {
+        Token::X => 1.0,
+        Token::O => -1.0,
+    }
+}
+
+/// Monte Carlo tree search, choosing moves by their value and prior as in PUCT, and playing
+/// the most visited move.
+///
+/// A move that closes a cycle is followed by a coin flip for the collapse, as under
+/// `Rules::RandomCollapse`. The search assumes those rules in every game, so under
+/// `Rules::PlayerCollapse` it values cycles as if neither player chose how they collapse.
+///
+/// With more than one thread, each thread grows its own tree with its own seed, and the visits
+/// of their roots are added up.
+///
+/// While pondering, the bot grows a tree from the board the opponent is to move on in the
+/// background, and the next search carries on from the subtree of the move the opponent made.
+pub struct MctsBot<G>
+where
+    G: Guide,
+{
+    guide: G,
+    simulations: usize,
+    exploration: f32,
+    threads: usize,
+    rng: StdRng,
+    nodes: Vec<Node>,
+    pondering: Option<Pondering>,
+    /// The tree grown while pondering, until the next search.
+    pondered: Vec<Node>,
+}
+
+// A copy doesn't take over the pondering, which only one of them could stop.
+impl<G> Clone for MctsBot<G>
+where
+    G: Guide + Clone,
+{
+    fn clone(&self) -> Self {
+        Self {
+            guide: self.guide.clone(),
+            simulations: self.simulations,
+            exploration: self.exploration,
+            threads: self.threads,
+            rng: self.rng.clone(),
+            nodes: self.nodes.clone(),
+            pondering: None,
+            pondered: self.pondered.clone(),
+        }
+    }
+}
+
+impl<G> MctsBot<G>
+where
+    G: Guide,
+{
+    /// The seed drives the coin flips of collapses during the search.
+    pub fn new(guide: G, simulations: usize, seed: u64) -> Self {
+        Self {
+            guide,
+            simulations,
+            exploration: DEFAULT_EXPLORATION,
+            threads: 1,
+            rng: StdRng::seed_from_u64(seed),
+            nodes: vec![],
+            pondering: None,
+            pondered: vec![],
+        }
+    }
+
+    /// How much the priors and rarely visited moves weigh against the values found so far.
+    pub fn set_exploration(&mut self, exploration: f32) {
+        self.exploration = exploration;
+    }
+
+    fn search_alone(&mut self, board: &Board) -> Vec<((Position, Position), u32)> {
+        self.prepare_root(board);
+
+        // The first simulation only expands a new root.
+        for _ in 0..=self.simulations {
+            self.simulate(0);
+        }
+
+        self.get_root_visits()
+    }
+
+    fn search_alone_for(
+        &mut self,
+        board: &Board,
+        budget: Duration,
+    ) -> Vec<((Position, Position), u32)> {
+        let deadline = Instant::now() + budget;
+        self.prepare_root(board);
+
+        self.simulate(0);
+        loop {
+            self.simulate(0);
+
+            if Instant::now() >= deadline {
+                break;
+            }
+        }
+
+        self.get_root_visits()
+    }
+
+    // Carries on from the node of the board in the tree grown while pondering, if it's the root
+    // or one move below it, or starts a new tree.
+    fn prepare_root(&mut self, board: &Board) {
+        let pondered = std::mem::take(&mut self.pondered);
+
+        let root = pondered.first().and_then(|root| {
+            let children = root.edges.iter().flat_map(|edge| edge.children).flatten();
+            std::iter::once(0)
+                .chain(children)
+                .find(|&index| pondered[index].board == *board)
+        });
+
+        match root {
+            Some(root) => self.nodes = get_subtree(pondered, root),
+            None => self.reset(board),
+        }
+    }
+
+    fn reset(&mut self, board: &Board) {
+        self.nodes = vec![Node {
+            board: board.clone(),
+            edges: vec![],
+            visits: 0,
+            is_expanded: false,
+        }];
+    }
+
+    fn get_root_visits(&self) -> Vec<((Position, Position), u32)> {
+        self.nodes[0]
+            .edges
+            .iter()
+            .map(|edge| (edge.positions, edge.visits))
+            .collect()
+    }
+
+    // Walks down the tree to a new node, and returns its value for X.
+    fn simulate(&mut self, index: usize) -> f32 {
+        let node = &self.nodes[index];
+
+        if node.board.is_game_over() {
+            return solver::get_value(&node.board);
+        }
+
+        if !node.is_expanded {
+            let (value, priors) = self.guide.guide(&node.board);
+
+            let node = &mut self.nodes[index];
+            node.edges = priors
+                .into_iter()
+                .map(|(positions, prior)| Edge {
+                    positions,
+                    prior,
+                    visits: 0,
+                    value_sum: 0.0,
+                    children: [None; 2],
+                })
+                .collect();
+            node.is_expanded = true;
+            node.visits += 1;
+
+            return value;
+        }
+
+        let sign = get_sign(node.board.get_current_token());
+        let exploration = self.exploration * (node.visits as f32).sqrt();
+
+        let (edge_index, _) = node
+            .edges
+            .iter()
+            .map(|edge| {
+                let mean_value = if edge.visits > 0 {
+                    sign * edge.value_sum / edge.visits as f32
+                } else {
+                    0.0
+                };
+
+                mean_value + exploration * edge.prior / (1.0 + edge.visits as f32)
+            })
+            .enumerate()
+            .fold((0, f32::NEG_INFINITY), |best, (i, score)| {
+                if score > best.1 {
+                    (i, score)
+                } else {
+                    best
+                }
+            });
+
+        let outcome = self.rng.random_range(0..2);
+
+        let child_index = match self.nodes[index].edges[edge_index].children[outcome] {
+            Some(child_index) => child_index,
+            None => {
+                let node = &self.nodes[index];
+                let (position_1, position_2) = node.edges[edge_index].positions;
+
+                let mut child = node.board.clone();
+                child.do_turn(position_1, position_2, child.get_current_token());
+
+                let closes_cycle = match child.get_pending_collapse() {
+                    Some(spooky_mark) => {
+                        let position = [spooky_mark.0, spooky_mark.1][outcome];
+                        child.collapse(position).unwrap();
+                        true
+                    }
+                    None => false,
+                };
+
+                self.nodes.push(Node {
+                    board: child,
+                    edges: vec![],
+                    visits: 0,
+                    is_expanded: false,
+                });
+
+                let child_index = self.nodes.len() - 1;
+                let children = &mut self.nodes[index].edges[edge_index].children;
+
+                // Without a collapse, both outcomes are the same node.
+                if closes_cycle {
+                    children[outcome] = Some(child_index);
+                } else {
+                    *children = [Some(child_index); 2];
+                }
+
+                child_index
+            }
+        };
+
+        let value = self.simulate(child_index);
+
+        let node = &mut self.nodes[index];
+        node.visits += 1;
+        node.edges[edge_index].visits += 1;
+        node.edges[edge_index].value_sum += value;
+
+        value
+    }
+}
+
+impl<G> MctsBot<G>
+where
+    G: Guide + Clone + Send,
+{
+    /// Spreads the search over the threads, sharing the simulations between them.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Searches the board, whose game mustn't be over, and returns how often each legal move
+    /// was visited, counting the visits of a search carried on from pondering.
+    pub fn search(&mut self, board: &Board) -> Vec<((Position, Position), u32)> {
+        self.finish_pondering();
+
+        if self.threads > 1 {
+            return self.search_in_parallel(board, None);
+        }
+
+        self.search_alone(board)
+    }
+
+    /// Like `search`, but simulating until the budget runs out instead of a fixed number of
+    /// times.
+    pub fn search_for(
+        &mut self,
+        board: &Board,
+        budget: Duration,
+    ) -> Vec<((Position, Position), u32)> {
+        self.finish_pondering();
+
+        if self.threads > 1 {
+            return self.search_in_parallel(board, Some(budget));
+        }
+
+        self.search_alone_for(board, budget)
+    }
+
+    fn search_in_parallel(
+        &mut self,
+        board: &Board,
+        budget: Option<Duration>,
+    ) -> Vec<((Position, Position), u32)> {
+        let simulations = self.simulations.div_ceil(self.threads);
+
+        let mut workers = (0..self.threads)
+            .map(|_| self.get_worker(simulations))
+            .collect::<Vec<_>>();
+
+        // Only one of the trees can carry on from pondering.
+        workers[0].pondered = std::mem::take(&mut self.pondered);
+
+        let results = thread::scope(|scope| {
+            let handles = workers
+                .into_iter()
+                .map(|mut worker| {
+                    scope.spawn(move || match budget {
+                        Some(budget) => worker.search_alone_for(board, budget),
+                        None => worker.search_alone(board),
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let mut visits = results[0].clone();
+        for result in &results[1..] {
+            for (positions, count) in result {
+                if let Some(total) = visits.iter_mut().find(|(p, _)| p == positions) {
+                    total.1 += count;
+                }
+            }
+        }
+
+        visits
+    }
+
+    // A bot searching apart from this one, with a seed of its own.
+    fn get_worker(&mut self, simulations: usize) -> MctsBot<G> {
+        let seed = self.rng.random();
+        let mut guide = self.guide.clone();
+        guide.reseed(seed);
+
+        let mut worker = MctsBot::new(guide, simulations, seed);
+        worker.exploration = self.exploration;
+
+        worker
+    }
+
+    // Stops pondering, keeping its tree for the next search.
+    fn finish_pondering(&mut self) {
+        if let Some(pondering) = self.pondering.take() {
+            self.pondered = pondering.finish();
+        }
+    }
+}
+
+impl<G> Bot for MctsBot<G>
+where
+    G: Guide + Clone + Send + 'static,
+{
+    fn get_next_move(&mut self, board: &Board, _token: Token) -> (Position, Position) {
+        get_most_visited(self.search(board))
+    }
+
+    fn get_timed_move(
+        &mut self,
+        board: &Board,
+        _token: Token,
+        budget: Duration,
+    ) -> (Position, Position) {
+        get_most_visited(self.search_for(board, budget))
+    }
+
+    // Grows a tree from the board on a thread of its own, until the pondering stops or the next
+    // search starts.
+    fn start_pondering(&mut self, board: &Board, _token: Token) {
+        self.finish_pondering();
+        self.pondered.clear();
+
+        if board.is_game_over() {
+            return;
+        }
+
+        let mut worker = self.get_worker(0);
+        let board = board.clone();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+
+        let handle = thread::spawn(move || {
+            worker.reset(&board);
+            while !stopped.load(Ordering::Relaxed) && worker.nodes.len() < MAX_PONDERING_NODES {
+                worker.simulate(0);
+            }
+
+            worker.nodes
+        });
+
+        self.pondering = Some(Pondering {
+            stop,
+            handle: Some(handle),
+        });
+    }
+
+    fn stop_pondering(&mut self, _token: Token) {
+        self.finish_pondering();
+    }
+}
+
+/// The first of the most visited moves. The root is expanded before searching, so there is a
+/// visit count for every legal move, and bots are only asked to move when there are some.
+fn get_most_visited(visits: Vec<((Position, Position), u32)>) -> (Position, Position) {
+    let (best_move, _) = visits
+        .into_iter()
+        .reduce(|best, edge| if edge.1 > best.1 { edge } else { best })
+        .expect("The board has legal moves");
+
+    best_move
+}
+
+#[cfg(test)]
+mod test_mcts {
+    use super::*;
+
+    use crate::bot::{BotPair, RandomBot};
+    use crate::game::{Game, TimeControl};
+    use crate::test_support::assert_beats_random_bot;
+
+    #[test]
+    fn test_mcts_takes_a_win() {
+        let mut board = Board::new();
+        for (position, token) in [
+            ((0, 0), Token::X),
+            ((1, 0), Token::O),
+            ((0, 1), Token::X),
+            ((1, 1), Token::O),
+        ] {
+            board.do_turn(
+                Position::new(position.0, position.1),
+                Position::new(position.0, position.1),
+                token,
+            );
+        }
+
+        let mut bot = MctsBot::new(RolloutGuide::new(0), 200, 0);
+        assert_eq!(
+            (Position::new(0, 2), Position::new(0, 2)),
+            bot.get_next_move(&board, Token::X)
+        );
+        assert_eq!(
+            200,
+            bot.search(&board)
+                .iter()
+                .map(|(_, visits)| visits)
+                .sum::<u32>()
+        );
+    }
+
+    #[test]
+    fn test_parallel_search() {
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::X);
+
+        let mut bot = MctsBot::new(RolloutGuide::new(0), 200, 0);
+        bot.set_threads(4);
+
+        let visits = bot.search(&board);
+        assert_eq!(200, visits.iter().map(|(_, count)| count).sum::<u32>());
+        assert_eq!(solver::get_distinct_moves(&board).len(), visits.len());
+    }
+
+    #[test]
+    fn test_pondering_carries_on() {
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::X);
+
+        let mut bot = MctsBot::new(RolloutGuide::new(0), 200, 0);
+        bot.start_pondering(&board, Token::X);
+        std::thread::sleep(Duration::from_millis(100));
+        bot.stop_pondering(Token::X);
+
+        // O's most visited reply that doesn't close the cycle.
+        let root = &bot.pondered[0];
+        let edge = root
+            .edges
+            .iter()
+            .filter(|edge| edge.children[0] == edge.children[1])
+            .max_by_key(|edge| edge.visits)
+            .unwrap();
+        let pondered_visits = bot.pondered[edge.children[0].unwrap()].visits;
+        assert!(pondered_visits > 1);
+
+        board.do_turn(edge.positions.0, edge.positions.1, Token::O);
+        let visits = bot.search(&board);
+
+        assert_eq!(
+            pondered_visits + 200,
+            visits.iter().map(|(_, count)| count).sum::<u32>()
+        );
+        assert!(bot.pondered.is_empty());
+    }
+
+    #[test]
+    fn test_pondering_game() {
+        let bots = BotPair {
+            bot_x: RandomBot::new(0),
+            bot_o: MctsBot::new(RolloutGuide::new(0), 100, 0),
+        };
+        let mut game = Game::new(bots, 0);
+        game.set_verbose(false);
+        game.set_pondering(true);
+        game.set_time_control(TimeControl::PerMove(Duration::from_millis(20)));
+
+        game.play_whole_game();
+
+        assert_eq!(None, game.get_forfeit());
+        assert!(game.get_bot().bot_o.pondering.is_none());
+    }
+
+    #[test]
+    fn test_mcts_bot_beats_random_bot() {
+        assert_beats_random_bot(10, Token::O, |seed| {
+            MctsBot::new(RolloutGuide::new(seed), 100, seed)
+        });
+    }
+}
@@ -0,0 +1,733 @@
+use std::collections::HashMap;
+use std::io::{self, ErrorKind};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use rand::prelude::{Rng, SeedableRng, StdRng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tungstenite::{Message, WebSocket};
+
+use crate::board::{Board, MoveError, Position, SpookyMark, Token};
+use crate::game::Rules;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    /// Takes a seat in the given room, or in the first room waiting for an opponent.
+    Join {
+        room: Option<String>,
+        #[serde(default)]
+        rules: Rules,
+    },
+    Spectate {
+        room: String,
+    },
+    /// Takes back a seat after a dropped connection.
+    Rejoin {
+        room: String,
+        key: String,
+    },
+    Move {
+        positions: [Position; 2],
+    },
+    Collapse {
+        position: Position,
+    },
+}
+
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ServerMessage<'a> {
+    Joined {
+        room: &'a str,
+        token: Token,
+        key: &'a str,
+    },
+    Spectating {
+        room: &'a str,
+    },
+    Moved {
+        token: Token,
+        positions: [Position; 2],
+    },
+    Cycle {
+        spooky_mark: SpookyMark,
+        chooser: Token,
+    },
+    Collapsed {
+        spooky_mark: SpookyMark,
+        position: Position,
+    },
+    PlayerDisconnected {
+        token: Token,
+    },
+    PlayerReconnected {
+        token: Token,
+    },
+    GameOver {
+        score: (f32, f32),
+    },
+    State {
+        room: &'a str,
+        rules: Rules,
+        board: &'a Board,
+        players_connected: [bool; 2],
+        started: bool,
+        to_move: Option<Token>,
+        pending_collapse: Option<SpookyMark>,
+        score: (f32, f32),
+        game_over: bool,
+    },
+    Error {
+        error: Value,
+    },
+}
+
+impl ServerMessage<'_> {
+    fn to_text(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+fn error_message(kind: &str, message: &str) -> String {
+    ServerMessage::Error {
+        error: json!({ "kind": kind, "message": message }),
+    }
+    .to_text()
+}
+
+fn move_error_message(move_error: MoveError) -> String {
+    let mut error = serde_json::to_value(move_error).unwrap();
+    error["message"] = move_error.to_string().into();
+
+    ServerMessage::Error { error }.to_text()
+}
+
+fn get_seat_index(token: Token) -> usize {
+    match token {
+        Token::X => 0,
+        Token::O => 1,
+    }
+}
+
+type ClientId = u64;
+
+struct Client {
+    id: ClientId,
+    sender: Sender<String>,
+}
+
+struct Seat {
+    key: String,
+    client: Option<Client>,
+}
+
+// Plays on a Board rather than a Game, since moves arrive from the clients whenever they send
+// them, instead of being asked of bots in turn.
+struct Room {
+    rules: Rules,
+    /// Whether players without a room name can be paired into it.
+    open: bool,
+    board: Board,
+    seats: [Option<Seat>; 2],
+    spectators: Vec<Client>,
+    rng: StdRng,
+}
+
+impl Room {
+    fn new(rules: Rules, open: bool, seed: u64) -> Self {
+        Self {
+            rules,
+            open,
+            board: Board::new(),
+            seats: [None, None],
+            spectators: vec![],
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn is_started(&self) -> bool {
+        self.seats.iter().all(Option::is_some)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.spectators.is_empty()
+            && self
+                .seats
+                .iter()
+                .flatten()
+                .all(|seat| seat.client.is_none())
+    }
+
+    fn get_token(&self, client_id: ClientId) -> Option<Token> {
+        [Token::X, Token::O].into_iter().find(|token| {
+            self.seats[get_seat_index(*token)]
+                .as_ref()
+                .and_then(|seat| seat.client.as_ref())
+                .is_some_and(|client| client.id == client_id)
+        })
+    }
+
+    fn broadcast(&self, message: &str) {
+        let seated_clients = self
+            .seats
+            .iter()
+            .flatten()
+            .filter_map(|s| s.client.as_ref());
+
+        for client in seated_clients.chain(&self.spectators) {
+            // A failed send means the client is gone, which leave() takes care of.
+            let _ = client.sender.send(message.to_string());
+        }
+    }
+
+    fn get_state(&self, room: &str) -> String {
+        let game_over = self.board.is_game_over();
+
+        ServerMessage::State {
+            room,
+            rules: self.rules,
+            board: &self.board,
+            players_connected: [0, 1].map(|index| {
+                self.seats[index]
+                    .as_ref()
+                    .is_some_and(|seat| seat.client.is_some())
+            }),
+            started: self.is_started(),
+            to_move: (self.is_started() && !game_over).then(|| self.board.get_current_token()),
+            pending_collapse: self.board.get_pending_collapse(),
+            score: self.board.get_score(),
+            game_over,
+        }
+        .to_text()
+    }
+
+    /// Announces a new cycle, and resolves it right away under the random collapse rules.
+    fn handle_cycle(&mut self) {
+        let Some(spooky_mark) = self.board.get_pending_collapse() else {
+            return;
+        };
+
+        self.broadcast(
+            &ServerMessage::Cycle {
+                spooky_mark,
+                chooser: self.board.get_current_token(),
+            }
+            .to_text(),
+        );
+
+        if self.rules == Rules::RandomCollapse {
+            let (spooky_mark, position) = self.board.collapse_loop(&mut self.rng).unwrap();
+
+            self.broadcast(
+                &ServerMessage::Collapsed {
+                    spooky_mark,
+                    position,
+                }
+                .to_text(),
+            );
+        }
+    }
+
+    fn finish_action(&self, room: &str) {
+        self.broadcast(&self.get_state(room));
+
+        if self.board.is_game_over() {
+            self.broadcast(
+                &ServerMessage::GameOver {
+                    score: self.board.get_score(),
+                }
+                .to_text(),
+            );
+        }
+    }
+}
+
+struct Rooms {
+    next_room: u64,
+    rooms: HashMap<String, Room>,
+    /// Seeds the rooms in the order they're created.
+    rng: StdRng,
+}
+
+/// Where a connection sits, so its messages can be routed and its departure announced.
+enum Membership {
+    Player(String),
+    Spectator(String),
+}
+
+/// A WebSocket server where two remote players share a room and any number of spectators
+/// follow along.
+///
+/// Clients send JSON messages tagged by `type` (`join`, `spectate`, `rejoin`, `move` and
+/// `collapse`) and receive every move, cycle, collapse and the full room state in return.
+pub struct MultiplayerServer {
+    next_client: AtomicU64,
+    rooms: Mutex<Rooms>,
+}
+
+impl MultiplayerServer {
+    /// The seed drives the random collapses of the rooms.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            next_client: AtomicU64::new(0),
+            rooms: Mutex::new(Rooms {
+                next_room: 0,
+                rooms: HashMap::new(),
+                rng: StdRng::seed_from_u64(seed),
+            }),
+        }
+    }
+
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = Arc::clone(&self);
+
+            thread::spawn(move || {
+                if let Err(error) = server.handle_connection(stream) {
+                    eprintln!("Connection failed: {error}");
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let mut socket = tungstenite::accept(stream).map_err(io::Error::other)?;
+
+        // Reads time out regularly so that pushed messages get sent in between.
+        socket.get_ref().set_read_timeout(Some(POLL_INTERVAL))?;
+
+        let client_id = self.next_client.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = mpsc::channel();
+        let mut membership = None;
+
+        let result = loop {
+            match socket.read() {
+                Ok(Message::Text(text)) => {
+                    self.handle_message(client_id, &sender, &mut membership, text.as_str())
+                }
+                Ok(Message::Close(_)) => break Ok(()),
+                Ok(_) => {}
+                Err(tungstenite::Error::Io(error))
+                    if matches!(error.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                Err(tungstenite::Error::ConnectionClosed) => break Ok(()),
+                Err(error) => break Err(io::Error::other(error)),
+            }
+
+            if let Err(error) = Self::flush_messages(&mut socket, &receiver) {
+                break Err(error);
+            }
+        };
+
+        self.leave(client_id, membership);
+
+        result
+    }
+
+    fn flush_messages(
+        socket: &mut WebSocket<TcpStream>,
+        receiver: &mpsc::Receiver<String>,
+    ) -> io::Result<()> {
+        while let Ok(message) = receiver.try_recv() {
+            socket
+                .send(Message::text(message))
+                .map_err(io::Error::other)?;
+        }
+
+        Ok(())
+    }
+
+    fn handle_message(
+        &self,
+        client_id: ClientId,
+        sender: &Sender<String>,
+        membership: &mut Option<Membership>,
+        text: &str,
+    ) {
+        let reply = |message: String| {
+            let _ = sender.send(message);
+        };
+
+        let message = match serde_json::from_str::<ClientMessage>(text) {
+            Ok(message) => message,
+            Err(error) => return reply(error_message("bad_request", &error.to_string())),
+        };
+
+        let client = || Client {
+            id: client_id,
+            sender: sender.clone(),
+        };
+
+        let mut rooms = self.rooms.lock().unwrap();
+
+        match (message, membership.as_ref()) {
+            (ClientMessage::Join { room, rules }, None) => {
+                let open = room.is_none();
+                let name = room.unwrap_or_else(|| {
+                    let waiting = rooms
+                        .rooms
+                        .iter()
+                        .find(|(_, room)| room.open && !room.is_started() && room.rules == rules)
+                        .map(|(name, _)| name.clone());
+
+                    waiting.unwrap_or_else(|| {
+                        rooms.next_room += 1;
+                        format!("room-{}", rooms.next_room)
+                    })
+                });
+
+                let rooms = &mut *rooms;
+                let room = rooms
+                    .rooms
+                    .entry(name.clone())
+                    .or_insert_with(|| Room::new(rules, open, rooms.rng.random()));
+
+                if room.rules != rules {
+                    return reply(error_message(
+                        "rules_mismatch",
+                        &format!("Room {name} is played under other rules"),
+                    ));
+                }
+
+                let Some(token) = [Token::X, Token::O]
+                    .into_iter()
+                    .find(|token| room.seats[get_seat_index(*token)].is_none())
+                else {
+                    return reply(error_message("room_full", &format!("Room {name} is full")));
+                };
+
+                let key = format!("{:016x}", rand::rng().random::<u64>());
+
+                room.seats[get_seat_index(token)] = Some(Seat {
+                    key: key.clone(),
+                    client: Some(client()),
+                });
+
+                reply(
+                    ServerMessage::Joined {
+                        room: &name,
+                        token,
+                        key: &key,
+                    }
+                    .to_text(),
+                );
+                room.broadcast(&room.get_state(&name));
+
+                *membership = Some(Membership::Player(name));
+            }
+            (ClientMessage::Spectate { room: name }, None) => {
+                let Some(room) = rooms.rooms.get_mut(&name) else {
+                    return reply(error_message("not_found", &format!("No room {name}")));
+                };
+
+                room.spectators.push(client());
+
+                reply(ServerMessage::Spectating { room: &name }.to_text());
+                reply(room.get_state(&name));
+
+                *membership = Some(Membership::Spectator(name));
+            }
+            (ClientMessage::Rejoin { room: name, key }, None) => {
+                let Some(room) = rooms.rooms.get_mut(&name) else {
+                    return reply(error_message("not_found", &format!("No room {name}")));
+                };
+
+                let Some(token) = [Token::X, Token::O].into_iter().find(|token| {
+                    room.seats[get_seat_index(*token)]
+                        .as_ref()
+                        .is_some_and(|seat| seat.key == key && seat.client.is_none())
+                }) else {
+                    return reply(error_message(
+                        "invalid_key",
+                        "No disconnected seat with that key",
+                    ));
+                };
+
+                room.seats[get_seat_index(token)].as_mut().unwrap().client = Some(client());
+
+                reply(
+                    ServerMessage::Joined {
+                        room: &name,
+                        token,
+                        key: &key,
+                    }
+                    .to_text(),
+                );
+                room.broadcast(&ServerMessage::PlayerReconnected { token }.to_text());
+                room.broadcast(&room.get_state(&name));
+
+                *membership = Some(Membership::Player(name));
+            }
+            (ClientMessage::Join { .. }, Some(_))
+            | (ClientMessage::Spectate { .. }, Some(_))
+            | (ClientMessage::Rejoin { .. }, Some(_)) => {
+                reply(error_message("already_joined", "Already in a room"));
+            }
+            (ClientMessage::Move { positions }, Some(Membership::Player(name))) => {
+                let room = rooms.rooms.get_mut(name).unwrap();
+                let token = room.get_token(client_id).unwrap();
+
+                if !room.is_started() {
+                    return reply(error_message(
+                        "waiting_for_opponent",
+                        "The game starts when both players have joined",
+                    ));
+                }
+
+                let [position_1, position_2] = positions;
+                if let Err(move_error) = room.board.try_do_turn(position_1, position_2, token) {
+                    return reply(move_error_message(move_error));
+                }
+
+                room.broadcast(&ServerMessage::Moved { token, positions }.to_text());
+                room.handle_cycle();
+                room.finish_action(name);
+            }
+            (ClientMessage::Collapse { position }, Some(Membership::Player(name))) => {
+                let room = rooms.rooms.get_mut(name).unwrap();
+                let token = room.get_token(client_id).unwrap();
+
+                if token != room.board.get_current_token() {
+                    return reply(move_error_message(MoveError::WrongTurn(
+                        room.board.get_current_token(),
+                    )));
+                }
+
+                let Some(spooky_mark) = room.board.get_pending_collapse() else {
+                    return reply(move_error_message(MoveError::NoCollapsePending));
+                };
+
+                if let Err(move_error) = room.board.collapse(position) {
+                    return reply(move_error_message(move_error));
+                }
+
+                room.broadcast(
+                    &ServerMessage::Collapsed {
+                        spooky_mark,
+                        position,
+                    }
+                    .to_text(),
+                );
+                room.finish_action(name);
+            }
+            (_, _) => reply(error_message(
+                "not_a_player",
+                "Only seated players can play",
+            )),
+        }
+    }
+
+    fn leave(&self, client_id: ClientId, membership: Option<Membership>) {
+        let mut rooms = self.rooms.lock().unwrap();
+
+        let name = match membership {
+            None => return,
+            Some(Membership::Spectator(name)) => {
+                if let Some(room) = rooms.rooms.get_mut(&name) {
+                    room.spectators.retain(|client| client.id != client_id);
+                }
+                name
+            }
+            Some(Membership::Player(name)) => {
+                if let Some(room) = rooms.rooms.get_mut(&name) {
+                    if let Some(token) = room.get_token(client_id) {
+                        room.seats[get_seat_index(token)].as_mut().unwrap().client = None;
+                        room.broadcast(&ServerMessage::PlayerDisconnected { token }.to_text());
+                    }
+                }
+                name
+            }
+        };
+
+        // Rooms are kept around for reconnection until the game is over and everyone left.
+        if rooms.rooms.get(&name).is_some_and(|room| {
+            room.is_empty() && (room.board.is_game_over() || !room.is_started())
+        }) {
+            rooms.rooms.remove(&name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test_multiplayer {
+    use super::*;
+
+    use std::net::SocketAddr;
+
+    type ClientSocket = WebSocket<TcpStream>;
+
+    fn start_server() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || Arc::new(MultiplayerServer::new(0)).serve(listener));
+
+        address
+    }
+
+    fn connect(address: SocketAddr) -> ClientSocket {
+        let stream = TcpStream::connect(address).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+
+        tungstenite::client(format!("ws://{address}/"), stream)
+            .unwrap()
+            .0
+    }
+
+    fn send(socket: &mut ClientSocket, message: Value) {
+        socket.send(Message::text(message.to_string())).unwrap();
+    }
+
+    fn receive_until(socket: &mut ClientSocket, message_type: &str) -> Value {
+        loop {
+            let message = socket.read().unwrap();
+            let value: Value = serde_json::from_str(message.to_text().unwrap()).unwrap();
+
+            if value["type"] == message_type {
+                return value;
+            }
+        }
+    }
+
+    fn position(row: usize, column: usize) -> Value {
+        json!({ "row": row, "column": column })
+    }
+
+    #[test]
+    fn test_players_and_spectators() {
+        let address = start_server();
+
+        let mut player_x = connect(address);
+        send(
+            &mut player_x,
+            json!({ "type": "join", "rules": "player_collapse" }),
+        );
+        let joined = receive_until(&mut player_x, "joined");
+        assert_eq!("X", joined["token"]);
+        let room = joined["room"].as_str().unwrap().to_string();
+
+        let mut player_o = connect(address);
+        send(
+            &mut player_o,
+            json!({ "type": "join", "rules": "player_collapse" }),
+        );
+        let joined = receive_until(&mut player_o, "joined");
+        assert_eq!("O", joined["token"]);
+        assert_eq!(room, joined["room"]);
+
+        let mut spectator = connect(address);
+        send(&mut spectator, json!({ "type": "spectate", "room": room }));
+        let state = receive_until(&mut spectator, "state");
+        assert!(state["started"].as_bool().unwrap());
+        assert_eq!("X", state["to_move"]);
+
+        send(
+            &mut player_o,
+            json!({ "type": "move", "positions": [position(0, 0), position(1, 1)] }),
+        );
+        let error = receive_until(&mut player_o, "error");
+        assert_eq!("wrong_turn", error["error"]["kind"]);
+
+        send(
+            &mut player_x,
+            json!({ "type": "move", "positions": [position(0, 0), position(1, 1)] }),
+        );
+        let moved = receive_until(&mut spectator, "moved");
+        assert_eq!("X", moved["token"]);
+
+        send(
+            &mut player_o,
+            json!({ "type": "move", "positions": [position(1, 1), position(0, 0)] }),
+        );
+        let cycle = receive_until(&mut spectator, "cycle");
+        assert_eq!("X", cycle["chooser"]);
+        receive_until(&mut player_x, "cycle");
+
+        send(
+            &mut player_x,
+            json!({ "type": "collapse", "position": position(1, 1) }),
+        );
+        let collapsed = receive_until(&mut spectator, "collapsed");
+        assert_eq!(position(1, 1), collapsed["position"]);
+
+        receive_until(&mut player_o, "collapsed");
+        let state = receive_until(&mut player_o, "state");
+        assert!(state["pending_collapse"].is_null());
+        assert_eq!(collapsed["spooky_mark"][2], state["board"]["board"][1][1]);
+    }
+
+    #[test]
+    fn test_reconnection() {
+        let address = start_server();
+
+        let mut player_x = connect(address);
+        send(
+            &mut player_x,
+            json!({ "type": "join", "room": "reconnect" }),
+        );
+        let key = receive_until(&mut player_x, "joined")["key"].clone();
+
+        let mut player_o = connect(address);
+        send(
+            &mut player_o,
+            json!({ "type": "join", "room": "reconnect" }),
+        );
+        receive_until(&mut player_o, "joined");
+
+        let mut other_rules = connect(address);
+        send(
+            &mut other_rules,
+            json!({ "type": "join", "room": "reconnect", "rules": "player_collapse" }),
+        );
+        assert_eq!(
+            "rules_mismatch",
+            receive_until(&mut other_rules, "error")["error"]["kind"]
+        );
+
+        send(
+            &mut player_x,
+            json!({ "type": "move", "positions": [position(0, 0), position(1, 1)] }),
+        );
+        receive_until(&mut player_o, "moved");
+
+        player_x.close(None).unwrap();
+        let disconnected = receive_until(&mut player_o, "player_disconnected");
+        assert_eq!("X", disconnected["token"]);
+
+        let mut player_x = connect(address);
+        send(
+            &mut player_x,
+            json!({ "type": "rejoin", "room": "reconnect", "key": "wrong" }),
+        );
+        assert_eq!(
+            "invalid_key",
+            receive_until(&mut player_x, "error")["error"]["kind"]
+        );
+
+        send(
+            &mut player_x,
+            json!({ "type": "rejoin", "room": "reconnect", "key": key }),
+        );
+        assert_eq!("X", receive_until(&mut player_x, "joined")["token"]);
+        let state = receive_until(&mut player_x, "state");
+        assert_eq!(2, state["board"]["turn"]);
+        assert_eq!("O", state["to_move"]);
+
+        assert_eq!(
+            "X",
+            receive_until(&mut player_o, "player_reconnected")["token"]
+        );
+    }
+}
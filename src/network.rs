@@ -0,0 +1,562 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::prelude::{IndexedRandom, Rng, SeedableRng, SliceRandom, StdRng};
+
+use crate::board::{Board, Position, Token, TurnToken, BOARD_SIZE};
+use crate::bot::Bot;
+use crate::evaluation::Evaluator;
+use crate::mcts::{Guide, MctsBot, Priors};
+use crate::solver;
+
+const SQUARES: usize = BOARD_SIZE * BOARD_SIZE;
+const PAIRS: usize = SQUARES * (SQUARES - 1) / 2;
+
+/// The size of `encode`'s output: the classical marks of each token, the spooky marks of each
+/// token between every pair of squares, whose turn it is and how far the game is.
+pub const INPUT_SIZE: usize = 2 * SQUARES + 2 * PAIRS + 2;
+/// The number of moves the policy ranks: a classical mark in every square, then a spooky mark
+/// in every pair of squares.
+pub const POLICY_SIZE: usize = SQUARES + PAIRS;
+
+const MAGIC: &[u8; 4] = b"TQNN";
+const VERSION: u32 = 1;
+const HEADER_SIZE: usize = 20;
+
+// The index of a pair of distinct squares, counting pairs in row-major order.
+fn get_pair_index(index_1: usize, index_2: usize) -> usize {
+    let (low, high) = (index_1.min(index_2), index_1.max(index_2));
+
+    low * (2 * SQUARES - low - 1) / 2 + (high - low - 1)
+}
+
+/// The index of a move in the policy.
+pub fn get_move_index((position_1, position_2): (Position, Position)) -> usize {
+    let (index_1, index_2) = (position_1.get_index(), position_2.get_index());
+
+    if index_1 == index_2 {
+        index_1
+    } else {
+        SQUARES + get_pair_index(index_1, index_2)
+    }
+}
+
+/// The board as the input of a network, with 1 for every mark present.
+pub fn encode(board: &Board) -> Vec<f32> {
+    let mut input = vec![0.0; INPUT_SIZE];
+
+    for position in &board.positions {
+        match board.get_mark(*position) {
+            Some(TurnToken::X(_)) => input[position.get_index()] = 1.0,
+            Some(TurnToken::O(_)) => input[SQUARES + position.get_index()] = 1.0,
+            None => {}
+        }
+    }
+
+    for spooky_mark in &board.spooky_marks {
+        let offset = match spooky_mark.2 {
+            TurnToken::X(_) => 2 * SQUARES,
+            TurnToken::O(_) => 2 * SQUARES + PAIRS,
+        };
+        input[offset + get_pair_index(spooky_mark.0.get_index(), spooky_mark.1.get_index())] = 1.0;
+    }
+
+    if board.get_current_token() == Token::X {
+        input[INPUT_SIZE - 2] = 1.0;
+    }
+    input[INPUT_SIZE - 1] = (board.turn - 1) as f32 / SQUARES as f32;
+
+    input
+}
+
+/// An encoded board with the outcome and moves the network should predict for it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TrainingExample {
+    pub input: Vec<f32>,
+    /// The final value of the game for X, as X's score minus O's score.
+    pub value: f32,
+    /// How likely each move of the policy should be, summing to 1.
+    pub policy: Vec<f32>,
+}
+
+/// What the network expects of a board.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Prediction {
+    /// The value of the board for X, between -1 and 1.
+    pub value: f32,
+    /// The probability of every move of `solver::get_distinct_moves`, summing to 1.
+    pub policy: Priors,
+}
+
+/// Settings of training a network on games it plays against itself with `MctsBot`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SelfPlayConfig {
+    pub iterations: usize,
+    pub games_per_iteration: usize,
+    /// Searched positions per move of the self-play games.
+    pub simulations: usize,
+    /// Passes over the examples of an iteration.
+    pub epochs: usize,
+    pub learning_rate: f32,
+    pub seed: u64,
+}
+
+impl Default for SelfPlayConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 10,
+            games_per_iteration: 100,
+            simulations: 100,
+            epochs: 5,
+            learning_rate: 0.01,
+            seed: 0,
+        }
+    }
+}
+
+/// A feed-forward network with one hidden layer of `tanh` units, and two heads: the value of
+/// the board for X, squashed by `tanh`, and a softmax policy over its moves.
+///
+/// The file is a 20 byte header, `TQNN`, then the version, the input size, the hidden size
+/// and the policy size as little endian `u32`s, followed by little endian `f32`s: the hidden
+/// weights as one row per hidden unit, the hidden biases, the value weights, the value bias,
+/// the policy weights as one row per move, and the policy biases.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Network {
+    hidden_size: usize,
+    hidden_weights: Vec<f32>,
+    hidden_biases: Vec<f32>,
+    value_weights: Vec<f32>,
+    value_bias: f32,
+    policy_weights: Vec<f32>,
+    policy_biases: Vec<f32>,
+}
+
+struct Activations {
+    hidden: Vec<f32>,
+    value: f32,
+    policy: Vec<f32>,
+}
+
+fn softmax(logits: &mut [f32]) {
+    let max = logits.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+    let mut sum = 0.0;
+
+    for logit in logits.iter_mut() {
+        *logit = (*logit - max).exp();
+        sum += *logit;
+    }
+
+    for logit in logits.iter_mut() {
+        *logit /= sum;
+    }
+}
+
+impl Network {
+    /// A network with small random weights.
+    pub fn new(hidden_size: usize, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        let mut random_weights = |count: usize, fan_in: usize| {
+            let limit = (3.0 / fan_in as f32).sqrt();
+            (0..count)
+                .map(|_| rng.random_range(-limit..limit))
+                .collect::<Vec<_>>()
+        };
+
+        Self {
+            hidden_size,
+            hidden_weights: random_weights(hidden_size * INPUT_SIZE, INPUT_SIZE),
+            hidden_biases: vec![0.0; hidden_size],
+            value_weights: random_weights(hidden_size, hidden_size),
+            value_bias: 0.0,
+            policy_weights: random_weights(POLICY_SIZE * hidden_size, hidden_size),
+            policy_biases: vec![0.0; POLICY_SIZE],
+        }
+    }
+
+    pub fn get_hidden_size(&self) -> usize {
+        self.hidden_size
+    }
+
+    fn forward(&self, input: &[f32]) -> Activations {
+        let hidden = (0..self.hidden_size)
+            .map(|unit| {
+                let weights = &self.hidden_weights[unit * INPUT_SIZE..(unit + 1) * INPUT_SIZE];
+                let sum = weights.iter().zip(input).map(|(w, x)| w * x).sum::<f32>();
+
+                (sum + self.hidden_biases[unit]).tanh()
+            })
+            .collect::<Vec<_>>();
+
+        let value = (self
+            .value_weights
+            .iter()
+            .zip(&hidden)
+            .map(|(w, h)| w * h)
+            .sum::<f32>()
+            + self.value_bias)
+            .tanh();
+
+        let mut policy = (0..POLICY_SIZE)
+            .map(|index| {
+                let weights =
+                    &self.policy_weights[index * self.hidden_size..(index + 1) * self.hidden_size];
+
+                weights.iter().zip(&hidden).map(|(w, h)| w * h).sum::<f32>()
+                    + self.policy_biases[index]
+            })
+            .collect::<Vec<_>>();
+        softmax(&mut policy);
+
+        Activations {
+            hidden,
+            value,
+            policy,
+        }
+    }
+
+    pub fn predict(&self, board: &Board) -> Prediction {
+        let activations = self.forward(&encode(board));
+
+        let legal_moves = solver::get_distinct_moves(board);
+        let total = legal_moves
+            .iter()
+            .map(|legal_move| activations.policy[get_move_index(*legal_move)])
+            .sum::<f32>();
+
+        let policy = legal_moves
+            .into_iter()
+            .map(|legal_move| {
+                (
+                    legal_move,
+                    activations.policy[get_move_index(legal_move)] / total,
+                )
+            })
+            .collect();
+
+        Prediction {
+            value: activations.value,
+            policy,
+        }
+    }
+
+    // One step of gradient descent on the squared error of the value plus the cross entropy of
+    // the policy, returning the loss before the step.
+    fn train_example(&mut self, example: &TrainingExample, learning_rate: f32) -> f32 {
+        let activations = self.forward(&example.input);
+        let hidden = &activations.hidden;
+
+        let value_error = activations.value - example.value;
+        let loss = value_error * value_error
+            - example
+                .policy
+                .iter()
+                .zip(&activations.policy)
+                .filter(|(target, _)| **target > 0.0)
+                .map(|(target, p)| target * p.max(1e-12).ln())
+                .sum::<f32>();
+
+        let value_gradient = 2.0 * value_error * (1.0 - activations.value * activations.value);
+        let policy_gradients = activations
+            .policy
+            .iter()
+            .zip(&example.policy)
+            .map(|(p, target)| p - target)
+            .collect::<Vec<_>>();
+
+        let mut hidden_gradients = self
+            .value_weights
+            .iter()
+            .map(|w| w * value_gradient)
+            .collect::<Vec<_>>();
+
+        for (index, gradient) in policy_gradients.iter().enumerate() {
+            let row = index * self.hidden_size;
+
+            for unit in 0..self.hidden_size {
+                hidden_gradients[unit] += self.policy_weights[row + unit] * gradient;
+                self.policy_weights[row + unit] -= learning_rate * gradient * hidden[unit];
+            }
+            self.policy_biases[index] -= learning_rate * gradient;
+        }
+
+        for (weight, h) in self.value_weights.iter_mut().zip(hidden) {
+            *weight -= learning_rate * value_gradient * h;
+        }
+        self.value_bias -= learning_rate * value_gradient;
+
+        for unit in 0..self.hidden_size {
+            let gradient = hidden_gradients[unit] * (1.0 - hidden[unit] * hidden[unit]);
+            let row = unit * INPUT_SIZE;
+
+            for (weight, x) in self.hidden_weights[row..row + INPUT_SIZE]
+                .iter_mut()
+                .zip(&example.input)
+            {
+                *weight -= learning_rate * gradient * x;
+            }
+            self.hidden_biases[unit] -= learning_rate * gradient;
+        }
+
+        loss
+    }
+
+    /// Trains on the examples in a random order every epoch, and returns the mean loss of the
+    /// last epoch.
+    pub fn train(
+        &mut self,
+        examples: &[TrainingExample],
+        epochs: usize,
+        learning_rate: f32,
+        seed: u64,
+    ) -> f32 {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut order = (0..examples.len()).collect::<Vec<_>>();
+        let mut loss = 0.0;
+
+        for _ in 0..epochs {
+            order.shuffle(&mut rng);
+
+            loss = order
+                .iter()
+                .map(|index| self.train_example(&examples[*index], learning_rate))
+                .sum::<f32>()
+                / examples.len().max(1) as f32;
+        }
+
+        loss
+    }
+
+    /// Plays games against itself with `MctsBot`, picking moves in proportion to their visits,
+    /// and returns an example for every position, with the visits as the target policy.
+    pub fn generate_examples(
+        &self,
+        games: usize,
+        simulations: usize,
+        seed: u64,
+    ) -> Vec<TrainingExample> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut bot = MctsBot::new(self.clone(), simulations, rng.random());
+        let mut examples = vec![];
+
+        for _ in 0..games {
+            let mut board = Board::new();
+            let first_example = examples.len();
+
+            while !board.is_game_over() {
+                let visits = bot.search(&board);
+                let total = visits.iter().map(|(_, count)| count).sum::<u32>() as f32;
+
+                let mut policy = vec![0.0; POLICY_SIZE];
+                for (positions, count) in &visits {
+                    policy[get_move_index(*positions)] = *count as f32 / total;
+                }
+
+                examples.push(TrainingExample {
+                    input: encode(&board),
+                    value: 0.0,
+                    policy,
+                });
+
+                let ((position_1, position_2), _) = *visits
+                    .choose_weighted(&mut rng, |(_, count)| *count)
+                    .unwrap();
+                board.do_turn(position_1, position_2, board.get_current_token());
+                board.collapse_loop(&mut rng);
+            }
+
+            let value = solver::get_value(&board);
+            for example in &mut examples[first_example..] {
+                example.value = value;
+            }
+        }
+
+        examples
+    }
+
+    /// Alternates self-play and training, and returns the loss of every iteration.
+    pub fn train_by_self_play(&mut self, config: &SelfPlayConfig) -> Vec<f32> {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+
+        (0..config.iterations)
+            .map(|_| {
+                let examples = self.generate_examples(
+                    config.games_per_iteration,
+                    config.simulations,
+                    rng.random(),
+                );
+
+                self.train(&examples, config.epochs, config.learning_rate, rng.random())
+            })
+            .collect()
+    }
+
+    fn get_parameters(&self) -> impl Iterator<Item = &f32> {
+        self.hidden_weights
+            .iter()
+            .chain(&self.hidden_biases)
+            .chain(&self.value_weights)
+            .chain(std::iter::once(&self.value_bias))
+            .chain(&self.policy_weights)
+            .chain(&self.policy_biases)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut bytes = vec![];
+
+        bytes.extend_from_slice(MAGIC);
+        for size in [VERSION as usize, INPUT_SIZE, self.hidden_size, POLICY_SIZE] {
+            bytes.extend_from_slice(&(size as u32).to_le_bytes());
+        }
+
+        for parameter in self.get_parameters() {
+            bytes.extend_from_slice(&parameter.to_le_bytes());
+        }
+
+        fs::write(path, bytes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(invalid("Not a network file"));
+        }
+
+        let [version, input_size, hidden_size, policy_size] = [4, 8, 12, 16]
+            .map(|start| u32::from_le_bytes(bytes[start..start + 4].try_into().unwrap()) as usize);
+
+        if version != VERSION as usize {
+            return Err(invalid("Unsupported network version"));
+        }
+
+        if input_size != INPUT_SIZE || policy_size != POLICY_SIZE {
+            return Err(invalid("Network doesn't match the board encoding"));
+        }
+
+        let parameter_count = hidden_size * (INPUT_SIZE + 2 + POLICY_SIZE) + 1 + POLICY_SIZE;
+
+        if bytes.len() != HEADER_SIZE + 4 * parameter_count {
+            return Err(invalid("Truncated network file"));
+        }
+
+        let mut parameters = bytes[HEADER_SIZE..]
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()));
+        let mut take = |count: usize| parameters.by_ref().take(count).collect::<Vec<_>>();
+
+        Ok(Self {
+            hidden_size,
+            hidden_weights: take(hidden_size * INPUT_SIZE),
+            hidden_biases: take(hidden_size),
+            value_weights: take(hidden_size),
+            value_bias: take(1)[0],
+            policy_weights: take(POLICY_SIZE * hidden_size),
+            policy_biases: take(POLICY_SIZE),
+        })
+    }
+}
+
+impl Evaluator for Network {
+    fn evaluate(&self, board: &Board) -> f32 {
+        if board.is_game_over() {
+            return solver::get_value(board);
+        }
+
+        self.forward(&encode(board)).value
+    }
+}
+
+impl Guide for Network {
+    fn guide(&mut self, board: &Board) -> (f32, Priors) {
+        let prediction = self.predict(board);
+
+        (prediction.value, prediction.policy)
+    }
+}
+
+/// Plays the move the network's policy finds most likely, without searching.
+#[derive(Clone)]
+pub struct NetworkBot {
+    network: Network,
+}
+
+impl NetworkBot {
+    pub fn new(network: Network) -> Self {
+        Self { network }
+    }
+}
+
+impl Bot for NetworkBot {
+    fn get_next_move(&mut self, board: &Board, _token: Token) -> (Position, Position) {
+        let policy = self.network.predict(board).policy;
+
+        // The policy covers the legal moves, and bots are only asked to move when there are some.
+        let (best_move, _) = policy
+            .into_iter()
+            .reduce(|best, m| if m.1 > best.1 { m } else { best })
+            .expect("The board has legal moves");
+
+        best_move
+    }
+}
+
+#[cfg(test)]
+mod test_network {
+    use super::*;
+
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_encoding() {
+        let positions = Board::new().positions;
+
+        let indices = positions
+            .iter()
+            .flat_map(|p1| positions.iter().map(move |p2| get_move_index((*p1, *p2))))
+            .collect::<HashSet<_>>();
+        assert_eq!((0..POLICY_SIZE).collect::<HashSet<_>>(), indices);
+
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(2, 2), Token::X);
+        board.do_turn(Position::new(1, 1), Position::new(1, 1), Token::O);
+
+        let input = encode(&board);
+        assert_eq!(INPUT_SIZE, input.len());
+        assert_eq!(1.0, input[SQUARES + 4]);
+        assert_eq!(1.0, input[2 * SQUARES + get_pair_index(0, 8)]);
+        assert_eq!(1.0, input[INPUT_SIZE - 2]);
+        assert_eq!(3.0, input.iter().sum::<f32>() - input[INPUT_SIZE - 1]);
+    }
+
+    #[test]
+    fn test_training_and_file_format() {
+        let mut network = Network::new(16, 0);
+        let examples = network.generate_examples(2, 10, 0);
+        assert!(examples
+            .iter()
+            .all(|e| (e.policy.iter().sum::<f32>() - 1.0).abs() < 1e-4));
+
+        let first_loss = network.train(&examples, 1, 0.01, 0);
+        let last_loss = network.train(&examples, 20, 0.01, 0);
+        assert!(last_loss < first_loss, "{first_loss} {last_loss}");
+
+        let prediction = network.predict(&Board::new());
+        assert!((prediction.policy.iter().map(|(_, p)| p).sum::<f32>() - 1.0).abs() < 1e-4);
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("network.tqnn");
+        network.save(&path).unwrap();
+        assert_eq!(network, Network::load(&path).unwrap());
+
+        let (position_1, position_2) =
+            NetworkBot::new(network).get_next_move(&Board::new(), Token::X);
+        assert!(Board::new()
+            .try_do_turn(position_1, position_2, Token::X)
+            .is_ok());
+    }
+}
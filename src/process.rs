@@ -0,0 +1,467 @@
+use std::io::{self, BufRead, BufReader, Write};
+use std::process::{Child, ChildStdin, Command, ExitStatus, Stdio};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::board::{Board, Position, Token};
+use crate::bot::Bot;
+use crate::game::Rules;
+use crate::protocol::{self, Response};
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+// The time a process gets over a move's budget, for starting to search and for its answer to
+// come back through the pipes.
+const MARGIN: Duration = Duration::from_millis(100);
+
+/// A bot played by an external engine, running as a child process that speaks the protocol of
+/// `PROTOCOL.md` over its standard input and output.
+///
+/// An engine that crashes, doesn't answer in time or answers something else than a move fails
+/// its move, which `Game` turns into a forfeit. Every line sent and received is logged.
+pub struct ProcessBot {
+    child: Child,
+    stdin: ChildStdin,
+    lines: Receiver<String>,
+    name: String,
+    rules: Rules,
+    timeout: Duration,
+    log: Vec<String>,
+    echo: bool,
+    // The turn of the last board asked about, to tell when a new game starts.
+    last_turn: Option<u8>,
+    // Whether an answer may still come for a move that timed out.
+    out_of_sync: bool,
+    // Set once the process can't be talked to anymore.
+    crash: Option<String>,
+    failure: Option<String>,
+}
+
+impl ProcessBot {
+    /// Starts the command and waits for its handshake, failing if it doesn't complete in time.
+    pub fn spawn(mut command: Command) -> io::Result<Self> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+
+        let stdin = child.stdin.take().unwrap();
+        let stdout = child.stdout.take().unwrap();
+        let (sender, lines) = mpsc::channel();
+
+        // Reading on a thread of its own is what lets a silent engine time out. The thread
+        // ends with the process, closing the channel.
+        thread::spawn(move || {
+            for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        let mut bot = Self {
+            child,
+            stdin,
+            lines,
+            name: String::new(),
+            rules: Rules::default(),
+            timeout: DEFAULT_TIMEOUT,
+            log: vec![],
+            echo: false,
+            last_turn: None,
+            out_of_sync: false,
+            crash: None,
+            failure: None,
+        };
+
+        bot.handshake()
+            .map_err(|message| io::Error::other(format!("Engine handshake failed: {message}")))?;
+
+        Ok(bot)
+    }
+
+    /// The name the engine gave in its handshake.
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    /// Every line sent to the engine, starting with `> `, and received from it, starting with
+    /// `< `, along with the failures, starting with `! `.
+    pub fn get_log(&self) -> &[String] {
+        &self.log
+    }
+
+    /// Whether the lines of the log are also printed to the standard error as they come.
+    pub fn set_echo(&mut self, echo: bool) {
+        self.echo = echo;
+    }
+
+    /// The rules told to the engine at the start of every game.
+    pub fn set_rules(&mut self, rules: Rules) {
+        self.rules = rules;
+    }
+
+    /// How long the engine gets for a move asked for without a budget, and for its handshake.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    fn log(&mut self, line: String) {
+        if self.echo {
+            eprintln!("{line}");
+        }
+
+        self.log.push(line);
+    }
+
+    fn send(&mut self, command: &protocol::Command) -> Result<(), String> {
+        let line = command.to_string();
+        self.log(format!("> {line}"));
+
+        writeln!(self.stdin, "{line}")
+            .and_then(|_| self.stdin.flush())
+            .map_err(|error| self.crashed(format!("couldn't write to the engine: {error}")))
+    }
+
+    // The next response that the filter accepts, skipping the others.
+    fn receive<T>(
+        &mut self,
+        timeout: Duration,
+        mut filter: impl FnMut(Response) -> Option<T>,
+    ) -> Result<T, String> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            let line = match self
+                .lines
+                .recv_timeout(deadline.saturating_duration_since(Instant::now()))
+            {
+                Ok(line) => line,
+                Err(RecvTimeoutError::Timeout) => {
+                    self.out_of_sync = true;
+                    return Err(format!("no answer within {timeout:?}"));
+                }
+                Err(RecvTimeoutError::Disconnected) => {
+                    let status = match self.wait_for_exit(MARGIN) {
+                        Some(status) => status.to_string(),
+                        None => "closed its output".to_string(),
+                    };
+                    return Err(self.crashed(format!("the engine exited ({status})")));
+                }
+            };
+
+            self.log(format!("< {line}"));
+
+            // Engines may print lines of their own, which are only logged.
+            if let Some(result) = Response::parse(&line).ok().and_then(&mut filter) {
+                return Ok(result);
+            }
+        }
+    }
+
+    fn wait_for_exit(&mut self, timeout: Duration) -> Option<ExitStatus> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            match self.child.try_wait() {
+                Ok(Some(status)) => return Some(status),
+                Ok(None) if Instant::now() < deadline => thread::sleep(Duration::from_millis(5)),
+                _ => return None,
+            }
+        }
+    }
+
+    fn crashed(&mut self, message: String) -> String {
+        self.crash = Some(message.clone());
+        message
+    }
+
+    fn handshake(&mut self) -> Result<(), String> {
+        self.send(&protocol::Command::Qttt)?;
+
+        let mut name = None;
+        self.receive(self.timeout, |response| match response {
+            Response::Id { name: id } => {
+                name = Some(id);
+                None
+            }
+            Response::QtttOk => Some(()),
+            _ => None,
+        })?;
+
+        self.name = name.unwrap_or_default();
+
+        Ok(())
+    }
+
+    // Gets the engine ready for a request about the board, starting a new game if needed.
+    fn prepare(&mut self, board: &Board) -> Result<(), String> {
+        if let Some(crash) = &self.crash {
+            return Err(crash.clone());
+        }
+
+        // A late answer to a move that timed out must not be taken for the next move.
+        if self.out_of_sync {
+            self.send(&protocol::Command::IsReady)?;
+            self.receive(self.timeout, |response| {
+                (response == Response::ReadyOk).then_some(())
+            })?;
+            self.out_of_sync = false;
+        }
+
+        if self.last_turn.is_none_or(|turn| board.turn < turn) {
+            self.send(&protocol::Command::NewGame(self.rules))?;
+        }
+        self.last_turn = Some(board.turn);
+
+        Ok(())
+    }
+
+    fn request_move(
+        &mut self,
+        board: &Board,
+        budget: Option<Duration>,
+    ) -> Result<(Position, Position), String> {
+        self.prepare(board)?;
+
+        self.send(&protocol::Command::Marks(Box::new(board.clone())))?;
+        self.send(&protocol::Command::Go { movetime: budget })?;
+
+        self.receive_answer(
+            budget.unwrap_or(self.timeout) + MARGIN,
+            |response| match response {
+                Response::BestMove(positions) => Some(positions),
+                _ => None,
+            },
+        )
+    }
+
+    fn request_collapse(&mut self, board: &Board) -> Result<Position, String> {
+        self.prepare(board)?;
+
+        self.send(&protocol::Command::Marks(Box::new(board.clone())))?;
+        self.send(&protocol::Command::Collapse)?;
+
+        self.receive_answer(self.timeout, |response| match response {
+            Response::BestCollapse(position) => Some(position),
+            _ => None,
+        })
+    }
+
+    // The answer the filter accepts, or the error the engine answered instead.
+    fn receive_answer<T>(
+        &mut self,
+        timeout: Duration,
+        mut filter: impl FnMut(Response) -> Option<T>,
+    ) -> Result<T, String> {
+        self.receive(timeout, |response| match response {
+            Response::Error(message) => Some(Err(message)),
+            response => filter(response).map(Ok),
+        })?
+        .map_err(|message| format!("the engine answered an error: {message}"))
+    }
+
+    // Keeps the outcome of a request as the failure of the bot's last answer.
+    fn record<T>(&mut self, result: Result<T, String>) -> Option<T> {
+        match result {
+            Ok(answer) => {
+                self.failure = None;
+                Some(answer)
+            }
+            Err(failure) => {
+                self.log(format!("! {failure}"));
+                self.failure = Some(failure);
+                None
+            }
+        }
+    }
+
+    fn play(&mut self, board: &Board, budget: Option<Duration>) -> (Position, Position) {
+        let result = self.request_move(board, budget);
+
+        // Any move will do after a failure, since the game is forfeited.
+        self.record(result).unwrap_or_else(|| {
+            board
+                .get_legal_moves()
+                .first()
+                .copied()
+                .unwrap_or((Position::new(0, 0), Position::new(0, 0)))
+        })
+    }
+}
+
+impl Bot for ProcessBot {
+    fn get_next_move(&mut self, board: &Board, _token: Token) -> (Position, Position) {
+        self.play(board, None)
+    }
+
+    fn get_timed_move(
+        &mut self,
+        board: &Board,
+        _token: Token,
+        budget: Duration,
+    ) -> (Position, Position) {
+        self.play(board, Some(budget))
+    }
+
+    fn get_failure(&mut self, _token: Token) -> Option<String> {
+        self.failure.clone()
+    }
+
+    fn choose_collapse(&mut self, board: &Board, _token: Token) -> Position {
+        let result = self.request_collapse(board);
+
+        self.record(result)
+            .unwrap_or_else(|| board.get_pending_collapse().unwrap().0)
+    }
+
+    fn new_game(&mut self, rules: Rules, _token: Token) {
+        self.rules = rules;
+        self.last_turn = None;
+    }
+}
+
+impl Drop for ProcessBot {
+    // Asks the engine to quit, and kills it if it takes too long.
+    fn drop(&mut self) {
+        if self.crash.is_none() {
+            let _ = writeln!(self.stdin, "{}", protocol::Command::Quit);
+            let _ = self.stdin.flush();
+        }
+
+        if self.wait_for_exit(MARGIN).is_none() {
+            let _ = self.child.kill();
+        }
+        let _ = self.child.wait();
+    }
+}
+
+#[cfg(all(test, unix))]
+mod test_process_bot {
+    use super::*;
+
+    use crate::bot::{BotPair, RandomBot};
+    use crate::game::{Forfeit, Game, TimeControl};
+
+    // An engine written as a shell script, answering `go` with the given line.
+    fn spawn_script(go: &str) -> ProcessBot {
+        let script = format!(
+            "while read line; do case $line in \
+             qttt) echo 'id name script'; echo qtttok;; \
+             isready) echo readyok;; \
+             go*) {go};; \
+             collapse) echo 'bestcollapse b2';; \
+             quit) exit;; \
+             esac; done"
+        );
+        let mut command = Command::new("sh");
+        command.args(["-c", &script]);
+
+        ProcessBot::spawn(command).unwrap()
+    }
+
+    fn play_against_random(bot: ProcessBot, time_control: Option<TimeControl>) -> Game<impl Bot> {
+        let bots = BotPair {
+            bot_x: bot,
+            bot_o: RandomBot::new(0),
+        };
+        let mut game = Game::new(bots, 0);
+        game.set_verbose(false);
+        if let Some(time_control) = time_control {
+            game.set_time_control(time_control);
+        }
+
+        game.play_whole_game();
+
+        game
+    }
+
+    #[test]
+    fn test_handshake_and_log() {
+        let mut bot = spawn_script("echo 'bestmove b2b2'");
+        assert_eq!("script", bot.get_name());
+
+        assert_eq!(
+            (Position::new(1, 1), Position::new(1, 1)),
+            bot.get_next_move(&Board::new(), Token::X)
+        );
+        assert_eq!(None, bot.get_failure(Token::X));
+        assert_eq!(
+            vec![
+                "> qttt",
+                "< id name script",
+                "< qtttok",
+                "> newgame random_collapse",
+                "> position marks",
+                "> go",
+                "< bestmove b2b2",
+            ],
+            bot.get_log()
+        );
+    }
+
+    #[test]
+    fn test_choose_collapse() {
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::X);
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::O);
+
+        let mut bot = spawn_script("echo 'bestmove b2b2'");
+        bot.new_game(Rules::PlayerCollapse, Token::X);
+
+        assert_eq!(Position::new(1, 1), bot.choose_collapse(&board, Token::X));
+        assert_eq!(None, bot.get_failure(Token::X));
+        assert_eq!(
+            [
+                "> newgame player_collapse",
+                "> position marks X1a1b2 O2a1b2",
+                "> collapse",
+                "< bestcollapse b2",
+            ],
+            bot.get_log()[3..]
+        );
+    }
+
+    #[test]
+    fn test_illegal_move_forfeits() {
+        // The second move of X is on a square that is already taken.
+        let game = play_against_random(spawn_script("echo 'bestmove b2b2'"), None);
+
+        assert!(matches!(
+            game.get_forfeit(),
+            Some((Token::X, Forfeit::IllegalMove(_)))
+        ));
+        assert_eq!((0.0, 1.0), game.get_score());
+    }
+
+    #[test]
+    fn test_crash_forfeits() {
+        let game = play_against_random(spawn_script("exit 3"), None);
+
+        assert!(matches!(
+            game.get_forfeit(),
+            Some((Token::X, Forfeit::Failure(message))) if message.contains("exit status: 3")
+        ));
+        assert!(game.get_record().turns.is_empty());
+    }
+
+    #[test]
+    fn test_timeout_forfeits_and_recovers() {
+        let mut bot = spawn_script("sleep 0.3; echo 'bestmove b2b2'");
+
+        bot.get_timed_move(&Board::new(), Token::X, Duration::from_millis(50));
+        assert!(bot.get_failure(Token::X).unwrap().starts_with("no answer"));
+
+        // The late answer to the first move is skipped.
+        bot.get_timed_move(&Board::new(), Token::X, Duration::from_millis(500));
+        assert_eq!(None, bot.get_failure(Token::X));
+        assert!(bot.get_log().contains(&"> isready".to_string()));
+
+        let game = play_against_random(bot, Some(TimeControl::PerMove(Duration::from_millis(10))));
+        assert!(matches!(
+            game.get_forfeit(),
+            Some((Token::X, Forfeit::Failure(_)))
+        ));
+    }
+}
@@ -0,0 +1,570 @@
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::time::Duration;
+
+use derive_more::{Display, Error};
+use itertools::Itertools;
+
+use crate::board::{Board, MoveError, Position, SpookyMark, Token, TurnToken, BOARD_SIZE};
+use crate::bot::Bot;
+use crate::game::Rules;
+
+/// A line of the engine protocol that couldn't be understood or played, see `PROTOCOL.md`.
+#[derive(Clone, Debug, Display, Eq, Error, PartialEq)]
+pub enum ProtocolError {
+    #[display("Invalid square {_0}")]
+    InvalidSquare(#[error(not(source))] String),
+    #[display("Invalid move {_0}")]
+    InvalidMove(#[error(not(source))] String),
+    #[display("Invalid mark {_0}")]
+    InvalidMark(#[error(not(source))] String),
+    #[display("Unknown rules {_0}")]
+    UnknownRules(#[error(not(source))] String),
+    #[display("Invalid command {_0}")]
+    InvalidCommand(#[error(not(source))] String),
+    #[display("{_0}")]
+    IllegalMove(MoveError),
+}
+
+impl From<MoveError> for ProtocolError {
+    fn from(error: MoveError) -> Self {
+        ProtocolError::IllegalMove(error)
+    }
+}
+
+/// The square's name, its column `a` to `c` from the left and its row `1` to `3` from the top.
+pub fn format_square(position: Position) -> String {
+    format!(
+        "{}{}",
+        (b'a' + position.column() as u8) as char,
+        position.row() + 1
+    )
+}
+
+pub fn parse_square(square: &str) -> Result<Position, ProtocolError> {
+    let invalid = || ProtocolError::InvalidSquare(square.to_string());
+
+    let [column, row] = square.as_bytes() else {
+        return Err(invalid());
+    };
+
+    let column = column.wrapping_sub(b'a') as usize;
+    let row = row.wrapping_sub(b'1') as usize;
+
+    if column >= BOARD_SIZE || row >= BOARD_SIZE {
+        return Err(invalid());
+    }
+
+    Ok(Position::new(row, column))
+}
+
+/// A move as written in the protocol, like `a1b2`, with the square its cycle collapsed to
+/// after a colon, like `a1b2:b2`, if it closed one.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProtocolMove {
+    pub positions: (Position, Position),
+    pub collapse: Option<Position>,
+}
+
+impl fmt::Display for ProtocolMove {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}{}",
+            format_square(self.positions.0),
+            format_square(self.positions.1)
+        )?;
+
+        match self.collapse {
+            Some(position) => write!(f, ":{}", format_square(position)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl ProtocolMove {
+    pub fn parse(text: &str) -> Result<Self, ProtocolError> {
+        let (squares, collapse) = match text.split_once(':') {
+            Some((squares, collapse)) => (squares, Some(parse_square(collapse)?)),
+            None => (text, None),
+        };
+
+        if squares.len() != 4 || !squares.is_ascii() {
+            return Err(ProtocolError::InvalidMove(text.to_string()));
+        }
+
+        Ok(Self {
+            positions: (parse_square(&squares[..2])?, parse_square(&squares[2..])?),
+            collapse,
+        })
+    }
+}
+
+/// The marks of the board in the order they were played, each written as its label followed by
+/// its square, or its two squares for a spooky mark, like `X1b2 O2a1c3`.
+pub fn format_marks(board: &Board) -> Vec<String> {
+    let classical_marks = board.positions.iter().filter_map(|position| {
+        board
+            .get_mark(*position)
+            .map(|turn_token| (turn_token, format_square(*position)))
+    });
+    let spooky_marks =
+        board
+            .spooky_marks
+            .iter()
+            .map(|SpookyMark(position_1, position_2, turn_token)| {
+                (
+                    *turn_token,
+                    format_square(*position_1) + &format_square(*position_2),
+                )
+            });
+
+    classical_marks
+        .chain(spooky_marks)
+        .sorted_by_key(|(turn_token, _)| get_turn(*turn_token))
+        .map(|(turn_token, squares)| turn_token.get_label() + &squares)
+        .collect()
+}
+
+fn get_turn(turn_token: TurnToken) -> u8 {
+    match turn_token {
+        TurnToken::X(turn) | TurnToken::O(turn) => turn,
+    }
+}
+
+/// The board with the marks of `format_marks`, which have to be numbered from 1 on, with X on
+/// odd turns and O on even ones. The marks are replayed as moves in the order of their turns, so
+/// they have to be legal then: only the last mark may close a cycle.
+pub fn parse_marks(marks: &[&str]) -> Result<Board, ProtocolError> {
+    let mut board = Board::new();
+
+    for (index, mark) in marks.iter().enumerate() {
+        let invalid = || ProtocolError::InvalidMark(mark.to_string());
+
+        if !mark.is_ascii() || (mark.len() != 4 && mark.len() != 6) {
+            return Err(invalid());
+        }
+
+        let turn = index as u8 + 1;
+        let token = match &mark[..2] {
+            label if label == TurnToken::X(turn).get_label() && !turn.is_multiple_of(2) => Token::X,
+            label if label == TurnToken::O(turn).get_label() && turn.is_multiple_of(2) => Token::O,
+            _ => return Err(invalid()),
+        };
+
+        let position_1 = parse_square(&mark[2..4])?;
+        let position_2 = match mark.len() {
+            6 => parse_square(&mark[4..])?,
+            _ => position_1,
+        };
+
+        board
+            .try_do_turn(position_1, position_2, token)
+            .map_err(|_| invalid())?;
+    }
+
+    Ok(board)
+}
+
+/// A line sent to an engine.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Command {
+    Qttt,
+    IsReady,
+    NewGame(Rules),
+    Position(Vec<ProtocolMove>),
+    /// A position given by its marks rather than by its moves, for boards whose moves aren't
+    /// known anymore.
+    Marks(Box<Board>),
+    Go {
+        movetime: Option<Duration>,
+    },
+    Collapse,
+    Quit,
+}
+
+fn format_rules(rules: Rules) -> &'static str {
+    match rules {
+        Rules::RandomCollapse => "random_collapse",
+        Rules::PlayerCollapse => "player_collapse",
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Command::Qttt => write!(f, "qttt"),
+            Command::IsReady => write!(f, "isready"),
+            Command::NewGame(rules) => write!(f, "newgame {}", format_rules(*rules)),
+            Command::Position(moves) if moves.is_empty() => write!(f, "position startpos"),
+            Command::Position(moves) => {
+                write!(f, "position startpos moves")?;
+                for protocol_move in moves {
+                    write!(f, " {protocol_move}")?;
+                }
+                Ok(())
+            }
+            Command::Marks(board) => {
+                write!(f, "position marks")?;
+                for mark in format_marks(board) {
+                    write!(f, " {mark}")?;
+                }
+                Ok(())
+            }
+            Command::Go { movetime: None } => write!(f, "go"),
+            Command::Go {
+                movetime: Some(movetime),
+            } => write!(f, "go movetime {}", movetime.as_millis()),
+            Command::Collapse => write!(f, "collapse"),
+            Command::Quit => write!(f, "quit"),
+        }
+    }
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Self, ProtocolError> {
+        let invalid = || ProtocolError::InvalidCommand(line.to_string());
+        let words = line.split_whitespace().collect::<Vec<_>>();
+
+        match words[..] {
+            ["qttt"] => Ok(Command::Qttt),
+            ["isready"] => Ok(Command::IsReady),
+            ["newgame"] => Ok(Command::NewGame(Rules::RandomCollapse)),
+            ["newgame", rules] => serde_json::from_value(rules.into())
+                .map(Command::NewGame)
+                .map_err(|_| ProtocolError::UnknownRules(rules.to_string())),
+            ["position", "startpos"] => Ok(Command::Position(vec![])),
+            ["position", "startpos", "moves", ref moves @ ..] => moves
+                .iter()
+                .map(|text| ProtocolMove::parse(text))
+                .collect::<Result<_, _>>()
+                .map(Command::Position),
+            ["position", "marks", ref marks @ ..] => {
+                parse_marks(marks).map(|board| Command::Marks(Box::new(board)))
+            }
+            ["go"] => Ok(Command::Go { movetime: None }),
+            ["go", "movetime", milliseconds] => milliseconds
+                .parse()
+                .map(|milliseconds| Command::Go {
+                    movetime: Some(Duration::from_millis(milliseconds)),
+                })
+                .map_err(|_| invalid()),
+            ["collapse"] => Ok(Command::Collapse),
+            ["quit"] => Ok(Command::Quit),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A line sent back by an engine.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Response {
+    Id { name: String },
+    QtttOk,
+    ReadyOk,
+    BestMove((Position, Position)),
+    BestCollapse(Position),
+    Error(String),
+}
+
+impl fmt::Display for Response {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Response::Id { name } => write!(f, "id name {name}"),
+            Response::QtttOk => write!(f, "qtttok"),
+            Response::ReadyOk => write!(f, "readyok"),
+            Response::BestMove(positions) => write!(
+                f,
+                "bestmove {}",
+                ProtocolMove {
+                    positions: *positions,
+                    collapse: None,
+                }
+            ),
+            Response::BestCollapse(position) => {
+                write!(f, "bestcollapse {}", format_square(*position))
+            }
+            Response::Error(message) => write!(f, "error {message}"),
+        }
+    }
+}
+
+impl Response {
+    pub fn parse(line: &str) -> Result<Self, ProtocolError> {
+        let invalid = || ProtocolError::InvalidCommand(line.to_string());
+
+        if let Some(name) = line.strip_prefix("id name ") {
+            return Ok(Response::Id {
+                name: name.to_string(),
+            });
+        }
+
+        if let Some(message) = line.strip_prefix("error ") {
+            return Ok(Response::Error(message.to_string()));
+        }
+
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            ["qtttok"] => Ok(Response::QtttOk),
+            ["readyok"] => Ok(Response::ReadyOk),
+            ["bestmove", text] => match ProtocolMove::parse(text)? {
+                ProtocolMove {
+                    positions,
+                    collapse: None,
+                } => Ok(Response::BestMove(positions)),
+                _ => Err(invalid()),
+            },
+            ["bestcollapse", square] => Ok(Response::BestCollapse(parse_square(square)?)),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// The board after the moves, which may end with a cycle still to collapse.
+pub fn replay(moves: &[ProtocolMove]) -> Result<Board, ProtocolError> {
+    let mut board = Board::new();
+
+    for protocol_move in moves {
+        let (position_1, position_2) = protocol_move.positions;
+        board.try_do_turn(position_1, position_2, board.get_current_token())?;
+
+        match (board.get_pending_collapse(), protocol_move.collapse) {
+            (Some(_), Some(position)) => board.collapse(position)?,
+            (None, Some(_)) => return Err(MoveError::NoCollapsePending.into()),
+            _ => {}
+        }
+    }
+
+    Ok(board)
+}
+
+/// Plays a bot over the engine protocol.
+pub struct Engine<B>
+where
+    B: Bot,
+{
+    bot: B,
+    name: String,
+    board: Board,
+}
+
+impl<B> Engine<B>
+where
+    B: Bot,
+{
+    pub fn new(bot: B, name: &str) -> Self {
+        Self {
+            bot,
+            name: name.to_string(),
+            board: Board::new(),
+        }
+    }
+
+    /// The responses to the command, none for commands that only change the engine's state.
+    pub fn handle(&mut self, command: &Command) -> Vec<Response> {
+        let result = match command {
+            Command::Qttt => {
+                return vec![
+                    Response::Id {
+                        name: self.name.clone(),
+                    },
+                    Response::QtttOk,
+                ]
+            }
+            Command::IsReady => return vec![Response::ReadyOk],
+            Command::NewGame(rules) => {
+                self.board = Board::new();
+
+                // The engine isn't told which side it plays, so it may play both.
+                for token in [Token::X, Token::O] {
+                    self.bot.new_game(*rules, token);
+                }
+                return vec![];
+            }
+            Command::Position(moves) => replay(moves).map(|board| {
+                self.board = board;
+                None
+            }),
+            Command::Marks(board) => {
+                self.board = (**board).clone();
+                Ok(None)
+            }
+            Command::Go { .. } if self.board.get_pending_collapse().is_some() => {
+                Err(MoveError::CollapsePending.into())
+            }
+            Command::Go { .. } if self.board.is_game_over() => Err(MoveError::GameOver.into()),
+            Command::Go { movetime } => {
+                let token = self.board.get_current_token();
+                let positions = match movetime {
+                    Some(movetime) => self.bot.get_timed_move(&self.board, token, *movetime),
+                    None => self.bot.get_next_move(&self.board, token),
+                };
+
+                Ok(Some(Response::BestMove(positions)))
+            }
+            Command::Collapse if self.board.get_pending_collapse().is_none() => {
+                Err(MoveError::NoCollapsePending.into())
+            }
+            Command::Collapse => {
+                let token = self.board.get_current_token();
+                let position = self.bot.choose_collapse(&self.board, token);
+
+                Ok(Some(Response::BestCollapse(position)))
+            }
+            Command::Quit => return vec![],
+        };
+
+        match result {
+            Ok(response) => response.into_iter().collect(),
+            Err(error) => vec![Response::Error(error.to_string())],
+        }
+    }
+
+    /// Answers commands line by line until `quit` or the end of the input.
+    pub fn run(&mut self, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        for line in input.lines() {
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let responses = match Command::parse(&line) {
+                Ok(Command::Quit) => return Ok(()),
+                Ok(command) => self.handle(&command),
+                Err(error) => vec![Response::Error(error.to_string())],
+            };
+
+            for response in responses {
+                writeln!(output, "{response}")?;
+            }
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test_protocol {
+    use super::*;
+
+    use rstest::rstest;
+
+    use crate::bot::RandomBot;
+
+    #[rstest]
+    #[case("qttt")]
+    #[case("isready")]
+    #[case("newgame player_collapse")]
+    #[case("position startpos")]
+    #[case("position startpos moves a1b2 b2a1:a1 c3c3")]
+    #[case("position marks")]
+    #[case("position marks X1b2 O2a1c3 X3a1c3")]
+    #[case("go")]
+    #[case("go movetime 500")]
+    #[case("collapse")]
+    #[case("quit")]
+    fn test_commands_round_trip(#[case] line: &str) {
+        assert_eq!(line, Command::parse(line).unwrap().to_string());
+    }
+
+    #[rstest]
+    #[case("id name tiqtaqtoe random")]
+    #[case("qtttok")]
+    #[case("readyok")]
+    #[case("bestmove a1c3")]
+    #[case("bestcollapse b2")]
+    #[case("error Invalid move, the game is over!")]
+    fn test_responses_round_trip(#[case] line: &str) {
+        assert_eq!(line, Response::parse(line).unwrap().to_string());
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        assert_eq!(
+            Err(ProtocolError::InvalidSquare("d1".to_string())),
+            ProtocolMove::parse("a1d1")
+        );
+        assert!(Command::parse("go movetime soon").is_err());
+        assert!(Command::parse("newgame chess").is_err());
+        assert!(Response::parse("bestmove a1b2:b2").is_err());
+        assert!(Command::parse("position marks O1a1").is_err());
+        assert!(Command::parse("position marks X1a1 O2a1b2").is_err());
+        assert!(Command::parse("position marks X1a1b2 O2b2b2").is_err());
+        // A cycle left open before the last mark.
+        assert!(Command::parse("position marks X1a1b2 O2a1b2 X3a1b2 O4c1c2").is_err());
+    }
+
+    #[test]
+    fn test_replay() {
+        let moves = ["a1b2", "a1b2:b2", "c3c3"].map(|text| ProtocolMove::parse(text).unwrap());
+        let board = replay(&moves).unwrap();
+        assert_eq!(None, board.get_pending_collapse());
+        assert_eq!(Token::O, board.get_current_token());
+
+        assert_eq!(
+            Some(ProtocolError::IllegalMove(MoveError::NoCollapsePending)),
+            replay(&[ProtocolMove::parse("a1b2:a1").unwrap()]).err()
+        );
+    }
+
+    #[test]
+    fn test_marks() {
+        let moves = ["a1b2", "a1c3", "b2c3"].map(|text| ProtocolMove::parse(text).unwrap());
+        let mut board = replay(&moves).unwrap();
+
+        let collapse = board.get_pending_collapse().unwrap().0;
+        board.collapse(collapse).unwrap();
+        board.do_turn(Position::new(0, 1), Position::new(0, 1), Token::O);
+
+        let marks = format_marks(&board);
+        assert_eq!(4, marks.len());
+        assert_eq!(
+            board,
+            parse_marks(&marks.iter().map(String::as_str).collect::<Vec<_>>()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_engine_session() {
+        let input = "qttt\n\
+                     newgame player_collapse\n\
+                     position startpos moves a1b2 a1b2\n\
+                     go\n\
+                     collapse\n\
+                     position startpos moves a1b2 a1b2:b2\n\
+                     go movetime 10\n\
+                     position startpos moves a1a2\n\
+                     quit\n\
+                     isready\n";
+
+        let mut output = vec![];
+        Engine::new(RandomBot::new(0), "random")
+            .run(input.as_bytes(), &mut output)
+            .unwrap();
+
+        let lines = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| Response::parse(line).unwrap())
+            .collect::<Vec<_>>();
+
+        assert_eq!(5, lines.len());
+        assert_eq!(
+            vec![
+                Response::Id {
+                    name: "random".to_string()
+                },
+                Response::QtttOk,
+                Response::Error(MoveError::CollapsePending.to_string()),
+            ],
+            lines[..3]
+        );
+        assert!(matches!(
+            lines[3],
+            Response::BestCollapse(position) if position == Position::new(0, 0)
+                || position == Position::new(1, 1)
+        ));
+        assert!(matches!(lines[4], Response::BestMove(_)));
+    }
+}
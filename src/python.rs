@@ -0,0 +1,406 @@
+use pyo3::create_exception;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+use pyo3::types::PyDict;
+use rand::prelude::{SeedableRng, StdRng};
+
+use crate::board::{Board, MoveError, Position, SpookyMark, Token};
+use crate::bot::{create_bot, Bot, BotPair};
+use crate::game::{Game, GameRecord};
+use crate::graphviz;
+use crate::simulation::get_game_seed;
+
+create_exception!(tiqtaqtoe, IllegalMoveError, PyValueError);
+
+/// Positions are `(row, column)` tuples on the Python side.
+type PyPosition = (usize, usize);
+
+type PySpookyMark = (PyPosition, PyPosition, String);
+
+fn to_position((row, column): PyPosition) -> Position {
+    Position::new(row, column)
+}
+
+fn from_position(position: Position) -> PyPosition {
+    (position.row(), position.column())
+}
+
+fn from_spooky_mark(SpookyMark(position_1, position_2, turn_token): SpookyMark) -> PySpookyMark {
+    (
+        from_position(position_1),
+        from_position(position_2),
+        turn_token.get_label(),
+    )
+}
+
+fn to_token(token: &str) -> PyResult<Token> {
+    match token {
+        "X" => Ok(Token::X),
+        "O" => Ok(Token::O),
+        _ => Err(PyValueError::new_err(format!("Unknown token {token}"))),
+    }
+}
+
+fn to_py_error(move_error: MoveError) -> PyErr {
+    IllegalMoveError::new_err(move_error.to_string())
+}
+
+fn to_py_record<'py>(py: Python<'py>, record: &GameRecord) -> PyResult<Bound<'py, PyDict>> {
+    let turns = record
+        .turns
+        .iter()
+        .map(|turn| {
+            let dict = PyDict::new(py);
+            dict.set_item("token", turn.token.to_string())?;
+            dict.set_item(
+                "positions",
+                (
+                    from_position(turn.positions.0),
+                    from_position(turn.positions.1),
+                ),
+            )?;
+            dict.set_item(
+                "collapse",
+                turn.collapse.map(|(spooky_mark, position)| {
+                    (from_spooky_mark(spooky_mark), from_position(position))
+                }),
+            )?;
+            Ok(dict)
+        })
+        .collect::<PyResult<Vec<_>>>()?;
+
+    let dict = PyDict::new(py);
+    dict.set_item("turns", turns)?;
+    dict.set_item("score", record.score)?;
+
+    Ok(dict)
+}
+
+/// The board, with its own RNG for random collapses.
+#[pyclass(name = "Board", skip_from_py_object)]
+#[derive(Clone)]
+pub struct PyBoard {
+    board: Board,
+    rng: StdRng,
+}
+
+#[pymethods]
+impl PyBoard {
+    #[new]
+    #[pyo3(signature = (seed=None))]
+    fn new(seed: Option<u64>) -> Self {
+        Self {
+            board: Board::new(),
+            rng: seed.map_or_else(StdRng::from_os_rng, StdRng::seed_from_u64),
+        }
+    }
+
+    #[getter]
+    fn turn(&self) -> u8 {
+        self.board.turn
+    }
+
+    #[getter]
+    fn current_token(&self) -> String {
+        self.board.get_current_token().to_string()
+    }
+
+    /// The classical mark in the position, like `"X3"`, if there is one.
+    fn get_mark(&self, position: PyPosition) -> PyResult<Option<String>> {
+        if !to_position(position).is_valid() {
+            return Err(to_py_error(MoveError::InvalidPosition(to_position(
+                position,
+            ))));
+        }
+
+        Ok(self
+            .board
+            .get_mark(to_position(position))
+            .map(|turn_token| turn_token.get_label()))
+    }
+
+    #[getter]
+    fn spooky_marks(&self) -> Vec<PySpookyMark> {
+        self.board
+            .spooky_marks
+            .iter()
+            .map(|spooky_mark| from_spooky_mark(*spooky_mark))
+            .collect()
+    }
+
+    #[getter]
+    fn pending_collapse(&self) -> Option<PySpookyMark> {
+        self.board.get_pending_collapse().map(from_spooky_mark)
+    }
+
+    fn do_turn(
+        &mut self,
+        position_1: PyPosition,
+        position_2: PyPosition,
+        token: &str,
+    ) -> PyResult<()> {
+        self.board
+            .try_do_turn(
+                to_position(position_1),
+                to_position(position_2),
+                to_token(token)?,
+            )
+            .map_err(to_py_error)
+    }
+
+    /// Resolves the pending cycle, in the given position or by a coin flip from the board's RNG.
+    ///
+    /// Returns the collapsed spooky mark and where it went, or `None` if there was no cycle.
+    #[pyo3(signature = (position=None))]
+    fn collapse(
+        &mut self,
+        position: Option<PyPosition>,
+    ) -> PyResult<Option<(PySpookyMark, PyPosition)>> {
+        let collapse = match position {
+            Some(position) => {
+                let spooky_mark = self
+                    .board
+                    .get_pending_collapse()
+                    .ok_or_else(|| to_py_error(MoveError::NoCollapsePending))?;
+
+                self.board
+                    .collapse(to_position(position))
+                    .map_err(to_py_error)?;
+
+                Some((spooky_mark, to_position(position)))
+            }
+            None => self.board.collapse_loop(&mut self.rng),
+        };
+
+        Ok(collapse.map(|(spooky_mark, position)| {
+            (from_spooky_mark(spooky_mark), from_position(position))
+        }))
+    }
+
+    fn get_score(&self) -> (f32, f32) {
+        self.board.get_score()
+    }
+
+    fn legal_moves(&self) -> Vec<(PyPosition, PyPosition)> {
+        self.board
+            .get_legal_moves()
+            .into_iter()
+            .map(|(position_1, position_2)| (from_position(position_1), from_position(position_2)))
+            .collect()
+    }
+
+    fn is_game_over(&self) -> bool {
+        self.board.is_game_over()
+    }
+
+    fn copy(&self) -> Self {
+        self.clone()
+    }
+
+    fn __copy__(&self) -> Self {
+        self.clone()
+    }
+
+    fn to_json(&self) -> String {
+        serde_json::to_string(&self.board).unwrap()
+    }
+
+    #[staticmethod]
+    #[pyo3(signature = (json, seed=None))]
+    fn from_json(json: &str, seed: Option<u64>) -> PyResult<Self> {
+        Ok(Self {
+            board: serde_json::from_str(json).map_err(|e| PyValueError::new_err(e.to_string()))?,
+            ..Self::new(seed)
+        })
+    }
+
+    fn to_dot(&self) -> String {
+        graphviz::to_dot(&self.board)
+    }
+
+    fn __str__(&self) -> String {
+        self.board.to_string()
+    }
+}
+
+/// One of the crate's bots, picked by name.
+#[pyclass(name = "Bot")]
+pub struct PyBot {
+    bot: Box<dyn Bot + Send + Sync>,
+}
+
+#[pymethods]
+impl PyBot {
+    #[new]
+    fn new(name: &str, seed: u64) -> PyResult<Self> {
+        let bot = create_bot(name, seed)
+            .ok_or_else(|| PyValueError::new_err(format!("Unknown bot {name}")))?;
+
+        Ok(Self { bot })
+    }
+
+    /// The bot's move for whoever's turn it is on the board. Raises an `IllegalMoveError`, a
+    /// `ValueError`, if a collapse is pending or the game is over.
+    fn get_next_move(&mut self, board: &PyBoard) -> PyResult<(PyPosition, PyPosition)> {
+        board.board.check_can_move().map_err(to_py_error)?;
+
+        let (position_1, position_2) = self
+            .bot
+            .get_next_move(&board.board, board.board.get_current_token());
+
+        Ok((from_position(position_1), from_position(position_2)))
+    }
+}
+
+/// Plays games between two bots, picked by name, and returns their records.
+///
+/// Each record is a dict with the `turns` played, each with its `token`, `positions` and
+/// `collapse`, and the final `score`.
+#[pyfunction]
+#[pyo3(signature = (games, x_bot="random", o_bot="random", seed=0))]
+fn self_play<'py>(
+    py: Python<'py>,
+    games: usize,
+    x_bot: &str,
+    o_bot: &str,
+    seed: u64,
+) -> PyResult<Vec<Bound<'py, PyDict>>> {
+    for name in [x_bot, o_bot] {
+        if create_bot(name, 0).is_none() {
+            return Err(PyValueError::new_err(format!("Unknown bot {name}")));
+        }
+    }
+
+    let records = py.detach(|| {
+        (0..games)
+            .map(|index| {
+                let game_seed = get_game_seed(seed, index);
+                let bots = BotPair {
+                    bot_x: create_bot(x_bot, game_seed).unwrap(),
+                    bot_o: create_bot(o_bot, game_seed.wrapping_add(1)).unwrap(),
+                };
+
+                let mut game = Game::new(bots, game_seed);
+                game.set_verbose(false);
+                game.play_whole_game();
+
+                game.get_record()
+            })
+            .collect::<Vec<_>>()
+    });
+
+    records
+        .iter()
+        .map(|record| to_py_record(py, record))
+        .collect()
+}
+
+#[pymodule]
+fn tiqtaqtoe(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyBoard>()?;
+    module.add_class::<PyBot>()?;
+    module.add_function(wrap_pyfunction!(self_play, module)?)?;
+    module.add(
+        "IllegalMoveError",
+        module.py().get_type::<IllegalMoveError>(),
+    )?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test_python_module {
+    use super::*;
+
+    #[test]
+    fn test_board() {
+        let mut board = PyBoard::new(Some(3));
+        board.do_turn((0, 0), (1, 1), "X").unwrap();
+        board.do_turn((1, 1), (0, 0), "O").unwrap();
+
+        assert!(board.do_turn((2, 2), (2, 1), "X").is_err());
+        assert_eq!(
+            Some(((0, 0), (1, 1), "X1".to_string())),
+            board.pending_collapse()
+        );
+
+        let mut copy = board.copy();
+        copy.collapse(Some((1, 1))).unwrap();
+        assert_eq!(Some("X1".to_string()), copy.get_mark((1, 1)).unwrap());
+        assert!(board.pending_collapse().is_some());
+
+        assert!(board.collapse(None).unwrap().is_some());
+        assert_eq!(Vec::<PySpookyMark>::new(), board.spooky_marks());
+        assert_eq!(49, board.legal_moves().len());
+
+        let loaded = PyBoard::from_json(&board.to_json(), None).unwrap();
+        assert_eq!(board.board, loaded.board);
+    }
+
+    #[test]
+    fn test_from_json_rejects_unreachable_boards() {
+        Python::initialize();
+
+        let board = PyBoard::new(None).to_json();
+        let error = PyBoard::from_json(&board.replace(r#""turn":1"#, r#""turn":0"#), None)
+            .err()
+            .unwrap();
+
+        Python::attach(|py| assert!(error.is_instance_of::<PyValueError>(py)));
+    }
+
+    #[test]
+    fn test_bot_needs_legal_moves() {
+        Python::initialize();
+
+        let mut board = PyBoard::new(Some(3));
+        let mut bot = PyBot::new("greedy", 0).unwrap();
+        board.do_turn((0, 0), (1, 1), "X").unwrap();
+        board.do_turn((1, 1), (0, 0), "O").unwrap();
+        assert!(bot.get_next_move(&board).is_err());
+
+        board.collapse(None).unwrap();
+        while !board.board.is_game_over() {
+            let (position_1, position_2) = bot.get_next_move(&board).unwrap();
+            let token = board.board.get_current_token().to_string();
+            board.do_turn(position_1, position_2, &token).unwrap();
+            if board.pending_collapse().is_some() {
+                board.collapse(None).unwrap();
+            }
+        }
+        assert!(bot.get_next_move(&board).is_err());
+    }
+
+    #[test]
+    fn test_self_play() {
+        Python::initialize();
+
+        Python::attach(|py| {
+            let records = self_play(py, 3, "random", "random", 5).unwrap();
+            assert_eq!(3, records.len());
+
+            let first_turn = records[0]
+                .get_item("turns")
+                .unwrap()
+                .unwrap()
+                .get_item(0)
+                .unwrap();
+            assert_eq!(
+                "X",
+                first_turn
+                    .get_item("token")
+                    .unwrap()
+                    .extract::<String>()
+                    .unwrap()
+            );
+
+            assert!(self_play(py, 1, "random", "unknown", 5).is_err());
+            assert_eq!(
+                2,
+                self_play(py, 2, "random", "random", u64::MAX)
+                    .unwrap()
+                    .len()
+            );
+        });
+    }
+}
@@ -0,0 +1,319 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::prelude::{IndexedRandom, Rng, SeedableRng, StdRng};
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Board, Position, Token};
+use crate::bot::Bot;
+use crate::game::Rules;
+use crate::solver;
+
+const VERSION: u32 = 1;
+
+/// Settings of Monte Carlo control by self-play.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TrainingConfig {
+    pub episodes: usize,
+    /// The chance of playing a random move instead of the best known one.
+    pub exploration: f32,
+    /// How far a value moves towards the outcome of every game that reaches its position.
+    pub learning_rate: f32,
+    pub seed: u64,
+}
+
+impl Default for TrainingConfig {
+    fn default() -> Self {
+        Self {
+            episodes: 100_000,
+            exploration: 0.1,
+            learning_rate: 0.05,
+            seed: 0,
+        }
+    }
+}
+
+/// Learned values of positions, as X's score minus O's score, keyed by
+/// `Board::get_canonical_hash`.
+///
+/// Only positions without a pending collapse are stored, since the value of a position with
+/// a pending collapse follows from its two outcomes under the rules. Tables are saved as
+/// JSON, like `{"version": 1, "rules": "random_collapse", "values": {"<hash>": 0.25, ...}}`.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ValueTable {
+    version: u32,
+    pub rules: Rules,
+    values: BTreeMap<u64, f32>,
+}
+
+fn get_sign(token: Token) -> f32 {
+    match token {
+        Token::X => 1.0,
+        Token::O => -1.0,
+    }
+}
+
+impl ValueTable {
+    pub fn new(rules: Rules) -> Self {
+        Self {
+            version: VERSION,
+            rules,
+            values: BTreeMap::new(),
+        }
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let table: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+
+        if table.version != VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unsupported value table version {}", table.version),
+            ));
+        }
+
+        Ok(table)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, serde_json::to_string(self)?)
+    }
+
+    /// The number of positions with a learned value.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// The exact value of a finished game, the learned value of a position seen in training,
+    /// or 0 for an unknown position.
+    pub fn get_value(&self, board: &Board) -> f32 {
+        if let Some(spooky_mark) = board.get_pending_collapse() {
+            let [value_1, value_2] = [spooky_mark.0, spooky_mark.1].map(|position| {
+                let mut child = board.clone();
+                child.collapse(position).unwrap();
+
+                self.get_value(&child)
+            });
+
+            return match self.rules {
+                Rules::RandomCollapse => (value_1 + value_2) / 2.0,
+                Rules::PlayerCollapse => {
+                    let sign = get_sign(board.get_current_token());
+                    sign * f32::max(sign * value_1, sign * value_2)
+                }
+            };
+        }
+
+        if board.is_game_over() {
+            return solver::get_value(board);
+        }
+
+        self.values
+            .get(&board.get_canonical_hash())
+            .copied()
+            .unwrap_or(0.0)
+    }
+
+    /// The legal moves of the board with the best learned value for the player to move.
+    pub fn get_best_moves(&self, board: &Board) -> Vec<(Position, Position)> {
+        let token = board.get_current_token();
+
+        let values = board
+            .get_legal_moves()
+            .into_iter()
+            .map(|legal_move| {
+                let mut child = board.clone();
+                child.do_turn(legal_move.0, legal_move.1, token);
+
+                (legal_move, get_sign(token) * self.get_value(&child))
+            })
+            .collect::<Vec<_>>();
+
+        let best_value = values
+            .iter()
+            .map(|(_, value)| *value)
+            .fold(f32::NEG_INFINITY, f32::max);
+
+        values
+            .into_iter()
+            .filter(|(_, value)| *value == best_value)
+            .map(|(best_move, _)| best_move)
+            .collect()
+    }
+
+    // The outcome of the pending collapse the player who chooses it likes best.
+    fn get_best_collapse(&self, board: &Board) -> Position {
+        let spooky_mark = board.get_pending_collapse().unwrap();
+        let sign = get_sign(board.get_current_token());
+
+        let value_of = |position| {
+            let mut child = board.clone();
+            child.collapse(position).unwrap();
+
+            sign * self.get_value(&child)
+        };
+
+        if value_of(spooky_mark.0) >= value_of(spooky_mark.1) {
+            spooky_mark.0
+        } else {
+            spooky_mark.1
+        }
+    }
+
+    /// Learns values by Monte Carlo control: both sides play the best known moves, with some
+    /// random exploration, and every position reached moves towards the outcome of the game.
+    pub fn train(&mut self, config: &TrainingConfig) {
+        let mut rng = StdRng::seed_from_u64(config.seed);
+
+        for _ in 0..config.episodes {
+            let mut board = Board::new();
+            let mut visited = vec![];
+
+            while !board.is_game_over() {
+                let (position_1, position_2) = if rng.random::<f32>() < config.exploration {
+                    *board.get_legal_moves().choose(&mut rng).unwrap()
+                } else {
+                    *self.get_best_moves(&board).choose(&mut rng).unwrap()
+                };
+
+                board.do_turn(position_1, position_2, board.get_current_token());
+
+                if let Some(spooky_mark) = board.get_pending_collapse() {
+                    let position = match self.rules {
+                        Rules::RandomCollapse => {
+                            if rng.random() {
+                                spooky_mark.0
+                            } else {
+                                spooky_mark.1
+                            }
+                        }
+                        Rules::PlayerCollapse => self.get_best_collapse(&board),
+                    };
+
+                    board.collapse(position).unwrap();
+                }
+
+                if !board.is_game_over() {
+                    visited.push(board.get_canonical_hash());
+                }
+            }
+
+            let outcome = solver::get_value(&board);
+
+            for hash in visited {
+                let value = self.values.entry(hash).or_insert(0.0);
+                *value += config.learning_rate * (outcome - *value);
+            }
+        }
+    }
+}
+
+/// Plays the moves with the best learned value, breaking ties at random.
+///
+/// The values only hold under the rules the table was trained for, so in a game with other
+/// rules the bot reports a failure through `Bot::get_failure` and forfeits.
+#[derive(Clone)]
+pub struct TabularBot {
+    table: ValueTable,
+    failure: Option<String>,
+    rng: StdRng,
+}
+
+impl TabularBot {
+    pub fn new(table: ValueTable, seed: u64) -> Self {
+        Self {
+            table,
+            failure: None,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Bot for TabularBot {
+    fn get_next_move(&mut self, board: &Board, _token: Token) -> (Position, Position) {
+        *self
+            .table
+            .get_best_moves(board)
+            .choose(&mut self.rng)
+            .unwrap()
+    }
+
+    fn get_failure(&mut self, _token: Token) -> Option<String> {
+        self.failure.clone()
+    }
+
+    fn choose_collapse(&mut self, board: &Board, _token: Token) -> Position {
+        self.table.get_best_collapse(board)
+    }
+
+    fn new_game(&mut self, rules: Rules, _token: Token) {
+        self.failure = (rules != self.table.rules).then(|| {
+            format!(
+                "table trained for {:?}, but the game is played under {rules:?}",
+                self.table.rules
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod test_value_table {
+    use super::*;
+
+    use crate::test_support::assert_beats_random_bot;
+
+    fn train_table(rules: Rules) -> ValueTable {
+        let mut table = ValueTable::new(rules);
+        table.train(&TrainingConfig {
+            episodes: 300,
+            ..TrainingConfig::default()
+        });
+
+        table
+    }
+
+    #[test]
+    fn test_tabular_bot_beats_random_bot() {
+        let table = train_table(Rules::RandomCollapse);
+        assert!(!table.is_empty());
+
+        assert_beats_random_bot(20, Token::X, |seed| TabularBot::new(table.clone(), seed));
+    }
+
+    #[test]
+    fn test_tabular_bot_checks_rules() {
+        let mut bot = TabularBot::new(ValueTable::new(Rules::RandomCollapse), 0);
+
+        bot.new_game(Rules::PlayerCollapse, Token::X);
+        assert!(bot.get_failure(Token::X).is_some());
+
+        bot.new_game(Rules::RandomCollapse, Token::X);
+        assert_eq!(None, bot.get_failure(Token::X));
+    }
+
+    #[test]
+    fn test_rules_and_save_and_load() {
+        let table = train_table(Rules::PlayerCollapse);
+
+        // O closes a cycle, so X chooses how it collapses and does at least as well as a coin flip.
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::X);
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::O);
+        let player_value = table.get_value(&board);
+
+        let mut random_table = table.clone();
+        random_table.rules = Rules::RandomCollapse;
+        assert!(player_value >= random_table.get_value(&board));
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("value_table.json");
+        table.save(&path).unwrap();
+        assert_eq!(table, ValueTable::load(&path).unwrap());
+    }
+}
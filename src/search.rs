@@ -0,0 +1,485 @@
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::board::{Board, Position, Token};
+use crate::bot::Bot;
+use crate::evaluation::Evaluator;
+use crate::game::Rules;
+use crate::solver::{self, get_distinct_moves};
+
+// How many positions are searched between looks at the clock.
+const NODES_PER_CLOCK_CHECK: u64 = 64;
+
+struct Timeout;
+
+/// A depth-limited alpha-beta search over the evaluator, deepened one move at a time until the
+/// time budget runs out, playing the best move of the deepest finished search.
+///
+/// A move that closes a cycle is followed by the collapse the rules of the game, given by
+/// `Bot::new_game`, call for: a coin flip, or the choice of the opponent under
+/// `Rules::PlayerCollapse`.
+///
+/// With more than one thread, the moves of the board are shared between the threads at every
+/// depth, which finds the same moves as a single thread, only faster.
+///
+/// Doesn't ponder: without a transposition table, nothing found on the opponent's time would
+/// carry over to the next search.
+#[derive(Clone)]
+pub struct SearchBot<E>
+where
+    E: Evaluator,
+{
+    evaluator: E,
+    rules: Rules,
+    default_budget: Duration,
+    threads: usize,
+    deadline: Option<Instant>,
+    nodes: u64,
+    completed_depth: usize,
+}
+
+impl<E> SearchBot<E>
+where
+    E: Evaluator,
+{
+    /// The default budget is used for moves asked for without one, through `get_next_move`.
+    pub fn new(evaluator: E, default_budget: Duration) -> Self {
+        Self {
+            evaluator,
+            rules: Rules::default(),
+            default_budget,
+            threads: 1,
+            deadline: None,
+            nodes: 0,
+            completed_depth: 0,
+        }
+    }
+
+    /// The depth of the deepest search finished for the last move.
+    pub fn get_completed_depth(&self) -> usize {
+        self.completed_depth
+    }
+
+    /// The number of positions searched for the last move.
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    fn check_clock(&mut self) -> Result<(), Timeout> {
+        self.nodes += 1;
+
+        match self.deadline {
+            Some(deadline)
+                if self.nodes.is_multiple_of(NODES_PER_CLOCK_CHECK)
+                    && Instant::now() >= deadline =>
+            {
+                Err(Timeout)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    // The value of the board for X, looking `depth` moves ahead.
+    fn search(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        alpha: f32,
+        beta: f32,
+    ) -> Result<f32, Timeout> {
+        self.check_clock()?;
+
+        if let Some(spooky_mark) = board.get_pending_collapse() {
+            let mut values = [0.0; 2];
+
+            for (value, position) in values.iter_mut().zip([spooky_mark.0, spooky_mark.1]) {
+                let mut child = board.clone();
+                child.collapse(position).unwrap();
+                *value = self.search(&child, depth, -1.0, 1.0)?;
+            }
+
+            // Under the player rules, the collapse is chosen by the player to move next.
+            return Ok(match (self.rules, board.get_current_token()) {
+                (Rules::RandomCollapse, _) => (values[0] + values[1]) / 2.0,
+                (Rules::PlayerCollapse, Token::X) => values[0].max(values[1]),
+                (Rules::PlayerCollapse, Token::O) => values[0].min(values[1]),
+            });
+        }
+
+        if board.is_game_over() {
+            return Ok(solver::get_value(board));
+        }
+
+        if depth == 0 {
+            return Ok(self.evaluator.evaluate(board));
+        }
+
+        let token = board.get_current_token();
+        let (mut alpha, mut beta) = (alpha, beta);
+        let mut best_value = match token {
+            Token::X => f32::NEG_INFINITY,
+            Token::O => f32::INFINITY,
+        };
+
+        for (position_1, position_2) in get_distinct_moves(board) {
+            let mut child = board.clone();
+            child.do_turn(position_1, position_2, token);
+
+            let value = self.search(&child, depth - 1, alpha, beta)?;
+
+            match token {
+                Token::X => {
+                    best_value = best_value.max(value);
+                    alpha = alpha.max(value);
+                }
+                Token::O => {
+                    best_value = best_value.min(value);
+                    beta = beta.min(value);
+                }
+            }
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        Ok(best_value)
+    }
+
+    // The values of the moves for X, each looking `depth` moves ahead. Each move is searched
+    // with the best value of the moves before it as a bound, so a move that can't beat them
+    // only gets a value no better than theirs.
+    fn search_moves(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        moves: &[(Position, Position)],
+    ) -> Result<Vec<f32>, Timeout> {
+        let token = board.get_current_token();
+        let (mut alpha, mut beta) = (-1.0, 1.0);
+        let mut values = Vec::with_capacity(moves.len());
+
+        for (position_1, position_2) in moves {
+            let mut child = board.clone();
+            child.do_turn(*position_1, *position_2, token);
+
+            let value = self.search(&child, depth - 1, alpha, beta)?;
+
+            match token {
+                Token::X => alpha = alpha.max(value),
+                Token::O => beta = beta.min(value),
+            }
+
+            values.push(value);
+        }
+
+        Ok(values)
+    }
+}
+
+impl<E> SearchBot<E>
+where
+    E: Evaluator + Clone + Send,
+{
+    /// Spreads the search over the threads.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    // Every thread searches every `threads`th move, with its own copy of the evaluator.
+    fn search_moves_in_parallel(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        moves: &[(Position, Position)],
+    ) -> Result<Vec<f32>, Timeout> {
+        let threads = self.threads;
+
+        let results = thread::scope(|scope| {
+            let handles = (0..threads)
+                .map(|thread_index| {
+                    let mut worker = self.clone();
+                    worker.nodes = 0;
+                    let thread_moves = moves
+                        .iter()
+                        .copied()
+                        .skip(thread_index)
+                        .step_by(threads)
+                        .collect::<Vec<_>>();
+
+                    scope.spawn(move || {
+                        let values = worker.search_moves(board, depth, &thread_moves);
+                        (values, worker.nodes)
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        let mut values = vec![0.0; moves.len()];
+
+        for (thread_index, (thread_values, nodes)) in results.into_iter().enumerate() {
+            self.nodes += nodes;
+
+            for (index, value) in thread_values?.into_iter().enumerate() {
+                values[thread_index + index * threads] = value;
+            }
+        }
+
+        Ok(values)
+    }
+
+    // The best move at the given depth, searching the previous best move first so that it
+    // bounds the search of the others.
+    fn search_root(
+        &mut self,
+        board: &Board,
+        depth: usize,
+        previous_best: Option<(Position, Position)>,
+    ) -> Result<(Position, Position), Timeout> {
+        let sign = match board.get_current_token() {
+            Token::X => 1.0,
+            Token::O => -1.0,
+        };
+
+        let mut moves = get_distinct_moves(board);
+        if let Some(index) = moves.iter().position(|m| Some(*m) == previous_best) {
+            moves[..=index].rotate_right(1);
+        }
+
+        let values = if self.threads > 1 {
+            self.search_moves_in_parallel(board, depth, &moves)?
+        } else {
+            self.search_moves(board, depth, &moves)?
+        };
+
+        let mut best = (moves[0], f32::NEG_INFINITY);
+
+        for (positions, value) in moves.into_iter().zip(values) {
+            if sign * value > best.1 {
+                best = (positions, sign * value);
+            }
+        }
+
+        Ok(best.0)
+    }
+
+    fn search_with_budget(&mut self, board: &Board, budget: Duration) -> (Position, Position) {
+        let start = Instant::now();
+        self.deadline = Some(start + budget);
+        self.nodes = 0;
+        self.completed_depth = 0;
+
+        // A game never lasts more than 9 moves, so searching past its end changes nothing.
+        let max_depth = 10 - board.turn as usize;
+        let mut best_move = None;
+
+        for depth in 1..=max_depth {
+            match self.search_root(board, depth, best_move) {
+                Ok(depth_best) => {
+                    best_move = Some(depth_best);
+                    self.completed_depth = depth;
+                }
+                Err(Timeout) => break,
+            }
+
+            // The next search takes longer than all the previous ones together.
+            if start.elapsed() * 2 > budget {
+                break;
+            }
+        }
+
+        best_move.unwrap_or_else(|| get_distinct_moves(board)[0])
+    }
+
+    /// The best move of a search looking `depth` moves ahead, or to the end of the game if
+    /// that's sooner, however long it takes. Doesn't look at the clock, so it runs where there
+    /// is none, as in WebAssembly.
+    pub fn search_to_depth(&mut self, board: &Board, depth: usize) -> (Position, Position) {
+        self.deadline = None;
+        self.nodes = 0;
+
+        let depth = depth.clamp(1, 10 - board.turn as usize);
+        let Ok(best_move) = self.search_root(board, depth, None) else {
+            unreachable!("A search without a deadline never times out");
+        };
+        self.completed_depth = depth;
+
+        best_move
+    }
+}
+
+impl<E> Bot for SearchBot<E>
+where
+    E: Evaluator + Clone + Send,
+{
+    fn get_next_move(&mut self, board: &Board, token: Token) -> (Position, Position) {
+        self.get_timed_move(board, token, self.default_budget)
+    }
+
+    fn get_timed_move(
+        &mut self,
+        board: &Board,
+        _token: Token,
+        budget: Duration,
+    ) -> (Position, Position) {
+        self.search_with_budget(board, budget)
+    }
+
+    fn new_game(&mut self, rules: Rules, _token: Token) {
+        self.rules = rules;
+    }
+}
+
+#[cfg(test)]
+mod test_search_bot {
+    use super::*;
+
+    use crate::bot::{BotPair, RandomBot};
+    use crate::evaluation::HeuristicEvaluator;
+    use crate::game::{Game, TimeControl};
+
+    #[test]
+    fn test_search_bot_takes_a_win() {
+        let mut board = Board::new();
+        for (position, token) in [
+            ((0, 0), Token::X),
+            ((1, 0), Token::O),
+            ((0, 1), Token::X),
+            ((1, 1), Token::O),
+        ] {
+            board.do_turn(
+                Position::new(position.0, position.1),
+                Position::new(position.0, position.1),
+                token,
+            );
+        }
+
+        let mut bot = SearchBot::new(HeuristicEvaluator::default(), Duration::from_secs(1));
+        assert_eq!(
+            (Position::new(0, 2), Position::new(0, 2)),
+            bot.get_next_move(&board, Token::X)
+        );
+
+        // With this few moves left, the search reaches the end of the game well in time.
+        assert_eq!(5, bot.get_completed_depth());
+    }
+
+    #[test]
+    fn test_search_bot_values_collapses_by_rules() {
+        // X closes the cycle of a1, b1 and c1, which O collapses.
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(0, 1), Token::X);
+        board.do_turn(Position::new(0, 1), Position::new(0, 2), Token::O);
+        board.do_turn(Position::new(0, 2), Position::new(0, 0), Token::X);
+        let spooky_mark = board.get_pending_collapse().unwrap();
+
+        let evaluator = HeuristicEvaluator::default();
+        let values = [spooky_mark.0, spooky_mark.1].map(|position| {
+            let mut collapsed = board.clone();
+            collapsed.collapse(position).unwrap();
+            evaluator.evaluate(&collapsed)
+        });
+        assert_ne!(values[0], values[1]);
+
+        let mut bot = SearchBot::new(evaluator, Duration::MAX);
+        let value = |bot: &mut SearchBot<_>| bot.search(&board, 0, -1.0, 1.0).ok().unwrap();
+        assert_eq!((values[0] + values[1]) / 2.0, value(&mut bot));
+
+        bot.new_game(Rules::PlayerCollapse, Token::X);
+        assert_eq!(values[0].min(values[1]), value(&mut bot));
+    }
+
+    #[test]
+    fn test_parallel_search() {
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::X);
+        board.do_turn(Position::new(1, 1), Position::new(2, 2), Token::O);
+
+        let mut bot = SearchBot::new(HeuristicEvaluator::default(), Duration::from_secs(60));
+        let mut parallel_bot = bot.clone();
+        parallel_bot.set_threads(4);
+
+        for depth in 1..=3 {
+            assert_eq!(
+                bot.search_root(&board, depth, None).ok(),
+                parallel_bot.search_root(&board, depth, None).ok()
+            );
+        }
+    }
+
+    #[test]
+    fn test_root_moves_are_bounded_by_the_best_so_far() {
+        let mut board = Board::new();
+        board.do_turn(Position::new(0, 0), Position::new(1, 1), Token::X);
+        board.do_turn(Position::new(1, 1), Position::new(2, 2), Token::O);
+        let moves = get_distinct_moves(&board);
+
+        let mut bot = SearchBot::new(HeuristicEvaluator::default(), Duration::MAX);
+        let exact_values = moves
+            .iter()
+            .map(|(position_1, position_2)| {
+                let mut child = board.clone();
+                child.do_turn(*position_1, *position_2, Token::X);
+                bot.search(&child, 2, -1.0, 1.0).ok().unwrap()
+            })
+            .collect::<Vec<_>>();
+        let exact_nodes = bot.nodes;
+
+        bot.nodes = 0;
+        let values = bot.search_moves(&board, 3, &moves).ok().unwrap();
+        assert!(bot.nodes < exact_nodes);
+
+        // The best move keeps its exact value, and no other move gets a better one.
+        let best = exact_values
+            .iter()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max);
+        let best_index = exact_values.iter().position(|value| *value == best);
+        assert_eq!(best_index, values.iter().position(|value| *value == best));
+        assert!(values.iter().all(|value| *value <= best));
+    }
+
+    #[test]
+    fn test_search_bot_keeps_to_its_budget() {
+        let mut bot = SearchBot::new(HeuristicEvaluator::default(), Duration::from_secs(1));
+
+        let start = Instant::now();
+        let (position_1, position_2) =
+            bot.get_timed_move(&Board::new(), Token::X, Duration::from_millis(50));
+
+        assert!(start.elapsed() < Duration::from_millis(500));
+        assert!(bot.get_completed_depth() >= 1);
+        assert!(Board::new()
+            .try_do_turn(position_1, position_2, Token::X)
+            .is_ok());
+    }
+
+    #[test]
+    fn test_game_clocks() {
+        let bots = BotPair {
+            bot_x: SearchBot::new(HeuristicEvaluator::default(), Duration::from_secs(1)),
+            bot_o: RandomBot::new(0),
+        };
+        let mut game = Game::new(bots, 0);
+        game.set_verbose(false);
+        // Seconds rather than milliseconds, so the few milliseconds a search can overrun its
+        // budget by never cost the game.
+        game.set_time_control(TimeControl::Clock {
+            initial: Duration::from_secs(1),
+            increment: Duration::from_millis(100),
+        });
+
+        game.play_turn();
+        assert!(game.get_clock(Token::X).unwrap() < Duration::from_millis(1100));
+        assert_eq!(Some(Duration::from_secs(1)), game.get_clock(Token::O));
+
+        game.play_whole_game();
+        assert_eq!(None, game.get_time_forfeit());
+        assert_eq!(game.get_board().get_score(), game.get_record().score);
+    }
+}
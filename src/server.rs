@@ -0,0 +1,605 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use rand::prelude::{IndexedRandom, SeedableRng, StdRng};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::board::{Board, MoveError, Position, SpookyMark, Token};
+use crate::bot::{Bot, RandomBot};
+use crate::difficulty::{DifficultyBot, Level};
+use crate::game::Rules;
+
+const MAX_BODY_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerKind {
+    /// Moves and collapses are posted through the API.
+    #[default]
+    Human,
+    /// The server plays a RandomBot with the given seed.
+    Random { seed: u64 },
+    /// The server plays a DifficultyBot of the level with the given seed.
+    Level { level: Level, seed: u64 },
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Players {
+    #[serde(default)]
+    pub x: PlayerKind,
+    #[serde(default)]
+    pub o: PlayerKind,
+}
+
+#[derive(Default, Deserialize)]
+struct NewGameRequest {
+    #[serde(default)]
+    rules: Rules,
+    #[serde(default)]
+    players: Players,
+    /// Drives the random collapses, and the random replacements of illegal bot moves.
+    #[serde(default)]
+    seed: u64,
+}
+
+#[derive(Deserialize)]
+struct MoveRequest {
+    token: Token,
+    positions: [Position; 2],
+}
+
+#[derive(Deserialize)]
+struct CollapseRequest {
+    position: Position,
+}
+
+#[derive(Serialize)]
+struct GameState<'a> {
+    id: u64,
+    rules: Rules,
+    players: Players,
+    board: &'a Board,
+    to_move: Option<Token>,
+    pending_collapse: Option<SpookyMark>,
+    legal_moves: Vec<(Position, Position)>,
+    score: (f32, f32),
+    game_over: bool,
+}
+
+enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    IllegalMove(MoveError),
+}
+
+impl ApiError {
+    fn into_response(self) -> Response {
+        match self {
+            ApiError::BadRequest(message) => Response::new(
+                400,
+                json!({ "error": { "kind": "bad_request", "message": message } }),
+            ),
+            ApiError::NotFound(message) => Response::new(
+                404,
+                json!({ "error": { "kind": "not_found", "message": message } }),
+            ),
+            ApiError::IllegalMove(move_error) => {
+                let mut error = serde_json::to_value(move_error).unwrap();
+                error["message"] = move_error.to_string().into();
+
+                Response::new(422, json!({ "error": error }))
+            }
+        }
+    }
+}
+
+impl From<MoveError> for ApiError {
+    fn from(move_error: MoveError) -> Self {
+        ApiError::IllegalMove(move_error)
+    }
+}
+
+impl From<serde_json::Error> for ApiError {
+    fn from(error: serde_json::Error) -> Self {
+        ApiError::BadRequest(format!("Invalid request body: {error}"))
+    }
+}
+
+#[derive(Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Response {
+    fn new(status: u16, body: Value) -> Self {
+        Self { status, body }
+    }
+
+    fn reason(&self) -> &'static str {
+        match self.status {
+            200 => "OK",
+            201 => "Created",
+            204 => "No Content",
+            400 => "Bad Request",
+            404 => "Not Found",
+            422 => "Unprocessable Entity",
+            _ => "Internal Server Error",
+        }
+    }
+}
+
+// Plays on a Board rather than a Game, since human moves arrive with requests, instead of being
+// asked of bots in turn.
+struct ServerGame {
+    rules: Rules,
+    players: Players,
+    bots: [Option<Box<dyn Bot + Send>>; 2],
+    board: Board,
+    rng: StdRng,
+}
+
+impl ServerGame {
+    fn new(rules: Rules, players: Players, seed: u64) -> Self {
+        let create_bot = |player_kind: PlayerKind| -> Option<Box<dyn Bot + Send>> {
+            match player_kind {
+                PlayerKind::Human => None,
+                PlayerKind::Random { seed } => Some(Box::new(RandomBot::new(seed))),
+                PlayerKind::Level { level, seed } => {
+                    Some(Box::new(DifficultyBot::new(level, seed)))
+                }
+            }
+        };
+
+        Self {
+            rules,
+            players,
+            bots: [create_bot(players.x), create_bot(players.o)],
+            board: Board::new(),
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    fn get_bot_index(token: Token) -> usize {
+        match token {
+            Token::X => 0,
+            Token::O => 1,
+        }
+    }
+
+    /// Plays bot moves and random collapses until a human has to act or the game is over.
+    ///
+    /// An illegal move or collapse of a bot is replaced by a random one, rather than failing
+    /// the request.
+    fn advance(&mut self) {
+        while !self.board.is_game_over() {
+            let token = self.board.get_current_token();
+            let bot = self.bots[Self::get_bot_index(token)].as_mut();
+
+            if self.board.get_pending_collapse().is_some() {
+                match (self.rules, bot) {
+                    (Rules::RandomCollapse, _) => {
+                        self.board.collapse_loop(&mut self.rng);
+                    }
+                    (Rules::PlayerCollapse, Some(bot)) => {
+                        let position = bot.choose_collapse(&self.board, token);
+                        if self.board.collapse(position).is_err() {
+                            self.board.collapse_loop(&mut self.rng);
+                        }
+                    }
+                    (Rules::PlayerCollapse, None) => return,
+                }
+                continue;
+            }
+
+            let Some(bot) = bot else {
+                return;
+            };
+
+            let (position_1, position_2) = bot.get_next_move(&self.board, token);
+            if self
+                .board
+                .try_do_turn(position_1, position_2, token)
+                .is_err()
+            {
+                let (position_1, position_2) =
+                    *self.board.get_legal_moves().choose(&mut self.rng).unwrap();
+                self.board.do_turn(position_1, position_2, token);
+            }
+        }
+    }
+
+    fn get_state(&self, id: u64) -> Value {
+        let game_over = self.board.is_game_over();
+        let pending_collapse = self.board.get_pending_collapse();
+
+        let legal_moves = if game_over || pending_collapse.is_some() {
+            vec![]
+        } else {
+            self.board.get_legal_moves()
+        };
+
+        serde_json::to_value(GameState {
+            id,
+            rules: self.rules,
+            players: self.players,
+            board: &self.board,
+            to_move: (!game_over).then(|| self.board.get_current_token()),
+            pending_collapse,
+            legal_moves,
+            score: self.board.get_score(),
+            game_over,
+        })
+        .unwrap()
+    }
+}
+
+#[derive(Default)]
+struct Games {
+    next_id: u64,
+    games: HashMap<u64, Arc<Mutex<ServerGame>>>,
+}
+
+/// A JSON over HTTP front for the game engine, with the Board as the authority on the rules.
+///
+/// Routes:
+/// - `POST /games` with `{"rules": ..., "players": {"x": ..., "o": ...}, "seed": ...}` creates a
+///   game.
+/// - `GET /games/{id}` returns the state of a game.
+/// - `POST /games/{id}/moves` with `{"token": ..., "positions": [..., ...]}` plays a move.
+/// - `POST /games/{id}/collapse` with `{"position": ...}` resolves a pending cycle.
+#[derive(Default)]
+pub struct Server {
+    games: Mutex<Games>,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn handle(&self, method: &str, path: &str, body: &str) -> Response {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments = path
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+
+        let result = match (method, segments.as_slice()) {
+            ("OPTIONS", _) => return Response::new(204, Value::Null),
+            ("POST", ["games"]) => self.create_game(body),
+            ("GET", ["games", id]) => self.with_game(id, |_| Ok(())),
+            ("POST", ["games", id, "moves"]) => {
+                let request: Result<MoveRequest, _> = serde_json::from_str(body);
+
+                request.map_err(ApiError::from).and_then(|request| {
+                    self.with_game(id, |game| {
+                        let [position_1, position_2] = request.positions;
+                        game.board
+                            .try_do_turn(position_1, position_2, request.token)?;
+                        Ok(())
+                    })
+                })
+            }
+            ("POST", ["games", id, "collapse"]) => {
+                let request: Result<CollapseRequest, _> = serde_json::from_str(body);
+
+                request.map_err(ApiError::from).and_then(|request| {
+                    self.with_game(id, |game| {
+                        game.board.collapse(request.position)?;
+                        Ok(())
+                    })
+                })
+            }
+            _ => Err(ApiError::NotFound(format!("No route for {method} {path}"))),
+        };
+
+        result.unwrap_or_else(ApiError::into_response)
+    }
+
+    fn create_game(&self, body: &str) -> Result<Response, ApiError> {
+        let request: NewGameRequest = if body.trim().is_empty() {
+            NewGameRequest::default()
+        } else {
+            serde_json::from_str(body)?
+        };
+
+        let mut game = ServerGame::new(request.rules, request.players, request.seed);
+        game.advance();
+
+        let mut games = self.games.lock().unwrap();
+        let id = games.next_id;
+        games.next_id += 1;
+
+        let state = game.get_state(id);
+        games.games.insert(id, Arc::new(Mutex::new(game)));
+
+        Ok(Response::new(201, state))
+    }
+
+    fn with_game(
+        &self,
+        id: &str,
+        action: impl FnOnce(&mut ServerGame) -> Result<(), ApiError>,
+    ) -> Result<Response, ApiError> {
+        let not_found = || ApiError::NotFound(format!("No game with id {id}"));
+
+        let id = id.parse::<u64>().map_err(|_| not_found())?;
+
+        // Only the game itself stays locked while its bots think, so other games aren't held up.
+        let entry = self.games.lock().unwrap().games.get(&id).cloned();
+        let game = entry.ok_or_else(not_found)?;
+        let mut game = game.lock().unwrap();
+
+        action(&mut game)?;
+        game.advance();
+
+        Ok(Response::new(200, game.get_state(id)))
+    }
+
+    pub fn serve(self: Arc<Self>, listener: TcpListener) -> io::Result<()> {
+        for stream in listener.incoming() {
+            let stream = stream?;
+            let server = Arc::clone(&self);
+
+            thread::spawn(move || {
+                if let Err(error) = server.handle_connection(stream) {
+                    eprintln!("Connection failed: {error}");
+                }
+            });
+        }
+
+        Ok(())
+    }
+
+    fn handle_connection(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(&stream);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let path = parts.next().unwrap_or_default().to_string();
+
+        let mut content_length = 0;
+
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header)?;
+
+            let header = header.trim_end();
+            if header.is_empty() {
+                break;
+            }
+
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+
+        let response = if content_length > MAX_BODY_SIZE {
+            ApiError::BadRequest(format!("Request body larger than {MAX_BODY_SIZE} bytes"))
+                .into_response()
+        } else {
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body)?;
+
+            self.handle(&method, &path, &String::from_utf8_lossy(&body))
+        };
+
+        let body = if response.body.is_null() {
+            String::new()
+        } else {
+            response.body.to_string()
+        };
+
+        write!(
+            &stream,
+            "HTTP/1.1 {} {}\r\n\
+             Content-Type: application/json\r\n\
+             Content-Length: {}\r\n\
+             Access-Control-Allow-Origin: *\r\n\
+             Access-Control-Allow-Methods: GET, POST, OPTIONS\r\n\
+             Access-Control-Allow-Headers: Content-Type\r\n\
+             Connection: close\r\n\
+             \r\n\
+             {body}",
+            response.status,
+            response.reason(),
+            body.len(),
+        )?;
+
+        (&stream).flush()
+    }
+}
+
+#[cfg(test)]
+mod test_server {
+    use super::*;
+
+    fn post_move(
+        server: &Server,
+        id: u64,
+        token: &str,
+        positions: [(usize, usize); 2],
+    ) -> Response {
+        let body = json!({
+            "token": token,
+            "positions": [
+                { "row": positions[0].0, "column": positions[0].1 },
+                { "row": positions[1].0, "column": positions[1].1 },
+            ],
+        });
+
+        server.handle("POST", &format!("/games/{id}/moves"), &body.to_string())
+    }
+
+    #[test]
+    fn test_create_and_get_game() {
+        let server = Server::new();
+
+        let created = server.handle("POST", "/games", "");
+        assert_eq!(201, created.status);
+        assert_eq!(0, created.body["id"]);
+        assert_eq!("random_collapse", created.body["rules"]);
+        assert_eq!("X", created.body["to_move"]);
+        assert_eq!(81, created.body["legal_moves"].as_array().unwrap().len());
+
+        let fetched = server.handle("GET", "/games/0", "");
+        assert_eq!(200, fetched.status);
+        assert_eq!(created.body, fetched.body);
+
+        assert_eq!(404, server.handle("GET", "/games/1", "").status);
+        assert_eq!(404, server.handle("GET", "/nothing", "").status);
+    }
+
+    #[test]
+    fn test_illegal_moves() {
+        let server = Server::new();
+        server.handle("POST", "/games", "{}");
+
+        let response = post_move(&server, 0, "O", [(0, 0), (1, 1)]);
+        assert_eq!(422, response.status);
+        assert_eq!("wrong_turn", response.body["error"]["kind"]);
+        assert_eq!("X", response.body["error"]["details"]);
+
+        let response = post_move(&server, 0, "X", [(0, 0), (3, 1)]);
+        assert_eq!(422, response.status);
+        assert_eq!("invalid_position", response.body["error"]["kind"]);
+
+        let response = server.handle("POST", "/games/0/moves", "{\"token\": \"X\"}");
+        assert_eq!(400, response.status);
+        assert_eq!("bad_request", response.body["error"]["kind"]);
+    }
+
+    #[test]
+    fn test_player_collapse() {
+        let server = Server::new();
+        server.handle("POST", "/games", "{\"rules\": \"player_collapse\"}");
+
+        post_move(&server, 0, "X", [(0, 0), (1, 1)]);
+        let response = post_move(&server, 0, "O", [(1, 1), (0, 0)]);
+        assert_eq!(200, response.status);
+        assert_eq!("X", response.body["to_move"]);
+        assert!(response.body["pending_collapse"].is_array());
+        assert!(response.body["legal_moves"].as_array().unwrap().is_empty());
+
+        let response = post_move(&server, 0, "X", [(2, 2), (1, 2)]);
+        assert_eq!("collapse_pending", response.body["error"]["kind"]);
+
+        let response = server.handle(
+            "POST",
+            "/games/0/collapse",
+            "{\"position\": {\"row\": 2, \"column\": 2}}",
+        );
+        assert_eq!("invalid_collapse", response.body["error"]["kind"]);
+
+        let response = server.handle(
+            "POST",
+            "/games/0/collapse",
+            "{\"position\": {\"row\": 0, \"column\": 0}}",
+        );
+        assert_eq!(200, response.status);
+        assert!(response.body["pending_collapse"].is_null());
+        assert!(response.body["board"]["spooky_marks"]
+            .as_array()
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_bots_play_whole_game() {
+        let server = Server::new();
+
+        let response = server.handle(
+            "POST",
+            "/games",
+            "{\"players\": {\"x\": {\"random\": {\"seed\": 1}}, \"o\": {\"random\": {\"seed\": 2}}}}",
+        );
+
+        assert!(response.body["game_over"].as_bool().unwrap());
+        assert!(response.body["to_move"].is_null());
+    }
+
+    // Plays moves out of the board and collapses to squares out of the cycle.
+    struct BrokenBot;
+
+    impl Bot for BrokenBot {
+        fn get_next_move(&mut self, _board: &Board, _token: Token) -> (Position, Position) {
+            (Position::new(5, 5), Position::new(5, 5))
+        }
+
+        fn choose_collapse(&mut self, _board: &Board, _token: Token) -> Position {
+            Position::new(5, 5)
+        }
+    }
+
+    #[test]
+    fn test_broken_bots_are_played_at_random() {
+        let mut game = ServerGame::new(Rules::PlayerCollapse, Players::default(), 0);
+        game.bots = [Some(Box::new(BrokenBot)), Some(Box::new(BrokenBot))];
+
+        game.advance();
+
+        assert!(game.board.is_game_over());
+    }
+
+    #[test]
+    fn test_seed_reproduces_the_game() {
+        let server = Server::new();
+        let body = "{\"players\": {\"x\": {\"random\": {\"seed\": 1}}, \"o\": {\"random\": {\"seed\": 2}}}, \"seed\": 3}";
+
+        let first = server.handle("POST", "/games", body);
+        let second = server.handle("POST", "/games", body);
+
+        assert_eq!(true, first.body["game_over"]);
+        assert_eq!(first.body["board"], second.body["board"]);
+    }
+
+    #[test]
+    fn test_level_bot_plays() {
+        let server = Server::new();
+
+        let response = server.handle(
+            "POST",
+            "/games",
+            "{\"players\": {\"x\": {\"level\": {\"level\": \"easy\", \"seed\": 1}}}}",
+        );
+
+        assert_eq!(2, response.body["board"]["turn"]);
+        assert_eq!("O", response.body["to_move"]);
+    }
+
+    #[test]
+    fn test_over_loopback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        thread::spawn(move || Arc::new(Server::new()).serve(listener));
+
+        let body = "{\"rules\": \"player_collapse\"}";
+        let mut stream = TcpStream::connect(address).unwrap();
+        write!(
+            stream,
+            "POST /games HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        )
+        .unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        assert!(response.starts_with("HTTP/1.1 201 Created\r\n"));
+
+        let (_, body) = response.split_once("\r\n\r\n").unwrap();
+        let state: Value = serde_json::from_str(body).unwrap();
+        assert_eq!("player_collapse", state["rules"]);
+    }
+}
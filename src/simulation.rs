@@ -0,0 +1,115 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use crate::bot::Bot;
+use crate::game::{Game, GameRecord};
+
+/// The seed of a game of a simulation, which only depends on the simulation's seed and the
+/// game's index, so that results don't depend on how games are spread over threads.
+pub fn get_game_seed(seed: u64, game_index: usize) -> u64 {
+    // The finalizer of splitmix64, which spreads nearby indices over the whole range.
+    let mut z = seed ^ (game_index as u64).wrapping_mul(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+
+    z ^ (z >> 31)
+}
+
+/// The number of threads the machine runs in parallel, or 1 if unknown.
+pub fn get_available_threads() -> usize {
+    thread::available_parallelism()
+        .map(|threads| threads.get())
+        .unwrap_or(1)
+}
+
+/// Plays independent games on the threads, each with the bot made for its seed and
+/// collapsing at random with the same seed, and returns their records in game order.
+///
+/// The records are the same for any number of threads.
+pub fn simulate_games<B, F>(
+    games: usize,
+    threads: usize,
+    seed: u64,
+    create_bot: F,
+) -> Vec<GameRecord>
+where
+    B: Bot,
+    F: Fn(u64) -> B + Sync,
+{
+    let next_game = AtomicUsize::new(0);
+
+    let mut results = thread::scope(|scope| {
+        let handles = (0..threads.clamp(1, games.max(1)))
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut records = vec![];
+
+                    loop {
+                        let game_index = next_game.fetch_add(1, Ordering::Relaxed);
+                        if game_index >= games {
+                            break;
+                        }
+
+                        let game_seed = get_game_seed(seed, game_index);
+                        let mut game = Game::new(create_bot(game_seed), game_seed);
+                        game.set_verbose(false);
+                        game.play_whole_game();
+
+                        records.push((game_index, game.get_record()));
+                    }
+
+                    records
+                })
+            })
+            .collect::<Vec<_>>();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    results.sort_by_key(|(game_index, _)| *game_index);
+
+    results.into_iter().map(|(_, record)| record).collect()
+}
+
+#[cfg(test)]
+mod test_simulation {
+    use super::*;
+
+    use crate::board::Board;
+    use crate::bot::{BotPair, GreedyBot, RandomBot};
+    use crate::evaluation::HeuristicEvaluator;
+    use crate::mcts::{MctsBot, RolloutGuide};
+    use crate::search::SearchBot;
+
+    fn assert_send_and_clone<T>()
+    where
+        T: Send + Clone,
+    {
+    }
+
+    #[test]
+    fn test_board_and_bots_are_send_and_clone() {
+        assert_send_and_clone::<Board>();
+        assert_send_and_clone::<RandomBot>();
+        assert_send_and_clone::<GreedyBot<HeuristicEvaluator>>();
+        assert_send_and_clone::<SearchBot<HeuristicEvaluator>>();
+        assert_send_and_clone::<MctsBot<RolloutGuide>>();
+        assert_send_and_clone::<BotPair<RandomBot, RandomBot>>();
+    }
+
+    #[test]
+    fn test_results_do_not_depend_on_threads() {
+        let create_bots = |seed: u64| BotPair {
+            bot_x: RandomBot::new(seed),
+            bot_o: GreedyBot::new(HeuristicEvaluator::default(), seed + 1),
+        };
+
+        let records = simulate_games(20, 1, 7, create_bots);
+        assert_eq!(20, records.len());
+        assert_eq!(records, simulate_games(20, 4, 7, create_bots));
+        assert_ne!(records, simulate_games(20, 4, 8, create_bots));
+    }
+}
@@ -0,0 +1,445 @@
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Board, Position, Token};
+use crate::game::Rules;
+use crate::transposition::{Bound, Entry, ReplacementPolicy, TranspositionTable};
+
+/// One step of a line of play: a move, or how the cycle it closed collapsed.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Step {
+    Move(Position, Position),
+    Collapse(Position),
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Solution {
+    /// X's score minus O's score under best play, expected over coin flips for random collapses.
+    pub value: f32,
+    /// A line of best play, following the first outcome of every coin flip.
+    pub principal_variation: Vec<Step>,
+}
+
+/// Enough entries to solve the empty board in a couple of minutes with a release build.
+pub const DEFAULT_TABLE_SIZE: usize = 1 << 20;
+
+/// The value of a finished game for X.
+pub fn get_value(board: &Board) -> f32 {
+    let (x, o) = board.get_score();
+
+    x - o
+}
+
+fn get_turns_left(board: &Board) -> u8 {
+    (board.positions.len() as u8 + 1).saturating_sub(board.turn)
+}
+
+/// The moves worth searching: a spooky mark in `(a, b)` is the same move as one in `(b, a)`.
+pub fn get_distinct_moves(board: &Board) -> Vec<(Position, Position)> {
+    board
+        .get_legal_moves()
+        .into_iter()
+        .filter(|(position_1, position_2)| position_1 <= position_2)
+        .collect()
+}
+
+/// Exact minimax search of the whole game tree, with the coin flips of random collapses as
+/// chance nodes.
+pub struct Solver {
+    rules: Rules,
+    table: TranspositionTable,
+    nodes: u64,
+}
+
+impl Solver {
+    pub fn new(rules: Rules) -> Self {
+        Self::with_table_size(rules, DEFAULT_TABLE_SIZE)
+    }
+
+    pub fn with_table_size(rules: Rules, table_size: usize) -> Self {
+        Self {
+            rules,
+            table: TranspositionTable::new(table_size, ReplacementPolicy::PreferDeeper),
+            nodes: 0,
+        }
+    }
+
+    pub fn get_rules(&self) -> Rules {
+        self.rules
+    }
+
+    /// The number of positions searched so far.
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes
+    }
+
+    pub fn get_table(&self) -> &TranspositionTable {
+        &self.table
+    }
+
+    /// The value of the board under best play by both sides.
+    pub fn get_value(&mut self, board: &Board) -> f32 {
+        self.search(board, -1.0, 1.0)
+    }
+
+    pub fn solve(&mut self, board: &Board) -> Solution {
+        let value = self.get_value(board);
+
+        let mut principal_variation = vec![];
+        let mut board = board.clone();
+
+        while let Some(step) = self.get_best_step(&board) {
+            match step {
+                Step::Move(position_1, position_2) => {
+                    board.do_turn(position_1, position_2, board.get_current_token());
+                }
+                Step::Collapse(position) => board.collapse(position).unwrap(),
+            }
+
+            principal_variation.push(step);
+        }
+
+        Solution {
+            value,
+            principal_variation,
+        }
+    }
+
+    /// The best move or collapse choice on the board, or `None` once the game is over.
+    ///
+    /// Under random collapses the coin decides, so the first outcome is returned.
+    pub fn get_best_step(&mut self, board: &Board) -> Option<Step> {
+        if let Some(spooky_mark) = board.get_pending_collapse() {
+            if self.rules == Rules::RandomCollapse {
+                return Some(Step::Collapse(spooky_mark.0));
+            }
+
+            let options = [spooky_mark.0, spooky_mark.1].map(|position| {
+                let mut child = board.clone();
+                child.collapse(position).unwrap();
+
+                (Step::Collapse(position), self.get_value(&child))
+            });
+
+            return self.get_best_option(board.get_current_token(), options);
+        }
+
+        if board.is_game_over() {
+            return None;
+        }
+
+        let options = get_distinct_moves(board)
+            .into_iter()
+            .map(|(position_1, position_2)| {
+                let mut child = board.clone();
+                child.do_turn(position_1, position_2, board.get_current_token());
+
+                (Step::Move(position_1, position_2), self.get_value(&child))
+            })
+            .collect::<Vec<_>>();
+
+        self.get_best_option(board.get_current_token(), options)
+    }
+
+    fn get_best_option(
+        &self,
+        token: Token,
+        options: impl IntoIterator<Item = (Step, f32)>,
+    ) -> Option<Step> {
+        let sign = match token {
+            Token::X => 1.0,
+            Token::O => -1.0,
+        };
+
+        options
+            .into_iter()
+            .max_by(|(_, value_1), (_, value_2)| (sign * value_1).total_cmp(&(sign * value_2)))
+            .map(|(step, _)| step)
+    }
+
+    fn search(&mut self, board: &Board, mut alpha: f32, mut beta: f32) -> f32 {
+        self.nodes += 1;
+
+        if let Some(spooky_mark) = board.get_pending_collapse() {
+            if self.rules == Rules::RandomCollapse {
+                return self.search_coin_flip(board, spooky_mark.0, spooky_mark.1, alpha, beta);
+            }
+        } else if board.is_game_over() {
+            return get_value(board);
+        }
+
+        let hash = board.get_canonical_hash();
+        let symmetry = board.get_canonical_symmetry();
+        let original_alpha = alpha;
+        let original_beta = beta;
+
+        let mut best_move = None;
+
+        if let Some(entry) = self.table.probe(hash) {
+            match entry.bound {
+                Bound::Exact => return entry.value,
+                Bound::Lower => alpha = alpha.max(entry.value),
+                Bound::Upper => beta = beta.min(entry.value),
+            }
+
+            if alpha >= beta {
+                return entry.value;
+            }
+
+            best_move = entry.best_move.map(|(position_1, position_2)| {
+                let position_1 = symmetry.inverse().apply(position_1);
+                let position_2 = symmetry.inverse().apply(position_2);
+
+                (position_1.min(position_2), position_1.max(position_2))
+            });
+        }
+
+        let token = board.get_current_token();
+
+        let mut children = match board.get_pending_collapse() {
+            Some(spooky_mark) => [spooky_mark.0, spooky_mark.1]
+                .map(|position| {
+                    let mut child = board.clone();
+                    child.collapse(position).unwrap();
+
+                    ((position, position), child)
+                })
+                .to_vec(),
+            None => get_distinct_moves(board)
+                .into_iter()
+                .map(|(position_1, position_2)| {
+                    let mut child = board.clone();
+                    child.do_turn(position_1, position_2, token);
+
+                    ((position_1, position_2), child)
+                })
+                .collect(),
+        };
+
+        // Games are only decided by collapses, so moves that close a cycle are tried first, and
+        // the best move of an earlier search before anything else.
+        children.sort_by_key(|(child_move, child)| {
+            (
+                Some(*child_move) != best_move,
+                child.get_pending_collapse().is_none(),
+            )
+        });
+
+        let mut best_value = match token {
+            Token::X => f32::NEG_INFINITY,
+            Token::O => f32::INFINITY,
+        };
+
+        for (child_move, child) in children {
+            let value = self.search(&child, alpha, beta);
+
+            let is_better = match token {
+                Token::X => value > best_value,
+                Token::O => value < best_value,
+            };
+
+            if is_better {
+                best_value = value;
+                best_move = Some(child_move);
+            }
+
+            match token {
+                Token::X => alpha = alpha.max(value),
+                Token::O => beta = beta.min(value),
+            }
+
+            if alpha >= beta {
+                break;
+            }
+        }
+
+        let bound = if best_value <= original_alpha {
+            Bound::Upper
+        } else if best_value >= original_beta {
+            Bound::Lower
+        } else {
+            Bound::Exact
+        };
+
+        self.table.store(Entry {
+            hash,
+            value: best_value,
+            bound,
+            depth: get_turns_left(board),
+            best_move: best_move.map(|(position_1, position_2)| {
+                (symmetry.apply(position_1), symmetry.apply(position_2))
+            }),
+        });
+
+        best_value
+    }
+
+    // Each outcome is searched with the narrowest window that can still change the average.
+    fn search_coin_flip(
+        &mut self,
+        board: &Board,
+        position_1: Position,
+        position_2: Position,
+        alpha: f32,
+        beta: f32,
+    ) -> f32 {
+        let mut first = board.clone();
+        first.collapse(position_1).unwrap();
+
+        let value_1 = self.search(
+            &first,
+            (2.0 * alpha - 1.0).max(-1.0),
+            (2.0 * beta + 1.0).min(1.0),
+        );
+
+        if 0.5 * value_1 + 0.5 <= alpha {
+            return 0.5 * value_1 + 0.5;
+        }
+
+        if 0.5 * value_1 - 0.5 >= beta {
+            return 0.5 * value_1 - 0.5;
+        }
+
+        let mut second = board.clone();
+        second.collapse(position_2).unwrap();
+
+        let value_2 = self.search(
+            &second,
+            (2.0 * alpha - value_1).max(-1.0),
+            (2.0 * beta - value_1).min(1.0),
+        );
+
+        0.5 * value_1 + 0.5 * value_2
+    }
+}
+
+#[cfg(test)]
+mod test_solver {
+    use super::*;
+
+    type Move = ((usize, usize), (usize, usize));
+
+    fn create_board(moves: &[Move]) -> Board {
+        let mut board = Board::new();
+
+        for (position_1, position_2) in moves {
+            board.do_turn(
+                Position::new(position_1.0, position_1.1),
+                Position::new(position_2.0, position_2.1),
+                board.get_current_token(),
+            );
+        }
+
+        board
+    }
+
+    #[test]
+    fn test_finishing_move() {
+        // X has two in the top row and can take the third square classically.
+        let board = create_board(&[
+            ((0, 0), (0, 0)),
+            ((1, 0), (1, 0)),
+            ((0, 1), (0, 1)),
+            ((1, 1), (1, 1)),
+        ]);
+
+        for rules in [Rules::RandomCollapse, Rules::PlayerCollapse] {
+            let solution = Solver::new(rules).solve(&board);
+            assert_eq!(1.0, solution.value);
+
+            let mut board = board.clone();
+            for step in solution.principal_variation {
+                match step {
+                    Step::Move(position_1, position_2) => {
+                        board.do_turn(position_1, position_2, board.get_current_token())
+                    }
+                    Step::Collapse(position) => board.collapse(position).unwrap(),
+                }
+            }
+            assert!(board.is_game_over());
+            assert_eq!(1.0, get_value(&board));
+        }
+    }
+
+    #[test]
+    fn test_rules_change_the_value_of_a_cycle() {
+        // X5 and O6 close a cycle, and X wins the top row if X5 collapses to (0, 2).
+        let board = create_board(&[
+            ((0, 0), (0, 0)),
+            ((1, 0), (1, 0)),
+            ((0, 1), (0, 1)),
+            ((1, 1), (1, 1)),
+            ((0, 2), (2, 2)),
+            ((0, 2), (2, 2)),
+        ]);
+
+        let mut solver = Solver::new(Rules::PlayerCollapse);
+        assert_eq!(1.0, solver.get_value(&board));
+        assert_eq!(
+            Some(Step::Collapse(Position::new(0, 2))),
+            solver.get_best_step(&board)
+        );
+
+        let mut solver = Solver::new(Rules::RandomCollapse);
+        let value = solver.get_value(&board);
+        assert!((0.0..1.0).contains(&value));
+
+        let mut other_outcome = board.clone();
+        other_outcome.collapse(Position::new(2, 2)).unwrap();
+        assert_eq!(0.5 + 0.5 * solver.get_value(&other_outcome), value);
+    }
+
+    fn get_brute_force_value(board: &Board, rules: Rules) -> f32 {
+        if let Some(spooky_mark) = board.get_pending_collapse() {
+            let values = [spooky_mark.0, spooky_mark.1].map(|position| {
+                let mut child = board.clone();
+                child.collapse(position).unwrap();
+                get_brute_force_value(&child, rules)
+            });
+
+            return match (rules, board.get_current_token()) {
+                (Rules::RandomCollapse, _) => 0.5 * values[0] + 0.5 * values[1],
+                (Rules::PlayerCollapse, Token::X) => values[0].max(values[1]),
+                (Rules::PlayerCollapse, Token::O) => values[0].min(values[1]),
+            };
+        }
+
+        if board.is_game_over() {
+            return get_value(board);
+        }
+
+        let values = board
+            .get_legal_moves()
+            .into_iter()
+            .map(|(position_1, position_2)| {
+                let mut child = board.clone();
+                child.do_turn(position_1, position_2, board.get_current_token());
+                get_brute_force_value(&child, rules)
+            });
+
+        match board.get_current_token() {
+            Token::X => values.fold(f32::NEG_INFINITY, f32::max),
+            Token::O => values.fold(f32::INFINITY, f32::min),
+        }
+    }
+
+    #[test]
+    fn test_matches_brute_force() {
+        let board = create_board(&[
+            ((0, 0), (1, 1)),
+            ((0, 1), (1, 2)),
+            ((2, 2), (2, 0)),
+            ((0, 2), (2, 1)),
+            ((1, 0), (0, 1)),
+            ((1, 1), (2, 2)),
+            ((2, 0), (1, 2)),
+        ]);
+
+        for rules in [Rules::RandomCollapse, Rules::PlayerCollapse] {
+            assert_eq!(
+                get_brute_force_value(&board, rules),
+                Solver::new(rules).get_value(&board)
+            );
+        }
+    }
+}
@@ -0,0 +1,148 @@
+use serde::{Deserialize, Serialize};
+
+use crate::board::{Position, TurnToken, BOARD_SIZE};
+
+/// One of the 8 symmetries of the square, acting on board positions.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Symmetry {
+    Identity,
+    /// A quarter turn clockwise.
+    Rotate90,
+    Rotate180,
+    /// A quarter turn counterclockwise.
+    Rotate270,
+    /// Mirrors left and right.
+    FlipHorizontal,
+    /// Mirrors top and bottom.
+    FlipVertical,
+    /// Mirrors along the diagonal from the top left corner.
+    FlipDiagonal,
+    /// Mirrors along the diagonal from the top right corner.
+    FlipAntiDiagonal,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipHorizontal,
+        Symmetry::FlipVertical,
+        Symmetry::FlipDiagonal,
+        Symmetry::FlipAntiDiagonal,
+    ];
+
+    pub fn apply(&self, position: Position) -> Position {
+        let last = BOARD_SIZE - 1;
+        let (row, column) = (position.row(), position.column());
+
+        let (row, column) = match self {
+            Symmetry::Identity => (row, column),
+            Symmetry::Rotate90 => (column, last - row),
+            Symmetry::Rotate180 => (last - row, last - column),
+            Symmetry::Rotate270 => (last - column, row),
+            Symmetry::FlipHorizontal => (row, last - column),
+            Symmetry::FlipVertical => (last - row, column),
+            Symmetry::FlipDiagonal => (column, row),
+            Symmetry::FlipAntiDiagonal => (last - column, last - row),
+        };
+
+        Position::new(row, column)
+    }
+
+    /// The symmetry that undoes this one.
+    pub fn inverse(&self) -> Symmetry {
+        match self {
+            Symmetry::Rotate90 => Symmetry::Rotate270,
+            Symmetry::Rotate270 => Symmetry::Rotate90,
+            symmetry => *symmetry,
+        }
+    }
+}
+
+const SQUARES: usize = BOARD_SIZE * BOARD_SIZE;
+
+// Turn subscripts run from 1 to the number of squares.
+const SUBSCRIPTS: usize = SQUARES + 1;
+
+const fn split_mix(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
+
+const fn generate_keys<const N: usize>(seed: u64) -> [u64; N] {
+    let mut keys = [0; N];
+    let mut state = seed;
+    let mut index = 0;
+
+    while index < N {
+        state = split_mix(state);
+        keys[index] = state;
+        index += 1;
+    }
+
+    keys
+}
+
+// Fixed keys, so hashes are stable across runs and can be stored in files.
+static CLASSICAL_KEYS: [u64; SQUARES * 2 * SUBSCRIPTS] = generate_keys(1);
+static SPOOKY_KEYS: [u64; SQUARES * SQUARES * 2 * SUBSCRIPTS] = generate_keys(2);
+
+fn get_token_index(turn_token: TurnToken) -> usize {
+    match turn_token {
+        TurnToken::X(turn) => 2 * turn as usize,
+        TurnToken::O(turn) => 2 * turn as usize + 1,
+    }
+}
+
+/// The Zobrist key of a classical mark.
+pub(crate) fn get_classical_key(position: Position, turn_token: TurnToken) -> u64 {
+    CLASSICAL_KEYS[position.get_index() * 2 * SUBSCRIPTS + get_token_index(turn_token)]
+}
+
+/// The Zobrist key of a spooky mark, which doesn't depend on the order of its positions.
+pub(crate) fn get_spooky_key(
+    position_1: Position,
+    position_2: Position,
+    turn_token: TurnToken,
+) -> u64 {
+    let (index_1, index_2) = (position_1.get_index(), position_2.get_index());
+    let (low, high) = (index_1.min(index_2), index_1.max(index_2));
+
+    SPOOKY_KEYS[(low * SQUARES + high) * 2 * SUBSCRIPTS + get_token_index(turn_token)]
+}
+
+#[cfg(test)]
+mod test_symmetry {
+    use super::*;
+
+    #[test]
+    fn test_inverse() {
+        for symmetry in Symmetry::ALL {
+            for row in 0..BOARD_SIZE {
+                for column in 0..BOARD_SIZE {
+                    let position = Position::new(row, column);
+                    assert_eq!(position, symmetry.inverse().apply(symmetry.apply(position)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_symmetries_are_distinct() {
+        let edge = Position::new(0, 1);
+        let corner = Position::new(0, 0);
+
+        assert_eq!(Position::new(1, 2), Symmetry::Rotate90.apply(edge));
+        assert_eq!(Position::new(1, 0), Symmetry::FlipDiagonal.apply(edge));
+
+        let images = Symmetry::ALL.map(|symmetry| (symmetry.apply(edge), symmetry.apply(corner)));
+        for (index, image) in images.iter().enumerate() {
+            assert!(!images[..index].contains(image));
+        }
+    }
+}
@@ -0,0 +1,505 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use derive_more::{Display, Error};
+use itertools::Itertools;
+
+use crate::board::{Board, Position, SpookyMark, Token, TurnToken, BOARD_SIZE};
+use crate::game::Rules;
+use crate::solver::{self, get_distinct_moves};
+use crate::symmetry::Symmetry;
+
+const MAGIC: &[u8; 4] = b"TQTB";
+const VERSION: u8 = 1;
+const HEADER_SIZE: usize = 16;
+const ENTRY_SIZE: usize = 12;
+
+/// Exact values of every position with at most a given number of free squares, that is squares
+/// without a classical mark. The positions are enumerated forwards, from their marks, and solved
+/// by retrograde analysis, one turn at a time from the last one backwards. Since every move
+/// adds a mark, a position's moves only lead to positions already solved, and no unmoves are
+/// needed.
+///
+/// The file is a 16 byte header, `TQTB`, the version, the rules (0 for random collapses, 1 for
+/// player collapses), the number of free squares, 1 byte of padding and the number of entries as
+/// a little endian `u64`, followed by the entries sorted by hash, each a little endian `u64`
+/// canonical hash and an `f32` value for X.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Tablebase {
+    rules: Rules,
+    max_free_squares: u8,
+    entries: Vec<(u64, f32)>,
+}
+
+// Every placement of turn subscripts on the marks, X on odd turns and O on even ones.
+fn enumerate_subscripts(
+    classical_positions: &[Position],
+    spooky_positions: &[(Position, Position)],
+) -> Vec<Board> {
+    let mut boards = vec![];
+    let slots = classical_positions.len() + spooky_positions.len();
+    let x_turns = (1..=slots as u8).filter(|turn| turn % 2 == 1).collect_vec();
+    let o_turns = (1..=slots as u8).filter(|turn| turn % 2 == 0).collect_vec();
+
+    for x_slots in (0..slots).combinations(x_turns.len()) {
+        let is_x = |slot: usize| x_slots.contains(&slot);
+
+        // Boards with a whole line of one token are already over.
+        let tokens = classical_positions
+            .iter()
+            .enumerate()
+            .map(|(slot, position)| (*position, is_x(slot)))
+            .collect::<HashMap<_, _>>();
+
+        if Board::new().rows_columns_and_diagonals.iter().any(|line| {
+            let line_tokens = line.iter().map(|p| tokens.get(p)).collect_vec();
+            line_tokens.iter().all(|t| *t == Some(&true))
+                || line_tokens.iter().all(|t| *t == Some(&false))
+        }) {
+            continue;
+        }
+
+        let o_slots = (0..slots).filter(|slot| !is_x(*slot)).collect_vec();
+
+        for x_order in x_turns.iter().permutations(x_turns.len()) {
+            for o_order in o_turns.iter().permutations(o_turns.len()) {
+                let mut turn_tokens = vec![TurnToken::X(0); slots];
+                for (slot, turn) in x_slots.iter().zip(&x_order) {
+                    turn_tokens[*slot] = TurnToken::X(**turn);
+                }
+                for (slot, turn) in o_slots.iter().zip(&o_order) {
+                    turn_tokens[*slot] = TurnToken::O(**turn);
+                }
+
+                let mut board = [[None; BOARD_SIZE]; BOARD_SIZE];
+                for (position, turn_token) in classical_positions.iter().zip(&turn_tokens) {
+                    board[position.row()][position.column()] = Some(*turn_token);
+                }
+
+                let mut spooky_marks = spooky_positions
+                    .iter()
+                    .zip(&turn_tokens[classical_positions.len()..])
+                    .map(|((position_1, position_2), turn_token)| {
+                        SpookyMark(*position_1, *position_2, *turn_token)
+                    })
+                    .collect_vec();
+                spooky_marks.sort_by_key(|SpookyMark(_, _, turn_token)| match turn_token {
+                    TurnToken::X(turn) | TurnToken::O(turn) => *turn,
+                });
+
+                boards.push(Board::from_marks(board, spooky_marks));
+            }
+        }
+    }
+
+    boards
+}
+
+// Whether the free squares and spooky marks are the first of their images under the symmetries
+// of the board, as only the boards of one of them need to be enumerated.
+fn is_representative(
+    free_positions: &[Position],
+    spooky_positions: &[(Position, Position)],
+) -> bool {
+    let get_image = |symmetry: Symmetry| {
+        let free_positions = free_positions
+            .iter()
+            .map(|position| symmetry.apply(*position))
+            .sorted()
+            .collect_vec();
+        let spooky_positions = spooky_positions
+            .iter()
+            .map(|(position_1, position_2)| {
+                let (position_1, position_2) =
+                    (symmetry.apply(*position_1), symmetry.apply(*position_2));
+                (position_1.min(position_2), position_1.max(position_2))
+            })
+            .sorted()
+            .collect_vec();
+
+        (free_positions, spooky_positions)
+    };
+
+    let image = get_image(Symmetry::Identity);
+
+    Symmetry::ALL
+        .into_iter()
+        .all(|symmetry| image <= get_image(symmetry))
+}
+
+/// Every position with between 1 and `max_free_squares` free squares that isn't over and has no
+/// cycle to collapse, once per symmetry class.
+fn enumerate_positions(max_free_squares: u8) -> Vec<Board> {
+    let all_positions = Board::new().positions;
+    let mut seen = HashSet::new();
+    let mut positions = vec![];
+
+    for free_squares in 1..=max_free_squares as usize {
+        for free_positions in all_positions.iter().copied().combinations(free_squares) {
+            let classical_positions = all_positions
+                .iter()
+                .copied()
+                .filter(|position| !free_positions.contains(position))
+                .collect_vec();
+
+            let pairs = free_positions
+                .iter()
+                .copied()
+                .tuple_combinations()
+                .collect_vec();
+
+            // Without cycles, there are fewer spooky marks than free squares.
+            for spooky_count in 0..free_squares {
+                if classical_positions.len() + spooky_count >= all_positions.len() {
+                    continue;
+                }
+
+                for spooky_positions in pairs.iter().copied().combinations(spooky_count) {
+                    if !is_representative(&free_positions, &spooky_positions) {
+                        continue;
+                    }
+
+                    for board in enumerate_subscripts(&classical_positions, &spooky_positions) {
+                        if board.get_pending_collapse().is_none()
+                            && !board.is_game_over()
+                            && seen.insert(board.get_canonical_hash())
+                        {
+                            positions.push(board);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    positions
+}
+
+/// A position `Tablebase::generate` couldn't solve, as one of its moves leads to a position
+/// that wasn't enumerated.
+#[derive(Clone, Copy, Debug, Display, Eq, Error, PartialEq)]
+#[display(
+    "Unable to solve position {hash:#018x}, a move leads to a position that wasn't enumerated"
+)]
+pub struct UnsolvedPosition {
+    pub hash: u64,
+}
+
+impl Tablebase {
+    /// Enumerates and solves every position with at most `max_free_squares` free squares.
+    ///
+    /// The positions are solved from the last turn backwards, since every move leads to a later
+    /// turn with at most as many free squares.
+    pub fn generate(rules: Rules, max_free_squares: u8) -> Result<Self, UnsolvedPosition> {
+        Self::solve(
+            rules,
+            max_free_squares,
+            enumerate_positions(max_free_squares),
+        )
+    }
+
+    fn solve(
+        rules: Rules,
+        max_free_squares: u8,
+        mut positions: Vec<Board>,
+    ) -> Result<Self, UnsolvedPosition> {
+        positions.sort_by_key(|board| std::cmp::Reverse(board.turn));
+
+        let mut tablebase = Self {
+            rules,
+            max_free_squares,
+            entries: vec![],
+        };
+        let mut values = HashMap::new();
+
+        for board in positions {
+            let value = tablebase
+                .get_best_value(&board, &|child| {
+                    values.get(&child.get_canonical_hash()).copied()
+                })
+                .ok_or(UnsolvedPosition {
+                    hash: board.get_canonical_hash(),
+                })?;
+
+            values.insert(board.get_canonical_hash(), value);
+        }
+
+        tablebase.entries = values
+            .into_iter()
+            .sorted_by_key(|(hash, _)| *hash)
+            .collect();
+
+        Ok(tablebase)
+    }
+
+    pub fn get_rules(&self) -> Rules {
+        self.rules
+    }
+
+    pub fn get_max_free_squares(&self) -> u8 {
+        self.max_free_squares
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // The value of the best move on the board, with values of positions without a cycle looked
+    // up by `lookup`.
+    fn get_best_value(
+        &self,
+        board: &Board,
+        lookup: &impl Fn(&Board) -> Option<f32>,
+    ) -> Option<f32> {
+        let token = board.get_current_token();
+
+        let values = get_distinct_moves(board)
+            .into_iter()
+            .map(|(position_1, position_2)| {
+                let mut child = board.clone();
+                child.do_turn(position_1, position_2, token);
+
+                self.get_value_with(&child, lookup)
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        match token {
+            Token::X => values.into_iter().reduce(f32::max),
+            Token::O => values.into_iter().reduce(f32::min),
+        }
+    }
+
+    fn get_value_with(
+        &self,
+        board: &Board,
+        lookup: &impl Fn(&Board) -> Option<f32>,
+    ) -> Option<f32> {
+        if let Some(spooky_mark) = board.get_pending_collapse() {
+            let values = [spooky_mark.0, spooky_mark.1].map(|position| {
+                let mut child = board.clone();
+                child.collapse(position).unwrap();
+
+                self.get_value_with(&child, lookup)
+            });
+            let [value_1, value_2] = [values[0]?, values[1]?];
+
+            return Some(match (self.rules, board.get_current_token()) {
+                (Rules::RandomCollapse, _) => 0.5 * value_1 + 0.5 * value_2,
+                (Rules::PlayerCollapse, Token::X) => value_1.max(value_2),
+                (Rules::PlayerCollapse, Token::O) => value_1.min(value_2),
+            });
+        }
+
+        if board.is_game_over() {
+            return Some(solver::get_value(board));
+        }
+
+        lookup(board)
+    }
+
+    /// The exact value of the board for X, if it has few enough free squares.
+    pub fn probe(&self, board: &Board) -> Option<f32> {
+        self.get_value_with(board, &|board| {
+            let hash = board.get_canonical_hash();
+
+            self.entries
+                .binary_search_by_key(&hash, |(entry_hash, _)| *entry_hash)
+                .ok()
+                .map(|index| self.entries[index].1)
+        })
+    }
+
+    /// A move with the best value for the player to move, if the board is in the tablebase and
+    /// not over.
+    pub fn get_best_move(&self, board: &Board) -> Option<(Position, Position)> {
+        if board.get_pending_collapse().is_some() || board.is_game_over() {
+            return None;
+        }
+
+        let token = board.get_current_token();
+        let sign = match token {
+            Token::X => 1.0,
+            Token::O => -1.0,
+        };
+
+        get_distinct_moves(board)
+            .into_iter()
+            .map(|(position_1, position_2)| {
+                let mut child = board.clone();
+                child.do_turn(position_1, position_2, token);
+
+                Some(((position_1, position_2), sign * self.probe(&child)?))
+            })
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .max_by(|(_, value_1), (_, value_2)| value_1.total_cmp(value_2))
+            .map(|(best_move, _)| best_move)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut bytes = Vec::with_capacity(HEADER_SIZE + ENTRY_SIZE * self.entries.len());
+
+        bytes.extend_from_slice(MAGIC);
+        bytes.push(VERSION);
+        bytes.push(match self.rules {
+            Rules::RandomCollapse => 0,
+            Rules::PlayerCollapse => 1,
+        });
+        bytes.push(self.max_free_squares);
+        bytes.push(0);
+        bytes.extend_from_slice(&(self.entries.len() as u64).to_le_bytes());
+
+        for (hash, value) in &self.entries {
+            bytes.extend_from_slice(&hash.to_le_bytes());
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+
+        fs::write(path, bytes)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let bytes = fs::read(path)?;
+        let invalid =
+            |message: &str| io::Error::new(io::ErrorKind::InvalidData, message.to_string());
+
+        if bytes.len() < HEADER_SIZE || &bytes[..4] != MAGIC {
+            return Err(invalid("Not a tablebase file"));
+        }
+
+        if bytes[4] != VERSION {
+            return Err(invalid("Unsupported tablebase version"));
+        }
+
+        let rules = match bytes[5] {
+            0 => Rules::RandomCollapse,
+            1 => Rules::PlayerCollapse,
+            _ => return Err(invalid("Unknown rules")),
+        };
+
+        let count = u64::from_le_bytes(bytes[8..16].try_into().unwrap());
+        let size = usize::try_from(count)
+            .ok()
+            .and_then(|count| count.checked_mul(ENTRY_SIZE))
+            .and_then(|size| size.checked_add(HEADER_SIZE));
+
+        if size != Some(bytes.len()) {
+            return Err(invalid("Truncated tablebase file"));
+        }
+
+        let entries: Vec<(u64, f32)> = bytes[HEADER_SIZE..]
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| {
+                (
+                    u64::from_le_bytes(entry[..8].try_into().unwrap()),
+                    f32::from_le_bytes(entry[8..].try_into().unwrap()),
+                )
+            })
+            .collect();
+
+        // Probes search the entries by hash.
+        if !entries.windows(2).all(|pair| pair[0].0 < pair[1].0) {
+            return Err(invalid("Tablebase entries not sorted by hash"));
+        }
+
+        Ok(Self {
+            rules,
+            max_free_squares: bytes[6],
+            entries,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test_tablebase {
+    use rand::prelude::{SeedableRng, StdRng};
+    use rstest::rstest;
+
+    use super::*;
+
+    use crate::bot::{Bot, RandomBot};
+    use crate::solver::Solver;
+
+    // Generating larger tablebases takes a while without optimizations.
+    #[rstest]
+    #[case(Rules::RandomCollapse, 2)]
+    #[case(Rules::PlayerCollapse, 1)]
+    fn test_matches_solver(#[case] rules: Rules, #[case] max_free_squares: u8) {
+        let tablebase = Tablebase::generate(rules, max_free_squares).unwrap();
+        let mut solver = Solver::new(rules);
+
+        let mut probed = 0;
+
+        for seed in 0..20 {
+            let mut bot = RandomBot::new(seed);
+            let mut rng = StdRng::seed_from_u64(seed);
+            let mut board = Board::new();
+
+            while !board.is_game_over() {
+                if let Some(value) = tablebase.probe(&board) {
+                    assert_eq!(solver.get_value(&board), value);
+                    probed += 1;
+
+                    let (position_1, position_2) = tablebase.get_best_move(&board).unwrap();
+                    let mut child = board.clone();
+                    child.do_turn(position_1, position_2, board.get_current_token());
+                    assert_eq!(Some(value), tablebase.probe(&child));
+                }
+
+                let (position_1, position_2) = bot.get_next_move(&board, board.get_current_token());
+                board.do_turn(position_1, position_2, board.get_current_token());
+                board.collapse_loop(&mut rng);
+            }
+        }
+
+        assert!(probed > 0);
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let tablebase = Tablebase::generate(Rules::PlayerCollapse, 1).unwrap();
+        assert!(tablebase.probe(&Board::new()).is_none());
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("tablebase.bin");
+        tablebase.save(&path).unwrap();
+        assert_eq!(tablebase, Tablebase::load(&path).unwrap());
+
+        std::fs::write(&path, b"not a tablebase").unwrap();
+        assert!(Tablebase::load(&path).is_err());
+
+        tablebase.save(&path).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+
+        // A count whose size overflows.
+        let mut corrupt = bytes.clone();
+        corrupt[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(&path, corrupt).unwrap();
+        assert!(Tablebase::load(&path).is_err());
+
+        // Entries out of order, which probes couldn't find.
+        assert!(tablebase.len() >= 2);
+        let mut corrupt = bytes;
+        let entries = &mut corrupt[HEADER_SIZE..HEADER_SIZE + 2 * ENTRY_SIZE];
+        entries.rotate_left(ENTRY_SIZE);
+        std::fs::write(&path, corrupt).unwrap();
+        assert!(Tablebase::load(&path).is_err());
+    }
+
+    #[test]
+    fn test_missing_positions_are_an_error() {
+        // Without the positions of the last turn, those of the turn before can't be solved.
+        let positions = enumerate_positions(2)
+            .into_iter()
+            .filter(|board| board.turn == 8)
+            .collect_vec();
+        assert!(!positions.is_empty());
+
+        assert!(Tablebase::solve(Rules::RandomCollapse, 2, positions).is_err());
+    }
+}
@@ -0,0 +1,37 @@
+//! Helpers shared by the tests of several modules.
+
+use crate::board::Token;
+use crate::bot::{Bot, BotPair, RandomBot};
+use crate::simulation::simulate_games;
+
+/// Plays the bot made for each seed against `RandomBot` as the given token, and checks that it
+/// scores more than twice as many points.
+pub fn assert_beats_random_bot<B, F>(games: usize, token: Token, create_bot: F)
+where
+    B: Bot,
+    F: Fn(u64) -> B + Sync,
+{
+    let records = match token {
+        Token::X => simulate_games(games, 1, 0, |seed| BotPair {
+            bot_x: create_bot(seed),
+            bot_o: RandomBot::new(seed.wrapping_add(1)),
+        }),
+        Token::O => simulate_games(games, 1, 0, |seed| BotPair {
+            bot_x: RandomBot::new(seed.wrapping_add(1)),
+            bot_o: create_bot(seed),
+        }),
+    };
+
+    // The bot's points first.
+    let points = records
+        .iter()
+        .map(|record| match token {
+            Token::X => record.score,
+            Token::O => (record.score.1, record.score.0),
+        })
+        .fold((0.0, 0.0), |points, score| {
+            (points.0 + score.0, points.1 + score.1)
+        });
+
+    assert!(points.0 > 2.0 * points.1, "{points:?}");
+}
@@ -0,0 +1,203 @@
+use crate::board::Position;
+
+/// How a stored value relates to the true value of the position.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Bound {
+    Exact,
+    /// The search failed high, so the true value is at least this.
+    Lower,
+    /// The search failed low, so the true value is at most this.
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Entry {
+    pub hash: u64,
+    pub value: f32,
+    pub bound: Bound,
+    pub depth: u8,
+    pub best_move: Option<(Position, Position)>,
+}
+
+/// Which entry to keep when two positions land in the same slot.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ReplacementPolicy {
+    /// The newest entry always wins.
+    Always,
+    /// Entries from earlier searches are replaced, but within a search the deeper entry is kept.
+    #[default]
+    PreferDeeper,
+}
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TableStats {
+    pub hits: u64,
+    pub misses: u64,
+    pub stores: u64,
+    /// Stores that overwrote an entry for another position.
+    pub replacements: u64,
+    /// Stores dropped by the replacement policy.
+    pub rejections: u64,
+}
+
+impl TableStats {
+    pub fn get_hit_rate(&self) -> f64 {
+        let probes = self.hits + self.misses;
+
+        if probes == 0 {
+            0.0
+        } else {
+            self.hits as f64 / probes as f64
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Slot {
+    entry: Entry,
+    generation: u32,
+}
+
+/// A fixed-size table of search results, keyed by a board hash such as `Board::get_hash`.
+///
+/// When keyed by `Board::get_canonical_hash`, best moves should be stored as seen on the
+/// canonical board and mapped back through the symmetry returned by `Board::canonicalize`.
+#[derive(Clone, Debug)]
+pub struct TranspositionTable {
+    slots: Vec<Option<Slot>>,
+    policy: ReplacementPolicy,
+    generation: u32,
+    stats: TableStats,
+}
+
+impl TranspositionTable {
+    /// A table with room for `capacity` entries, rounded up to a power of two.
+    pub fn new(capacity: usize, policy: ReplacementPolicy) -> Self {
+        Self {
+            slots: vec![None; capacity.max(1).next_power_of_two()],
+            policy,
+            generation: 0,
+            stats: TableStats::default(),
+        }
+    }
+
+    pub fn get_capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn get_stats(&self) -> TableStats {
+        self.stats
+    }
+
+    fn get_slot_index(&self, hash: u64) -> usize {
+        hash as usize & (self.slots.len() - 1)
+    }
+
+    /// The entry stored for the hash, if it is still in the table.
+    pub fn probe(&mut self, hash: u64) -> Option<Entry> {
+        let index = self.get_slot_index(hash);
+
+        match self.slots[index] {
+            Some(slot) if slot.entry.hash == hash => {
+                self.stats.hits += 1;
+                Some(slot.entry)
+            }
+            _ => {
+                self.stats.misses += 1;
+                None
+            }
+        }
+    }
+
+    pub fn store(&mut self, entry: Entry) {
+        let index = self.get_slot_index(entry.hash);
+
+        if let Some(slot) = self.slots[index] {
+            let is_same_position = slot.entry.hash == entry.hash;
+
+            let keep_old = match self.policy {
+                ReplacementPolicy::Always => false,
+                ReplacementPolicy::PreferDeeper => {
+                    slot.generation == self.generation && slot.entry.depth > entry.depth
+                }
+            };
+
+            if keep_old {
+                self.stats.rejections += 1;
+                return;
+            }
+
+            if !is_same_position {
+                self.stats.replacements += 1;
+            }
+        }
+
+        self.stats.stores += 1;
+        self.slots[index] = Some(Slot {
+            entry,
+            generation: self.generation,
+        });
+    }
+
+    /// Marks every stored entry as coming from an earlier search, so new ones can replace them.
+    pub fn new_search(&mut self) {
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    pub fn clear(&mut self) {
+        self.slots.fill(None);
+        self.stats = TableStats::default();
+    }
+}
+
+#[cfg(test)]
+mod test_transposition_table {
+    use super::*;
+
+    fn create_entry(hash: u64, depth: u8) -> Entry {
+        Entry {
+            hash,
+            value: 0.5,
+            bound: Bound::Exact,
+            depth,
+            best_move: Some((Position::new(0, 0), Position::new(1, 1))),
+        }
+    }
+
+    #[test]
+    fn test_probe_and_store() {
+        let mut table = TranspositionTable::new(100, ReplacementPolicy::PreferDeeper);
+        assert_eq!(128, table.get_capacity());
+
+        assert_eq!(None, table.probe(7));
+        table.store(create_entry(7, 3));
+        assert_eq!(Some(create_entry(7, 3)), table.probe(7));
+
+        // Same slot, different position.
+        assert_eq!(None, table.probe(7 + 128));
+
+        let stats = table.get_stats();
+        assert_eq!((1, 2, 1), (stats.hits, stats.misses, stats.stores));
+        assert_eq!(1.0 / 3.0, stats.get_hit_rate());
+    }
+
+    #[test]
+    fn test_replacement_policies() {
+        let mut table = TranspositionTable::new(16, ReplacementPolicy::PreferDeeper);
+        table.store(create_entry(1, 5));
+        table.store(create_entry(17, 2));
+        assert_eq!(Some(create_entry(1, 5)), table.probe(1));
+        assert_eq!(1, table.get_stats().rejections);
+
+        table.new_search();
+        table.store(create_entry(17, 2));
+        assert_eq!(Some(create_entry(17, 2)), table.probe(17));
+        assert_eq!(1, table.get_stats().replacements);
+
+        let mut table = TranspositionTable::new(16, ReplacementPolicy::Always);
+        table.store(create_entry(1, 5));
+        table.store(create_entry(17, 2));
+        assert_eq!(None, table.probe(1));
+        assert_eq!(Some(create_entry(17, 2)), table.probe(17));
+    }
+}
@@ -0,0 +1,250 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use rand::prelude::{Rng, SeedableRng, StdRng};
+
+use crate::bot::{Bot, BotPair, GreedyBot};
+use crate::evaluation::{HeuristicEvaluator, Weights};
+use crate::game::{Game, Rules};
+
+/// Settings of a self-play tuning run, with the gains of SPSA (simultaneous perturbation
+/// stochastic approximation).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TuningConfig {
+    pub iterations: usize,
+    /// Games played by every match, half of them with each side playing X.
+    pub games_per_match: usize,
+    /// How far the weights move along the estimated gradient in the first iteration.
+    pub learning_rate: f32,
+    /// How far the weights are perturbed in the first iteration to estimate the gradient.
+    pub perturbation: f32,
+    pub seed: u64,
+}
+
+impl Default for TuningConfig {
+    fn default() -> Self {
+        Self {
+            iterations: 100,
+            games_per_match: 100,
+            learning_rate: 0.005,
+            perturbation: 0.05,
+            seed: 0,
+        }
+    }
+}
+
+/// The weights after an iteration, and how they fare against the starting weights.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TuningStep {
+    pub iteration: usize,
+    pub weights: Weights,
+    /// The mean score of a greedy bot with these weights against one with the starting
+    /// weights, as its score minus the opponent's.
+    pub fitness: f32,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct TuningResult {
+    /// The fittest weights, or the starting weights at iteration 0 if none beat them.
+    pub best: TuningStep,
+    pub learning_curve: Vec<TuningStep>,
+}
+
+impl TuningResult {
+    /// Writes the learning curve as CSV, one row per iteration.
+    pub fn save_learning_curve(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut csv = String::from(
+            "iteration,fitness,open_lines,threatened_lines,entanglement,collapse_control\n",
+        );
+
+        for step in &self.learning_curve {
+            let weights = &step.weights;
+            csv += &format!(
+                "{},{},{},{},{},{}\n",
+                step.iteration,
+                step.fitness,
+                weights.open_lines,
+                weights.threatened_lines,
+                weights.entanglement,
+                weights.collapse_control
+            );
+        }
+
+        fs::write(path, csv)
+    }
+}
+
+// The weights tuned by self-play. The tuner's own games use the default rules, where cycles
+// collapse at random, so `collapse_control` has no effect on them and is kept as is.
+fn get_tuned_weights(weights: &Weights) -> [f32; 3] {
+    [
+        weights.open_lines,
+        weights.threatened_lines,
+        weights.entanglement,
+    ]
+}
+
+fn with_tuned_weights(weights: &Weights, tuned: [f32; 3]) -> Weights {
+    Weights {
+        open_lines: tuned[0],
+        threatened_lines: tuned[1],
+        entanglement: tuned[2],
+        ..*weights
+    }
+}
+
+/// The mean score of a greedy bot with the first weights against one with the second weights,
+/// as its score minus the opponent's, over games alternating who plays X.
+pub fn play_match(weights: &Weights, opponent: &Weights, games: usize, seed: u64) -> f32 {
+    let mut total = 0.0;
+
+    for game_index in 0..games as u64 {
+        let game_seed = seed.wrapping_mul(1_000_003).wrapping_add(game_index);
+        let bot = GreedyBot::new(
+            HeuristicEvaluator::new(Rules::RandomCollapse, *weights),
+            game_seed,
+        );
+        let opponent = GreedyBot::new(
+            HeuristicEvaluator::new(Rules::RandomCollapse, *opponent),
+            game_seed + 1,
+        );
+
+        let (score_x, score_o) = if game_index % 2 == 0 {
+            play_game(
+                BotPair {
+                    bot_x: bot,
+                    bot_o: opponent,
+                },
+                game_seed,
+            )
+        } else {
+            let (score_x, score_o) = play_game(
+                BotPair {
+                    bot_x: opponent,
+                    bot_o: bot,
+                },
+                game_seed,
+            );
+            (score_o, score_x)
+        };
+
+        total += score_x - score_o;
+    }
+
+    total / games.max(1) as f32
+}
+
+fn play_game<X, O>(bots: BotPair<X, O>, seed: u64) -> (f32, f32)
+where
+    X: Bot,
+    O: Bot,
+{
+    let mut game = Game::new(bots, seed);
+    game.set_verbose(false);
+    game.play_whole_game();
+
+    game.get_board().get_score()
+}
+
+/// Tunes the weights of the greedy bot by self-play with SPSA: every iteration, a match
+/// between two randomly perturbed copies of the weights estimates the gradient, and another
+/// match against the starting weights measures the fitness of the new weights.
+pub fn tune(start: Weights, config: &TuningConfig) -> TuningResult {
+    let mut rng = StdRng::seed_from_u64(config.seed);
+    let mut tuned = get_tuned_weights(&start);
+
+    let mut best = TuningStep {
+        iteration: 0,
+        weights: start,
+        fitness: 0.0,
+    };
+    let mut learning_curve = vec![];
+
+    for iteration in 1..=config.iterations {
+        // The usual SPSA gain sequences, decaying slowly enough to keep exploring.
+        let learning_rate = config.learning_rate * (11.0 / (iteration as f32 + 10.0)).powf(0.602);
+        let perturbation = config.perturbation / (iteration as f32).powf(0.101);
+
+        let directions: [f32; 3] = std::array::from_fn(|_| if rng.random() { 1.0 } else { -1.0 });
+
+        let plus = with_tuned_weights(
+            &start,
+            std::array::from_fn(|i| tuned[i] + perturbation * directions[i]),
+        );
+        let minus = with_tuned_weights(
+            &start,
+            std::array::from_fn(|i| tuned[i] - perturbation * directions[i]),
+        );
+
+        let difference = play_match(&plus, &minus, config.games_per_match, rng.random());
+
+        for (weight, direction) in tuned.iter_mut().zip(directions) {
+            *weight += learning_rate * difference / (2.0 * perturbation) * direction;
+        }
+
+        let weights = with_tuned_weights(&start, tuned);
+        let step = TuningStep {
+            iteration,
+            weights,
+            fitness: play_match(&weights, &start, config.games_per_match, rng.random()),
+        };
+
+        if step.fitness > best.fitness {
+            best = step;
+        }
+        learning_curve.push(step);
+    }
+
+    TuningResult {
+        best,
+        learning_curve,
+    }
+}
+
+#[cfg(test)]
+mod test_tuning {
+    use super::*;
+
+    #[test]
+    fn test_play_match() {
+        let weights = Weights::default();
+        assert_eq!(
+            play_match(&weights, &weights, 4, 7),
+            play_match(&weights, &weights, 4, 7)
+        );
+
+        // Weights that prefer the opponent's lines lose to the default weights.
+        let bad_weights = Weights {
+            open_lines: -1.0,
+            threatened_lines: -1.0,
+            ..weights
+        };
+        assert!(play_match(&weights, &bad_weights, 10, 0) > 0.0);
+    }
+
+    #[test]
+    fn test_tune() {
+        let config = TuningConfig {
+            iterations: 3,
+            games_per_match: 4,
+            ..TuningConfig::default()
+        };
+        let result = tune(Weights::default(), &config);
+
+        assert_eq!(3, result.learning_curve.len());
+        assert!(result
+            .learning_curve
+            .iter()
+            .all(|step| step.fitness <= result.best.fitness));
+        assert!(result
+            .learning_curve
+            .iter()
+            .all(|step| step.weights.collapse_control == 0.5));
+
+        let directory = tempfile::tempdir().unwrap();
+        let path = directory.path().join("learning_curve.csv");
+        result.save_learning_curve(&path).unwrap();
+        assert_eq!(4, fs::read_to_string(&path).unwrap().lines().count());
+    }
+}
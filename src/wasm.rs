@@ -0,0 +1,308 @@
+use rand::prelude::{SeedableRng, StdRng};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use wasm_bindgen::prelude::{wasm_bindgen, JsError};
+
+use crate::board::{Board, MoveError, Position, SpookyMark, Token};
+use crate::bot::{create_bot, Bot};
+use crate::game::Rules;
+
+#[derive(Deserialize)]
+struct MoveRequest {
+    token: Token,
+    positions: [Position; 2],
+}
+
+#[derive(Serialize)]
+struct GameState<'a> {
+    rules: Rules,
+    board: &'a Board,
+    to_move: Option<Token>,
+    pending_collapse: Option<SpookyMark>,
+    legal_moves: Vec<(Position, Position)>,
+    score: (f32, f32),
+    game_over: bool,
+}
+
+#[derive(Deserialize)]
+struct SavedGame {
+    rules: Rules,
+    board: Board,
+}
+
+fn to_js_error(error: String) -> JsError {
+    JsError::new(&error)
+}
+
+fn move_error_to_json(move_error: MoveError) -> String {
+    let mut error = serde_json::to_value(move_error).unwrap();
+    error["message"] = move_error.to_string().into();
+
+    error.to_string()
+}
+
+/// A whole game for a browser frontend, with moves and states exchanged as JSON strings.
+///
+/// Errors are thrown as JS errors whose message is the JSON of the error, such as
+/// `{"kind": "occupied", "details": {"row": 0, "column": 0}, "message": "..."}`.
+#[wasm_bindgen]
+pub struct WasmGame {
+    rules: Rules,
+    board: Board,
+    rng: StdRng,
+}
+
+impl WasmGame {
+    fn create(rules: &str, seed: u64) -> Result<Self, String> {
+        Ok(Self {
+            rules: serde_json::from_value(rules.into()).map_err(|e| e.to_string())?,
+            board: Board::new(),
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    fn load(json: &str, seed: u64) -> Result<Self, String> {
+        let saved: SavedGame = serde_json::from_str(json).map_err(|e| e.to_string())?;
+
+        Ok(Self {
+            rules: saved.rules,
+            board: saved.board,
+            rng: StdRng::seed_from_u64(seed),
+        })
+    }
+
+    fn get_state(&self) -> String {
+        let game_over = self.board.is_game_over();
+        let pending_collapse = self.board.get_pending_collapse();
+
+        let legal_moves = if game_over || pending_collapse.is_some() {
+            vec![]
+        } else {
+            self.board.get_legal_moves()
+        };
+
+        serde_json::to_string(&GameState {
+            rules: self.rules,
+            board: &self.board,
+            to_move: (!game_over).then(|| self.board.get_current_token()),
+            pending_collapse,
+            legal_moves,
+            score: self.board.get_score(),
+            game_over,
+        })
+        .unwrap()
+    }
+
+    fn do_turn(
+        &mut self,
+        position_1: Position,
+        position_2: Position,
+        token: Token,
+    ) -> Result<String, String> {
+        self.board
+            .try_do_turn(position_1, position_2, token)
+            .map_err(move_error_to_json)?;
+
+        if self.rules == Rules::RandomCollapse {
+            self.board.collapse_loop(&mut self.rng);
+        }
+
+        Ok(self.get_state())
+    }
+
+    fn try_play_move(&mut self, move_json: &str) -> Result<String, String> {
+        let request: MoveRequest = serde_json::from_str(move_json).map_err(|e| e.to_string())?;
+        let [position_1, position_2] = request.positions;
+
+        self.do_turn(position_1, position_2, request.token)
+    }
+
+    fn try_collapse(&mut self, position_json: &str) -> Result<String, String> {
+        let position: Position = serde_json::from_str(position_json).map_err(|e| e.to_string())?;
+
+        self.board.collapse(position).map_err(move_error_to_json)?;
+
+        Ok(self.get_state())
+    }
+
+    fn try_play_bot_move(&mut self, bot: &mut WasmBot) -> Result<String, String> {
+        // Bots expect a board with legal moves.
+        self.board.check_can_move().map_err(move_error_to_json)?;
+
+        let token = self.board.get_current_token();
+        let (position_1, position_2) = bot.bot.get_next_move(&self.board, token);
+
+        self.do_turn(position_1, position_2, token)
+    }
+}
+
+#[wasm_bindgen]
+impl WasmGame {
+    /// Starts a game with the given rules (`"random_collapse"` or `"player_collapse"`), where
+    /// the seed drives the random collapses.
+    #[wasm_bindgen(constructor)]
+    pub fn new(rules: &str, seed: u64) -> Result<WasmGame, JsError> {
+        Self::create(rules, seed).map_err(to_js_error)
+    }
+
+    /// Restores a game saved with `save`.
+    #[wasm_bindgen(js_name = fromJson)]
+    pub fn from_json(json: &str, seed: u64) -> Result<WasmGame, JsError> {
+        Self::load(json, seed).map_err(to_js_error)
+    }
+
+    pub fn save(&self) -> String {
+        json!({ "rules": self.rules, "board": self.board }).to_string()
+    }
+
+    /// The board, whose turn it is, the pending collapse, the legal moves and the score.
+    pub fn state(&self) -> String {
+        self.get_state()
+    }
+
+    /// Plays `{"token": "X", "positions": [{"row": 0, "column": 0}, {"row": 1, "column": 1}]}`.
+    #[wasm_bindgen(js_name = playMove)]
+    pub fn play_move(&mut self, move_json: &str) -> Result<String, JsError> {
+        self.try_play_move(move_json).map_err(to_js_error)
+    }
+
+    /// Resolves the pending cycle by putting its spooky mark in `{"row": 0, "column": 0}`.
+    pub fn collapse(&mut self, position_json: &str) -> Result<String, JsError> {
+        self.try_collapse(position_json).map_err(to_js_error)
+    }
+
+    /// Lets the bot play the move of whoever's turn it is.
+    #[wasm_bindgen(js_name = playBotMove)]
+    pub fn play_bot_move(&mut self, bot: &mut WasmBot) -> Result<String, JsError> {
+        self.try_play_bot_move(bot).map_err(to_js_error)
+    }
+}
+
+/// One of the crate's bots, to play against in the browser.
+#[wasm_bindgen]
+pub struct WasmBot {
+    bot: Box<dyn Bot>,
+}
+
+impl WasmBot {
+    fn create(name: &str, seed: u64) -> Result<Self, String> {
+        let bot = create_bot(name, seed).ok_or_else(|| format!("Unknown bot {name}"))?;
+
+        Ok(Self { bot })
+    }
+}
+
+#[wasm_bindgen]
+impl WasmBot {
+    #[wasm_bindgen(constructor)]
+    pub fn new(name: &str, seed: u64) -> Result<WasmBot, JsError> {
+        Self::create(name, seed).map_err(to_js_error)
+    }
+}
+
+#[cfg(test)]
+mod test_wasm_game {
+    use super::*;
+
+    #[test]
+    fn test_play_and_collapse() {
+        let mut game = WasmGame::create("player_collapse", 0).unwrap();
+
+        game.try_play_move(
+            r#"{"token": "X", "positions": [{"row": 0, "column": 0}, {"row": 1, "column": 1}]}"#,
+        )
+        .unwrap();
+        let state = game
+            .try_play_move(
+                r#"{"token": "O", "positions": [{"row": 1, "column": 1}, {"row": 0, "column": 0}]}"#,
+            )
+            .unwrap();
+        assert!(state.contains(r#""pending_collapse":["#));
+
+        let error = game
+            .try_play_move(
+                r#"{"token": "X", "positions": [{"row": 2, "column": 2}, {"row": 2, "column": 1}]}"#,
+            )
+            .unwrap_err();
+        assert!(error.contains(r#""kind":"collapse_pending""#));
+
+        let state = game.try_collapse(r#"{"row": 0, "column": 0}"#).unwrap();
+        assert!(state.contains(r#""pending_collapse":null"#));
+    }
+
+    #[test]
+    fn test_bots_are_reproducible() {
+        let play = |seed: u64| {
+            let mut game = WasmGame::create("random_collapse", seed).unwrap();
+            let mut bot = WasmBot::create("random", seed).unwrap();
+
+            let mut state = game.get_state();
+            while !game.board.is_game_over() {
+                state = game.try_play_bot_move(&mut bot).unwrap();
+            }
+
+            state
+        };
+
+        assert_eq!(play(7), play(7));
+        assert!(WasmBot::create("unknown", 7).is_err());
+    }
+
+    #[test]
+    fn test_bot_move_without_legal_moves() {
+        let mut game = WasmGame::create("player_collapse", 0).unwrap();
+        let mut bot = WasmBot::create("greedy", 0).unwrap();
+        game.try_play_move(
+            r#"{"token": "X", "positions": [{"row": 0, "column": 0}, {"row": 1, "column": 1}]}"#,
+        )
+        .unwrap();
+        game.try_play_move(
+            r#"{"token": "O", "positions": [{"row": 1, "column": 1}, {"row": 0, "column": 0}]}"#,
+        )
+        .unwrap();
+
+        let error = game.try_play_bot_move(&mut bot).unwrap_err();
+        assert!(error.contains(r#""kind":"collapse_pending""#));
+
+        game.try_collapse(r#"{"row": 0, "column": 0}"#).unwrap();
+        while !game.board.is_game_over() {
+            game.try_play_bot_move(&mut bot).unwrap();
+            if let Some(spooky_mark) = game.board.get_pending_collapse() {
+                game.board.collapse(spooky_mark.0).unwrap();
+            }
+        }
+
+        let error = game.try_play_bot_move(&mut bot).unwrap_err();
+        assert!(error.contains(r#""kind":"game_over""#));
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let mut game = WasmGame::create("player_collapse", 0).unwrap();
+        game.try_play_move(
+            r#"{"token": "X", "positions": [{"row": 0, "column": 0}, {"row": 1, "column": 1}]}"#,
+        )
+        .unwrap();
+
+        let loaded = WasmGame::load(&game.save(), 0).unwrap();
+
+        assert_eq!(game.get_state(), loaded.get_state());
+    }
+
+    #[test]
+    fn test_load_rejects_unreachable_boards() {
+        let empty = "[[null,null,null],[null,null,null],[null,null,null]]";
+
+        for (turn, spooky_marks) in [
+            (0, ""),
+            (2, r#"[{"row":0,"column":0},{"row":0,"column":1},{"X":12}]"#),
+        ] {
+            let json = format!(
+                r#"{{"rules":"random_collapse","board":{{"board":{empty},"turn":{turn},"spooky_marks":[{spooky_marks}]}}}}"#
+            );
+
+            let error = WasmGame::load(&json, 0).err().unwrap();
+            assert!(error.contains("Invalid board"), "{error}");
+        }
+    }
+}